@@ -72,6 +72,9 @@ else
     exit 1
 fi
 
+echo ""
+"$(dirname "$0")/write-binary-checksums.sh" "$BINARIES_DIR"
+
 echo ""
 echo "✅ Done! Binaries downloaded to $BINARIES_DIR:"
 ls -la "$BINARIES_DIR"
@@ -0,0 +1,25 @@
+#!/bin/bash
+# Write src-tauri/binaries/checksums.sha256 for the sidecars in that folder.
+# The app verifies bundled binaries against this manifest before running them.
+
+set -e
+
+BINARIES_DIR="${1:-src-tauri/binaries}"
+MANIFEST="checksums.sha256"
+
+if command -v sha256sum >/dev/null 2>&1; then
+    HASH_CMD="sha256sum"
+else
+    HASH_CMD="shasum -a 256"
+fi
+
+cd "$BINARIES_DIR"
+rm -f "$MANIFEST" "$MANIFEST.tmp"
+touch "$MANIFEST.tmp"
+for file in yt-dlp-* ffmpeg-* ffprobe-*; do
+    [ -f "$file" ] && $HASH_CMD "$file" >> "$MANIFEST.tmp"
+done
+mv "$MANIFEST.tmp" "$MANIFEST"
+
+echo "🔒 Wrote $BINARIES_DIR/$MANIFEST:"
+cat "$MANIFEST"
@@ -1,3 +1,21 @@
 fn main() {
+    // Release builds refuse to compile without these (see license.rs)
+    println!("cargo:rerun-if-env-changed=CLIPME_LICENSE_URL");
+    println!("cargo:rerun-if-env-changed=CLIPME_LICENSE_PUBLIC_KEY");
+    println!("cargo::rustc-check-cfg=cfg(clipme_license_configured)");
+    let configured = ["CLIPME_LICENSE_URL", "CLIPME_LICENSE_PUBLIC_KEY"]
+        .iter()
+        .all(|name| std::env::var(name).is_ok_and(|value| !value.trim().is_empty()));
+    if configured {
+        println!("cargo:rustc-cfg=clipme_license_configured");
+    }
+
+    // Release builds also need yt-dlp's signing key to verify updates (see ytdlp_update.rs)
+    println!("cargo:rerun-if-env-changed=CLIPME_YTDLP_SIGNING_KEY");
+    println!("cargo::rustc-check-cfg=cfg(clipme_ytdlp_key_configured)");
+    if std::env::var("CLIPME_YTDLP_SIGNING_KEY").is_ok_and(|key| !key.trim().is_empty()) {
+        println!("cargo:rustc-cfg=clipme_ytdlp_key_configured");
+    }
+
     tauri_build::build()
 }
@@ -0,0 +1,414 @@
+use serde::{Deserialize, Serialize};
+use std::io::BufReader;
+use std::path::Path;
+use std::process::Stdio;
+use tauri::{AppHandle, Emitter};
+
+use crate::{
+    get_ffmpeg, get_ffprobe, probe_local_file, read_until_delimiter, timecode, validation,
+    ClipSegment,
+};
+
+/// Scene and silence candidates closer than this are reported as one boundary
+const MERGE_WINDOW_SECS: f64 = 1.0;
+
+/// Tunable thresholds for `analyze_cut_points`
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct AnalysisOptions {
+    /// ffmpeg scene score (0-1) above which a frame starts a new scene
+    scene_threshold: f64,
+    /// Level in dB below which audio counts as silence
+    silence_noise_db: f64,
+    /// Minimum silence length in seconds
+    silence_min_duration: f64,
+    /// Segments shorter than this are merged into their neighbour when splitting
+    min_segment_duration: f64,
+    /// Produce `ClipSegment`s by splitting on these boundaries
+    split: Option<SplitMode>,
+}
+
+impl Default for AnalysisOptions {
+    fn default() -> Self {
+        AnalysisOptions {
+            scene_threshold: 0.4,
+            silence_noise_db: -30.0,
+            silence_min_duration: 0.5,
+            min_segment_duration: 5.0,
+            split: None,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SplitMode {
+    Silence,
+    Scenes,
+    Both,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CandidateKind {
+    Scene,
+    Silence,
+    SceneAndSilence,
+}
+
+/// A suggested cut point. `score` is 0-1, higher is a more natural boundary.
+#[derive(Serialize, Debug, Clone)]
+pub struct CutCandidate {
+    time: f64,
+    score: f64,
+    kind: CandidateKind,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SilenceSpan {
+    start: f64,
+    end: f64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct AnalysisResult {
+    duration: f64,
+    candidates: Vec<CutCandidate>,
+    silences: Vec<SilenceSpan>,
+    segments: Vec<ClipSegment>,
+}
+
+#[derive(Clone, Serialize, Debug)]
+struct AnalysisProgress {
+    request_id: u64,
+    percent: f64,
+}
+
+/// Parse scene scores and silence spans from ffmpeg's stderr
+#[derive(Default)]
+struct DetectionParser {
+    scenes: Vec<(f64, f64)>,
+    silences: Vec<SilenceSpan>,
+    pending_scene_time: Option<f64>,
+    pending_silence_start: Option<f64>,
+}
+
+impl DetectionParser {
+    fn feed(&mut self, line: &str) {
+        // metadata=print emits "frame:N pts:P pts_time:T" followed by the score
+        if let Some(rest) = line.split("pts_time:").nth(1) {
+            self.pending_scene_time = rest.split_whitespace().next().and_then(|t| t.parse().ok());
+        } else if let Some(score) = line.split("lavfi.scene_score=").nth(1) {
+            if let (Some(time), Ok(score)) = (self.pending_scene_time.take(), score.trim().parse()) {
+                self.scenes.push((time, score));
+            }
+        } else if let Some(start) = line.split("silence_start:").nth(1) {
+            self.pending_silence_start = start.trim().parse().ok();
+        } else if let Some(end) = line.split("silence_end:").nth(1) {
+            let end = end.split('|').next().and_then(|e| e.trim().parse::<f64>().ok());
+            if let (Some(start), Some(end)) = (self.pending_silence_start.take(), end) {
+                self.silences.push(SilenceSpan {
+                    start: start.max(0.0),
+                    end,
+                });
+            }
+        }
+    }
+
+    /// Close a silence that runs until the end of the file
+    fn finish(&mut self, duration: f64) {
+        if let Some(start) = self.pending_silence_start.take() {
+            self.silences.push(SilenceSpan { start, end: duration });
+        }
+    }
+}
+
+/// Merge scene and silence boundaries into scored candidates.
+/// A silence scores by its length (2 s or longer is a full 1.0); a scene change
+/// that coincides with a silence is boosted since both signals agree.
+fn score_candidates(scenes: &[(f64, f64)], silences: &[SilenceSpan]) -> Vec<CutCandidate> {
+    let mut candidates: Vec<CutCandidate> = silences
+        .iter()
+        .map(|s| CutCandidate {
+            time: (s.start + s.end) / 2.0,
+            score: ((s.end - s.start) / 2.0).min(1.0),
+            kind: CandidateKind::Silence,
+        })
+        .collect();
+
+    for (time, score) in scenes {
+        let near_silence = candidates.iter_mut().find(|c| {
+            c.kind == CandidateKind::Silence && (c.time - time).abs() <= MERGE_WINDOW_SECS
+        });
+        match near_silence {
+            Some(candidate) => {
+                candidate.time = *time;
+                candidate.score = (candidate.score.max(*score) + 0.25).min(1.0);
+                candidate.kind = CandidateKind::SceneAndSilence;
+            }
+            None => candidates.push(CutCandidate {
+                time: *time,
+                score: score.clamp(0.0, 1.0),
+                kind: CandidateKind::Scene,
+            }),
+        }
+    }
+
+    candidates.sort_by(|a, b| a.time.total_cmp(&b.time));
+    candidates
+}
+
+/// Turn boundaries into clip segments. Silence splitting keeps only the audible
+/// parts; scene splitting cuts at each change. Short pieces are merged forward.
+fn split_segments(
+    mode: SplitMode,
+    duration: f64,
+    candidates: &[CutCandidate],
+    silences: &[SilenceSpan],
+    min_duration: f64,
+) -> Vec<ClipSegment> {
+    let mut ranges: Vec<(f64, f64)> = Vec::new();
+
+    if mode == SplitMode::Silence {
+        let mut cursor = 0.0;
+        for silence in silences {
+            if silence.start > cursor {
+                ranges.push((cursor, silence.start));
+            }
+            cursor = silence.end;
+        }
+        if duration > cursor {
+            ranges.push((cursor, duration));
+        }
+    } else {
+        let mut cuts: Vec<f64> = candidates
+            .iter()
+            .filter(|c| mode == SplitMode::Both || c.kind != CandidateKind::Silence)
+            .map(|c| c.time)
+            .filter(|t| *t > 0.0 && *t < duration)
+            .collect();
+        cuts.push(duration);
+        let mut start = 0.0;
+        for cut in cuts {
+            ranges.push((start, cut));
+            start = cut;
+        }
+    }
+
+    let mut merged: Vec<(f64, f64)> = Vec::new();
+    for (start, end) in ranges {
+        if mode == SplitMode::Silence {
+            // Short bursts between silences are noise rather than content
+            if end - start >= min_duration {
+                merged.push((start, end));
+            }
+            continue;
+        }
+        match merged.last_mut() {
+            Some(last) if last.1 - last.0 < min_duration => last.1 = end,
+            _ => merged.push((start, end)),
+        }
+    }
+    // A short trailing scene joins the one before it
+    if mode != SplitMode::Silence && merged.len() > 1 {
+        let (start, end) = merged[merged.len() - 1];
+        if end - start < min_duration {
+            merged.pop();
+            if let Some(last) = merged.last_mut() {
+                last.1 = end;
+            }
+        }
+    }
+
+    merged
+        .into_iter()
+        .enumerate()
+        .map(|(index, (start, end))| ClipSegment {
+            id: format!("auto-{}", index + 1),
+            start,
+            end,
+            label: None,
+        })
+        .collect()
+}
+
+/// Run ffmpeg scene detection and silencedetect over a local file (or downloaded
+/// preview/proxy) and return scored cut candidates, optionally split into segments.
+/// Progress is emitted as `analysis-progress` events.
+#[tauri::command]
+pub async fn analyze_cut_points(
+    app: AppHandle,
+    source: String,
+    options: Option<AnalysisOptions>,
+    request_id: u64,
+) -> Result<AnalysisResult, String> {
+    let options = options.unwrap_or_default();
+    let source = validation::validate_source(&app, &source)?.as_arg();
+    if !Path::new(&source).is_file() {
+        return Err("Analysis needs a local file or downloaded preview".to_string());
+    }
+
+    let probe = probe_local_file(&get_ffprobe(&app)?, &source)?;
+    let duration = probe["format"]["duration"]
+        .as_str()
+        .and_then(|s| s.parse::<f64>().ok())
+        .unwrap_or(0.0);
+    let streams = probe["streams"].as_array().cloned().unwrap_or_default();
+    let has_stream = |kind: &str| streams.iter().any(|s| s["codec_type"].as_str() == Some(kind));
+    let has_video = has_stream("video");
+    let has_audio = has_stream("audio");
+    if !has_video && !has_audio {
+        return Err("Source has no audio or video to analyze".to_string());
+    }
+
+    log::debug!("Analyzing cut points for {}", source);
+
+    let mut args: Vec<String> = vec!["-hide_banner".into(), "-nostdin".into(), "-i".into(), source.clone()];
+    if has_video {
+        // Scene scores are just as good on a small copy and much faster
+        args.extend([
+            "-map".into(),
+            "0:v:0".into(),
+            "-vf".into(),
+            format!(
+                "scale=320:-2,select='gt(scene,{})',metadata=print:key=lavfi.scene_score",
+                options.scene_threshold
+            ),
+        ]);
+    }
+    if has_audio {
+        args.extend([
+            "-map".into(),
+            "0:a:0".into(),
+            "-af".into(),
+            format!(
+                "silencedetect=n={}dB:d={}",
+                options.silence_noise_db, options.silence_min_duration
+            ),
+        ]);
+    }
+    args.extend(["-f".into(), "null".into(), "-".into()]);
+
+    let mut child = get_ffmpeg(&app)?
+        .command()
+        .args(&args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to start ffmpeg: {}", e))?;
+
+    let mut parser = DetectionParser::default();
+    if let Some(stderr) = child.stderr.take() {
+        for line in read_until_delimiter(BufReader::new(stderr), b'\r', b'\n') {
+            if duration > 0.0 && line.contains("time=") && line.contains("speed=") {
+                let current = line
+                    .split_whitespace()
+                    .find_map(|part| part.strip_prefix("time="))
+                    .and_then(|t| timecode::parse_timecode(t, None).ok());
+                if let Some(current) = current {
+                    let _ = app.emit(
+                        "analysis-progress",
+                        AnalysisProgress {
+                            request_id,
+                            percent: (current / duration * 100.0).min(100.0),
+                        },
+                    );
+                }
+                continue;
+            }
+            parser.feed(&line);
+        }
+    }
+
+    let status = child
+        .wait()
+        .map_err(|e| format!("Failed to wait on ffmpeg: {}", e))?;
+    if !status.success() {
+        return Err("Cut point analysis failed".to_string());
+    }
+    parser.finish(duration);
+
+    let candidates = score_candidates(&parser.scenes, &parser.silences);
+    let segments = match options.split {
+        Some(mode) => split_segments(
+            mode,
+            duration,
+            &candidates,
+            &parser.silences,
+            options.min_segment_duration,
+        ),
+        None => Vec::new(),
+    };
+
+    Ok(AnalysisResult {
+        duration,
+        candidates,
+        silences: parser.silences,
+        segments,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STDERR: &str = "\
+[Parsed_metadata_1 @ 0x55d1c0] frame:0    pts:115    pts_time:4.8
+[Parsed_metadata_1 @ 0x55d1c0] lavfi.scene_score=0.512000
+[silencedetect @ 0x55d1c4] silence_start: 9.5
+[Parsed_metadata_1 @ 0x55d1c0] frame:1    pts:264    pts_time:11
+[Parsed_metadata_1 @ 0x55d1c0] lavfi.scene_score=0.600000
+[silencedetect @ 0x55d1c4] silence_end: 12.25 | silence_duration: 2.75
+[silencedetect @ 0x55d1c4] silence_start: 28";
+
+    fn parse() -> DetectionParser {
+        let mut parser = DetectionParser::default();
+        STDERR.lines().for_each(|line| parser.feed(line));
+        parser.finish(30.0);
+        parser
+    }
+
+    fn ranges(segments: &[ClipSegment]) -> Vec<(f64, f64)> {
+        segments.iter().map(|s| (s.start, s.end)).collect()
+    }
+
+    #[test]
+    fn parses_scene_scores_and_silences() {
+        let parser = parse();
+        assert_eq!(parser.scenes, [(4.8, 0.512), (11.0, 0.6)]);
+        let silences: Vec<(f64, f64)> = parser.silences.iter().map(|s| (s.start, s.end)).collect();
+        assert_eq!(silences, [(9.5, 12.25), (28.0, 30.0)]);
+    }
+
+    #[test]
+    fn merges_scenes_that_fall_inside_a_silence() {
+        let parser = parse();
+        let candidates = score_candidates(&parser.scenes, &parser.silences);
+        let summary: Vec<(f64, f64, CandidateKind)> = candidates
+            .iter()
+            .map(|c| (c.time, c.score, c.kind))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (4.8, 0.512, CandidateKind::Scene),
+                (11.0, 1.0, CandidateKind::SceneAndSilence),
+                (29.0, 1.0, CandidateKind::Silence),
+            ]
+        );
+    }
+
+    #[test]
+    fn splits_on_silence_or_scenes() {
+        let parser = parse();
+        let candidates = score_candidates(&parser.scenes, &parser.silences);
+        let split = |mode| split_segments(mode, 30.0, &candidates, &parser.silences, 5.0);
+
+        assert_eq!(ranges(&split(SplitMode::Silence)), [(0.0, 9.5), (12.25, 28.0)]);
+        // The 4.8 s opening scene is too short and joins the next one
+        assert_eq!(ranges(&split(SplitMode::Scenes)), [(0.0, 11.0), (11.0, 30.0)]);
+        // The 1 s tail after the final silence joins the scene before it
+        assert_eq!(ranges(&split(SplitMode::Both)), [(0.0, 11.0), (11.0, 30.0)]);
+        assert_eq!(split(SplitMode::Both)[0].id, "auto-1");
+    }
+}
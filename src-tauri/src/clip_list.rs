@@ -0,0 +1,1010 @@
+//! Clip lists shared between people: history entries or the current session
+//! exported as JSON, CSV or a CMX3600 EDL, and imported back from those plus
+//! YouTube timestamp comments and plain `url start end` lines.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+use tauri_plugin_dialog::DialogExt;
+
+use crate::history::{self, DownloadHistoryItem, JobParams};
+use crate::{timecode, validation, ClipSegment};
+
+const JSON_VERSION: u32 = 1;
+const CSV_COLUMNS: &[&str] = &["url", "title", "start", "end", "label"];
+/// EDL record timecodes conventionally start at one hour
+const EDL_RECORD_START: f64 = 3600.0;
+const EDL_REEL: &str = "AX";
+/// Anything bigger isn't a clip list
+const MAX_IMPORT_BYTES: u64 = 10 * 1024 * 1024;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ClipListFormat {
+    Json,
+    Csv,
+    Edl,
+    /// `0:00 Intro` lines as posted in YouTube comments and descriptions (import only)
+    Timestamps,
+    /// `url start end [label]` per line (import only)
+    Lines,
+}
+
+/// One source and the ranges to cut from it
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ClipListJob {
+    pub url: String,
+    #[serde(default)]
+    pub title: String,
+    pub segments: Vec<ClipSegment>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonClipList {
+    version: u32,
+    jobs: Vec<ClipListJob>,
+}
+
+/// What to export
+#[derive(Deserialize, Debug)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ExportSource {
+    /// History entries by id, in the given order
+    History { ids: Vec<String> },
+    /// The clips currently being edited
+    Session { jobs: Vec<ClipListJob> },
+}
+
+/// A clip list to import, from pasted text or a file picked in a dialog
+#[derive(Deserialize, Debug, Default)]
+pub struct ImportRequest {
+    /// Pasted clip list; when absent the user is asked for a file
+    #[serde(default)]
+    text: Option<String>,
+    /// Detected from the extension and content when not given
+    #[serde(default)]
+    format: Option<ClipListFormat>,
+    /// Source for formats that don't name one (timestamps)
+    #[serde(default)]
+    url: Option<String>,
+    #[serde(default)]
+    title: Option<String>,
+    /// Ends the last timestamp
+    #[serde(default, deserialize_with = "timecode::deserialize_optional_seconds")]
+    duration: Option<f64>,
+}
+
+/// A line or job that was left out, and why
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ImportIssue {
+    /// 1-based line in the input, when the problem is tied to one
+    line: Option<usize>,
+    message: String,
+}
+
+/// Jobs ready to validate and queue
+#[derive(Serialize, Debug)]
+pub struct ClipListImport {
+    format: ClipListFormat,
+    jobs: Vec<ClipListJob>,
+    issues: Vec<ImportIssue>,
+}
+
+fn issue(line: usize, message: impl Into<String>) -> ImportIssue {
+    ImportIssue {
+        line: Some(line),
+        message: message.into(),
+    }
+}
+
+fn segment(index: usize, start: f64, end: f64, label: Option<String>) -> ClipSegment {
+    ClipSegment {
+        id: format!("import-{}", index + 1),
+        start,
+        end,
+        label: label.filter(|label| !label.is_empty()),
+    }
+}
+
+/// Append a segment, starting a new job when the source changes
+fn push_segment(jobs: &mut Vec<ClipListJob>, url: &str, title: &str, mut segment: ClipSegment) {
+    let count: usize = jobs.iter().map(|job| job.segments.len()).sum();
+    segment.id = format!("import-{}", count + 1);
+    match jobs.last_mut() {
+        Some(job) if job.url == url && (title.is_empty() || job.title == title) => {
+            job.segments.push(segment)
+        }
+        _ => jobs.push(ClipListJob {
+            url: url.to_string(),
+            title: title.to_string(),
+            segments: vec![segment],
+        }),
+    }
+}
+
+/// Seconds with millisecond precision and no trailing zeros
+fn seconds_text(seconds: f64) -> String {
+    let text = format!("{:.3}", seconds);
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+// ---------------------------------------------------------------------------
+// Export
+// ---------------------------------------------------------------------------
+
+/// Group history entries into jobs. Entries of one multi-clip job stay together;
+/// a failed multi-clip job has no range of its own and exports all its segments.
+pub fn jobs_from_history(items: &[DownloadHistoryItem]) -> Vec<ClipListJob> {
+    let mut jobs: Vec<(String, ClipListJob)> = Vec::new();
+    for item in items {
+        let (title, segments) = match (&item.job, item.start_time, item.end_time) {
+            (Some(JobParams::MultiClip(job)), Some(start), Some(end)) => {
+                (&job.title, vec![segment(0, start, end, None)])
+            }
+            (Some(JobParams::MultiClip(job)), _, _) => (&job.title, job.segments.clone()),
+            (Some(JobParams::Clip(job)), _, _) => {
+                (&job.title, vec![segment(0, job.start, job.end, None)])
+            }
+            (None, Some(start), Some(end)) => (&item.title, vec![segment(0, start, end, None)]),
+            // Old entries without a stored range can't be shared
+            (None, _, _) => continue,
+        };
+        let key = item.job_id.clone().unwrap_or_else(|| item.id.clone());
+        match jobs.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, job)) => job.segments.extend(segments),
+            None => jobs.push((
+                key,
+                ClipListJob {
+                    url: item.url.clone(),
+                    title: title.clone(),
+                    segments,
+                },
+            )),
+        }
+    }
+    jobs.into_iter().map(|(_, job)| job).collect()
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn export_csv(jobs: &[ClipListJob]) -> String {
+    let mut out = CSV_COLUMNS.join(",") + "\r\n";
+    for job in jobs {
+        for segment in &job.segments {
+            let row = [
+                csv_field(&job.url),
+                csv_field(&job.title),
+                seconds_text(segment.start),
+                seconds_text(segment.end),
+                csv_field(segment.label.as_deref().unwrap_or_default()),
+            ];
+            out.push_str(&row.join(","));
+            out.push_str("\r\n");
+        }
+    }
+    out
+}
+
+/// Comment lines can't hold line breaks
+fn edl_comment(value: &str) -> String {
+    value.replace(['\r', '\n'], " ")
+}
+
+/// CMX3600 with one cut per segment laid end to end on the record side. The
+/// source is named in `* SOURCE FILE:` comments, which most editors read.
+fn export_edl(jobs: &[ClipListJob]) -> String {
+    let fps = timecode::DEFAULT_FPS;
+    let tc = |seconds: f64| timecode::format_smpte(seconds, fps, false);
+    let mut out = String::from("TITLE: Clipme clips\r\nFCM: NON-DROP FRAME\r\n\r\n");
+    let mut record = EDL_RECORD_START;
+    let mut event = 0;
+    for job in jobs {
+        for segment in &job.segments {
+            event += 1;
+            let length = segment.end - segment.start;
+            out.push_str(&format!(
+                "{:03}  {:<8} V     C        {} {} {} {}\r\n",
+                event,
+                EDL_REEL,
+                tc(segment.start),
+                tc(segment.end),
+                tc(record),
+                tc(record + length)
+            ));
+            let name = segment.label.as_deref().unwrap_or(&job.title);
+            if !name.is_empty() {
+                out.push_str(&format!("* FROM CLIP NAME: {}\r\n", edl_comment(name)));
+            }
+            out.push_str(&format!("* SOURCE FILE: {}\r\n\r\n", edl_comment(&job.url)));
+            record += length;
+        }
+    }
+    out
+}
+
+pub fn export(jobs: &[ClipListJob], format: ClipListFormat) -> Result<String, String> {
+    match format {
+        ClipListFormat::Json => serde_json::to_string_pretty(&JsonClipList {
+            version: JSON_VERSION,
+            jobs: jobs.to_vec(),
+        })
+        .map_err(|e| format!("Failed to serialize clip list: {}", e)),
+        ClipListFormat::Csv => Ok(export_csv(jobs)),
+        ClipListFormat::Edl => Ok(export_edl(jobs)),
+        ClipListFormat::Timestamps | ClipListFormat::Lines => {
+            Err("This format can only be imported".to_string())
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Import
+// ---------------------------------------------------------------------------
+
+fn import_json(text: &str) -> Result<Vec<ClipListJob>, String> {
+    let value: serde_json::Value =
+        serde_json::from_str(text).map_err(|e| format!("Invalid clip list: {}", e))?;
+    // A bare array of jobs is accepted too
+    let jobs = if value.is_array() {
+        value
+    } else {
+        let version = value.get("version").and_then(|v| v.as_u64()).unwrap_or(1);
+        if version > JSON_VERSION as u64 {
+            return Err(format!(
+                "This clip list was made by a newer version (format {})",
+                version
+            ));
+        }
+        value.get("jobs").cloned().unwrap_or_default()
+    };
+    serde_json::from_value(jobs).map_err(|e| format!("Invalid clip list: {}", e))
+}
+
+/// RFC 4180 rows with their starting line numbers
+fn parse_csv(text: &str) -> Vec<(usize, Vec<String>)> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let (mut line, mut row_line) = (1, 1);
+    let mut in_quotes = false;
+    let mut chars = text.trim_start_matches('\u{feff}').chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' if in_quotes => in_quotes = false,
+            '"' if field.is_empty() => in_quotes = true,
+            ',' if !in_quotes => row.push(std::mem::take(&mut field)),
+            '\r' if !in_quotes => {}
+            '\n' if !in_quotes => {
+                row.push(std::mem::take(&mut field));
+                if row.iter().any(|f| !f.trim().is_empty()) {
+                    rows.push((row_line, std::mem::take(&mut row)));
+                }
+                row.clear();
+                line += 1;
+                row_line = line;
+            }
+            _ => {
+                if c == '\n' {
+                    line += 1;
+                }
+                field.push(c);
+            }
+        }
+    }
+    row.push(field);
+    if row.iter().any(|f| !f.trim().is_empty()) {
+        rows.push((row_line, row));
+    }
+    rows
+}
+
+fn import_csv(text: &str) -> (Vec<ClipListJob>, Vec<ImportIssue>) {
+    let mut rows = parse_csv(text).into_iter().peekable();
+    let has_header = rows
+        .peek()
+        .is_some_and(|(_, row)| row[0].trim().eq_ignore_ascii_case("url"));
+    let columns: Vec<String> = if has_header {
+        let (_, header) = rows.next().unwrap_or_default();
+        header.iter().map(|c| c.trim().to_lowercase()).collect()
+    } else if rows.peek().is_some_and(|(_, row)| row.len() == 3) {
+        vec!["url".into(), "start".into(), "end".into()]
+    } else {
+        CSV_COLUMNS.iter().map(|c| c.to_string()).collect()
+    };
+    let index = |name: &str| columns.iter().position(|c| c == name);
+
+    let (mut jobs, mut issues) = (Vec::new(), Vec::new());
+    let (Some(url_col), Some(start_col), Some(end_col)) =
+        (index("url"), index("start"), index("end"))
+    else {
+        issues.push(issue(1, "The header needs url, start and end columns"));
+        return (jobs, issues);
+    };
+    let cell = |row: &[String], col: Option<usize>| {
+        col.and_then(|c| row.get(c))
+            .map(|v| v.trim().to_string())
+            .unwrap_or_default()
+    };
+    for (line, row) in rows {
+        let range = parse_range(
+            &cell(&row, Some(start_col)),
+            &cell(&row, Some(end_col)),
+            None,
+        );
+        match range {
+            Ok((start, end)) => push_segment(
+                &mut jobs,
+                &cell(&row, Some(url_col)),
+                &cell(&row, index("title")),
+                segment(0, start, end, Some(cell(&row, index("label")))),
+            ),
+            Err(e) => issues.push(issue(line, e)),
+        }
+    }
+    (jobs, issues)
+}
+
+fn parse_range(start: &str, end: &str, fps: Option<f64>) -> Result<(f64, f64), String> {
+    let start = timecode::parse_timecode(start, fps)?;
+    let end = timecode::parse_timecode(end, fps)?;
+    if end <= start {
+        return Err(format!(
+            "Clip ends before it starts ({} to {})",
+            timecode::format_clock(start),
+            timecode::format_clock(end)
+        ));
+    }
+    Ok((start, end))
+}
+
+/// An EDL event waiting for the comments that follow it
+struct EdlEvent {
+    line: usize,
+    start: f64,
+    end: f64,
+    name: Option<String>,
+    source: Option<String>,
+}
+
+fn flush_edl_event(
+    event: Option<EdlEvent>,
+    jobs: &mut Vec<ClipListJob>,
+    issues: &mut Vec<ImportIssue>,
+) {
+    let Some(event) = event else { return };
+    match event.source {
+        Some(url) => push_segment(
+            jobs,
+            &url,
+            "",
+            segment(0, event.start, event.end, event.name),
+        ),
+        None => issues.push(issue(event.line, "Event has no * SOURCE FILE comment")),
+    }
+}
+
+fn import_edl(text: &str) -> (Vec<ClipListJob>, Vec<ImportIssue>) {
+    let (mut jobs, mut issues) = (Vec::new(), Vec::new());
+    let mut pending: Option<EdlEvent> = None;
+    for (index, raw) in text.lines().enumerate() {
+        let line = raw.trim();
+        let number = index + 1;
+        if let Some(comment) = line.strip_prefix('*') {
+            let comment = comment.trim();
+            if let Some(event) = pending.as_mut() {
+                if let Some(name) = comment.strip_prefix("FROM CLIP NAME:") {
+                    event.name = Some(name.trim().to_string());
+                } else if let Some(source) = comment.strip_prefix("SOURCE FILE:") {
+                    event.source = Some(source.trim().to_string());
+                }
+            }
+            continue;
+        }
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let is_event = fields.len() >= 8 && fields[0].chars().all(|c| c.is_ascii_digit());
+        if !is_event {
+            continue;
+        }
+        flush_edl_event(pending.take(), &mut jobs, &mut issues);
+        let n = fields.len();
+        match parse_range(fields[n - 4], fields[n - 3], Some(timecode::DEFAULT_FPS)) {
+            Ok((start, end)) => {
+                pending = Some(EdlEvent {
+                    line: number,
+                    start,
+                    end,
+                    name: None,
+                    source: None,
+                })
+            }
+            Err(e) => issues.push(issue(number, e)),
+        }
+    }
+    flush_edl_event(pending, &mut jobs, &mut issues);
+
+    // Events of one source share its clip name as the job title
+    for job in &mut jobs {
+        if let Some(name) = job.segments.first().and_then(|s| s.label.clone()) {
+            if job.segments.iter().all(|s| s.label.as_ref() == Some(&name)) {
+                job.title = name;
+                job.segments.iter_mut().for_each(|s| s.label = None);
+            }
+        }
+    }
+    (jobs, issues)
+}
+
+/// A `1:23` / `01:02:03` token, optionally wrapped in brackets
+fn clock_token(token: &str) -> Option<f64> {
+    let token = token.trim_matches(|c: char| "[]()".contains(c));
+    if !token.contains(':')
+        || !token
+            .chars()
+            .all(|c| c.is_ascii_digit() || ":.".contains(c))
+    {
+        return None;
+    }
+    timecode::parse_timecode(token, None).ok()
+}
+
+const RANGE_SEPARATORS: &[&str] = &["-", "–", "—", "to"];
+
+/// `0:00 Intro`, `1:23 - 2:10 Verse`, `1:23-2:10 Verse`, `- 3:45 Chorus`
+fn timestamp_line(line: &str) -> Option<(f64, Option<f64>, String)> {
+    let mut tokens = line
+        .split_whitespace()
+        .skip_while(|t| clock_token(t).is_none() && t.chars().all(|c| !c.is_alphanumeric()));
+    let first = tokens.next()?;
+    let (start, mut end) = match RANGE_SEPARATORS
+        .iter()
+        .find_map(|sep| first.split_once(sep))
+    {
+        Some((a, b)) if clock_token(a).is_some() && clock_token(b).is_some() => {
+            (clock_token(a)?, clock_token(b))
+        }
+        _ => (clock_token(first)?, None),
+    };
+    let mut rest: Vec<&str> = tokens.collect();
+    if end.is_none() && rest.len() >= 2 && RANGE_SEPARATORS.contains(&rest[0]) {
+        if let Some(to) = clock_token(rest[1]) {
+            end = Some(to);
+            rest.drain(..2);
+        }
+    }
+    let label = rest
+        .join(" ")
+        .trim_start_matches(|c: char| "-–—:|".contains(c) || c.is_whitespace())
+        .to_string();
+    Some((start, end, label))
+}
+
+fn import_timestamps(
+    text: &str,
+    url: Option<&str>,
+    title: &str,
+    duration: Option<f64>,
+) -> (Vec<ClipListJob>, Vec<ImportIssue>) {
+    let (mut jobs, mut issues) = (Vec::new(), Vec::new());
+    // Comments often include the link itself
+    let url = url.map(str::to_string).or_else(|| {
+        text.split_whitespace()
+            .find(|t| t.starts_with("https://") || t.starts_with("http://"))
+            .map(str::to_string)
+    });
+    let Some(url) = url else {
+        issues.push(ImportIssue {
+            line: None,
+            message: "Timestamps don't name a video; open the video first".to_string(),
+        });
+        return (jobs, issues);
+    };
+
+    // Lines without a timestamp are prose and skipped
+    let stamps: Vec<(usize, f64, Option<f64>, String)> = text
+        .lines()
+        .enumerate()
+        .filter_map(|(i, line)| timestamp_line(line).map(|(s, e, l)| (i + 1, s, e, l)))
+        .collect();
+    for (index, (line, start, end, label)) in stamps.iter().enumerate() {
+        // A chapter runs until the next one starts
+        let end = end
+            .or_else(|| stamps.get(index + 1).map(|next| next.1))
+            .or(duration);
+        match end {
+            Some(end) if end > *start => push_segment(
+                &mut jobs,
+                &url,
+                title,
+                segment(0, *start, end, Some(label.clone())),
+            ),
+            Some(_) => issues.push(issue(*line, "Timestamp is out of order")),
+            None => issues.push(issue(
+                *line,
+                "The last timestamp has no end; it needs the video's duration",
+            )),
+        }
+    }
+    (jobs, issues)
+}
+
+fn import_lines(text: &str) -> (Vec<ClipListJob>, Vec<ImportIssue>) {
+    let (mut jobs, mut issues) = (Vec::new(), Vec::new());
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let [url, start, end, label @ ..] = fields.as_slice() else {
+            issues.push(issue(index + 1, "Expected: url start end [label]"));
+            continue;
+        };
+        match parse_range(start, end, None) {
+            Ok((start, end)) => push_segment(
+                &mut jobs,
+                url,
+                "",
+                segment(0, start, end, Some(label.join(" "))),
+            ),
+            Err(e) => issues.push(issue(index + 1, e)),
+        }
+    }
+    (jobs, issues)
+}
+
+/// Guess the format from the file extension, then from the content
+pub fn detect_format(path: Option<&Path>, text: &str) -> ClipListFormat {
+    let extension = path
+        .and_then(|p| p.extension())
+        .map(|e| e.to_string_lossy().to_lowercase());
+    match extension.as_deref() {
+        Some("json") => return ClipListFormat::Json,
+        Some("csv") => return ClipListFormat::Csv,
+        Some("edl") => return ClipListFormat::Edl,
+        _ => {}
+    }
+
+    let trimmed = text.trim_start_matches('\u{feff}').trim_start();
+    let first = trimmed.lines().next().unwrap_or_default();
+    if trimmed.starts_with('{') || trimmed.starts_with('[') {
+        ClipListFormat::Json
+    } else if first.starts_with("TITLE:") || first.starts_with("FCM:") {
+        ClipListFormat::Edl
+    } else if first.to_lowercase().starts_with("url,")
+        || (first.starts_with("http") && first.contains(','))
+    {
+        ClipListFormat::Csv
+    } else if first.starts_with("http") && first.split_whitespace().count() >= 3 {
+        ClipListFormat::Lines
+    } else {
+        ClipListFormat::Timestamps
+    }
+}
+
+/// Parse a clip list without touching the sources
+pub fn parse(
+    text: &str,
+    format: ClipListFormat,
+    request: &ImportRequest,
+) -> (Vec<ClipListJob>, Vec<ImportIssue>) {
+    match format {
+        ClipListFormat::Json => match import_json(text) {
+            Ok(jobs) => (jobs, Vec::new()),
+            Err(message) => (
+                Vec::new(),
+                vec![ImportIssue {
+                    line: None,
+                    message,
+                }],
+            ),
+        },
+        ClipListFormat::Csv => import_csv(text),
+        ClipListFormat::Edl => import_edl(text),
+        ClipListFormat::Timestamps => import_timestamps(
+            text,
+            request.url.as_deref(),
+            request.title.as_deref().unwrap_or_default(),
+            request.duration,
+        ),
+        ClipListFormat::Lines => import_lines(text),
+    }
+}
+
+/// Drop jobs whose source fails `check_source` and segments with bad ranges
+fn check_jobs(
+    jobs: Vec<ClipListJob>,
+    check_source: impl Fn(&str) -> Result<String, String>,
+) -> (Vec<ClipListJob>, Vec<ImportIssue>) {
+    let mut issues = Vec::new();
+    let jobs = jobs
+        .into_iter()
+        .filter_map(|mut job| {
+            let name = if job.title.is_empty() {
+                job.url.clone()
+            } else {
+                job.title.clone()
+            };
+            match check_source(&job.url) {
+                Ok(url) => job.url = url,
+                Err(e) => {
+                    issues.push(ImportIssue {
+                        line: None,
+                        message: format!("{}: {}", name, e),
+                    });
+                    return None;
+                }
+            }
+            job.segments.retain(|s| {
+                let valid =
+                    s.start.is_finite() && s.end.is_finite() && 0.0 <= s.start && s.start < s.end;
+                if !valid {
+                    issues.push(ImportIssue {
+                        line: None,
+                        message: format!("{}: invalid range {}-{}", name, s.start, s.end),
+                    });
+                }
+                valid
+            });
+            (!job.segments.is_empty()).then_some(job)
+        })
+        .collect();
+    (jobs, issues)
+}
+
+/// Export format for the file name picked in the save dialog
+fn export_format(path: &Path, fallback: ClipListFormat) -> ClipListFormat {
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase());
+    match extension.as_deref() {
+        Some("json") => ClipListFormat::Json,
+        Some("csv") => ClipListFormat::Csv,
+        Some("edl") => ClipListFormat::Edl,
+        _ => fallback,
+    }
+}
+
+/// Ask where to save history entries or the current session and write them in
+/// the format of the chosen extension. Returns the number of clips written, or
+/// `None` if the dialog was cancelled.
+#[tauri::command]
+pub async fn export_clip_list(
+    app: AppHandle,
+    source: ExportSource,
+    format: Option<ClipListFormat>,
+) -> Result<Option<usize>, String> {
+    let jobs = match source {
+        ExportSource::History { ids } => {
+            let conn = history::open(&app)?;
+            let mut items = Vec::with_capacity(ids.len());
+            for id in &ids {
+                if let Some(item) = history::get_item(&conn, id)? {
+                    items.push(item);
+                }
+            }
+            jobs_from_history(&items)
+        }
+        ExportSource::Session { jobs } => jobs,
+    };
+    let clips: usize = jobs.iter().map(|job| job.segments.len()).sum();
+    if clips == 0 {
+        return Err("Nothing to export: none of these entries have a clip range".to_string());
+    }
+
+    let fallback = format.unwrap_or(ClipListFormat::Json);
+    let extension = match fallback {
+        ClipListFormat::Csv => "csv",
+        ClipListFormat::Edl => "edl",
+        _ => "json",
+    };
+    let picked = app
+        .dialog()
+        .file()
+        .add_filter("Clip list (JSON)", &["json"])
+        .add_filter("Spreadsheet (CSV)", &["csv"])
+        .add_filter("Edit decision list (EDL)", &["edl"])
+        .set_file_name(format!("clips.{}", extension))
+        .blocking_save_file();
+    let Some(picked) = picked else {
+        return Ok(None);
+    };
+    let path = picked
+        .into_path()
+        .map_err(|e| format!("Failed to read selected file: {}", e))?;
+
+    let text = export(&jobs, export_format(&path, fallback))?;
+    fs::write(&path, text).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    log::info!("Exported {} clips to {}", clips, path.display());
+    Ok(Some(clips))
+}
+
+fn pick_clip_list(app: &AppHandle) -> Result<Option<PathBuf>, String> {
+    let picked = app
+        .dialog()
+        .file()
+        .add_filter("Clip list", &["json", "csv", "edl", "txt"])
+        .blocking_pick_file();
+    let Some(picked) = picked else {
+        return Ok(None);
+    };
+    picked
+        .into_path()
+        .map(Some)
+        .map_err(|e| format!("Failed to read selected file: {}", e))
+}
+
+/// Read pasted text, or a clip list file the user picks, and return the jobs in
+/// it that can be queued. Lines and sources that can't be used are reported
+/// instead of failing the import. `None` if the dialog was cancelled.
+#[tauri::command]
+pub async fn import_clip_list(
+    app: AppHandle,
+    request: ImportRequest,
+) -> Result<Option<ClipListImport>, String> {
+    let (text, path) = match &request.text {
+        Some(text) => (text.clone(), None),
+        None => {
+            let Some(path) = pick_clip_list(&app)? else {
+                return Ok(None);
+            };
+            let size = fs::metadata(&path)
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+                .len();
+            if size > MAX_IMPORT_BYTES {
+                return Err("File is too large to be a clip list".to_string());
+            }
+            let text = fs::read_to_string(&path)
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            (text, Some(path))
+        }
+    };
+
+    let format = request
+        .format
+        .unwrap_or_else(|| detect_format(path.as_deref(), &text));
+    let (jobs, mut issues) = parse(&text, format, &request);
+    let (jobs, source_issues) = check_jobs(jobs, |url| {
+        validation::validate_source(&app, url).map(|source| source.as_arg())
+    });
+    issues.extend(source_issues);
+    log::info!(
+        "Imported {} jobs from {:?} clip list ({} issues)",
+        jobs.len(),
+        format,
+        issues.len()
+    );
+    Ok(Some(ClipListImport {
+        format,
+        jobs,
+        issues,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranges(jobs: &[ClipListJob]) -> Vec<(String, f64, f64, Option<String>)> {
+        jobs.iter()
+            .flat_map(|job| {
+                job.segments
+                    .iter()
+                    .map(|s| (job.url.clone(), s.start, s.end, s.label.clone()))
+            })
+            .collect()
+    }
+
+    fn session() -> Vec<ClipListJob> {
+        vec![
+            ClipListJob {
+                url: "https://youtu.be/abc".to_string(),
+                title: "Talk, \"live\"".to_string(),
+                segments: vec![
+                    segment(0, 12.5, 20.0, Some("Intro".to_string())),
+                    segment(1, 65.0, 90.25, None),
+                ],
+            },
+            ClipListJob {
+                url: "https://vimeo.com/1".to_string(),
+                title: "Other".to_string(),
+                segments: vec![segment(0, 0.0, 3.0, None)],
+            },
+        ]
+    }
+
+    #[test]
+    fn round_trips_every_export_format() {
+        let jobs = session();
+        let request = ImportRequest::default();
+        for format in [
+            ClipListFormat::Json,
+            ClipListFormat::Csv,
+            ClipListFormat::Edl,
+        ] {
+            let text = export(&jobs, format).unwrap();
+            assert_eq!(detect_format(None, &text), format);
+            let (imported, issues) = parse(&text, format, &request);
+            assert!(issues.is_empty(), "{:?}: {:?}", format, issues);
+            assert_eq!(imported.len(), 2, "{:?}", format);
+            let expected = ranges(&jobs);
+            let got = ranges(&imported);
+            for (a, b) in expected.iter().zip(&got) {
+                assert_eq!(a.0, b.0);
+                // EDL timecodes are frame accurate at 30 fps
+                assert!(
+                    (a.1 - b.1).abs() < 0.02 && (a.2 - b.2).abs() < 0.02,
+                    "{:?}",
+                    format
+                );
+            }
+            if format != ClipListFormat::Edl {
+                assert_eq!(imported[0].title, "Talk, \"live\"");
+                assert_eq!(got[0].3.as_deref(), Some("Intro"));
+            }
+        }
+    }
+
+    #[test]
+    fn groups_history_entries_by_job() {
+        let entry = |id: &str, job_id: Option<&str>, range: Option<(f64, f64)>| {
+            serde_json::from_value::<DownloadHistoryItem>(serde_json::json!({
+                "id": id,
+                "title": format!("Song ({})", id),
+                "url": "https://youtu.be/abc",
+                "duration": 1.0,
+                "quality": "best",
+                "format": "mp4",
+                "filePath": "",
+                "downloadedAt": "2024-05-01T10:00:00.000Z",
+                "startTime": range.map(|r| r.0),
+                "endTime": range.map(|r| r.1),
+                "jobId": job_id,
+            }))
+            .unwrap()
+        };
+        let jobs = jobs_from_history(&[
+            entry("a-1", Some("a"), Some((1.0, 2.0))),
+            entry("legacy", None, None),
+            entry("b", None, Some((5.0, 6.0))),
+            entry("a-2", Some("a"), Some((3.0, 4.0))),
+        ]);
+        assert_eq!(jobs.len(), 2);
+        assert_eq!(jobs[0].title, "Song (a-1)");
+        assert_eq!(jobs[0].segments.len(), 2);
+        assert_eq!(jobs[1].segments[0].start, 5.0);
+    }
+
+    #[test]
+    fn writes_cmx3600_events() {
+        let edl = export_edl(&session());
+        let lines: Vec<&str> = edl.lines().collect();
+        assert_eq!(lines[0], "TITLE: Clipme clips");
+        assert_eq!(
+            lines[3],
+            "001  AX       V     C        00:00:12:15 00:00:20:00 01:00:00:00 01:00:07:15"
+        );
+        assert_eq!(lines[4], "* FROM CLIP NAME: Intro");
+        assert_eq!(lines[5], "* SOURCE FILE: https://youtu.be/abc");
+        // The record side continues where the previous event ended
+        assert!(
+            lines[7].ends_with("01:00:07:15 01:00:32:23"),
+            "{}",
+            lines[7]
+        );
+    }
+
+    #[test]
+    fn imports_csv_without_header_and_reports_bad_rows() {
+        let text = "https://a.com/v,0:10,0:20\nhttps://a.com/v,1:00,0:30\nhttps://b.com/v,5,9\n";
+        let (jobs, issues) = import_csv(text);
+        assert_eq!(jobs.len(), 2);
+        assert_eq!(
+            ranges(&jobs)[0],
+            ("https://a.com/v".to_string(), 10.0, 20.0, None)
+        );
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].line, Some(2));
+
+        let quoted = "url,title,start,end\r\nhttps://a.com/v,\"Line\nbreak\",1,2\r\nhttps://a.com/v,x,a,2\r\n";
+        let (jobs, issues) = import_csv(quoted);
+        assert_eq!(jobs[0].title, "Line\nbreak");
+        assert_eq!(issues[0].line, Some(4));
+    }
+
+    #[test]
+    fn imports_youtube_timestamp_comments() {
+        let comment = "Great video! Chapters:\n\
+            0:00 Intro\n\
+            [1:23] - Verse one\n\
+            2:10–2:40 Chorus\n\
+            Not a timestamp 5 minutes in\n\
+            3:45 | Outro";
+        let (jobs, issues) = import_timestamps(comment, Some("https://youtu.be/x"), "Song", None);
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].title, "Song");
+        let got = ranges(&jobs);
+        assert_eq!(got[0].1..got[0].2, 0.0..83.0);
+        assert_eq!(got[1].3.as_deref(), Some("Verse one"));
+        assert_eq!((got[2].1, got[2].2), (130.0, 160.0));
+        assert_eq!(got[2].3.as_deref(), Some("Chorus"));
+        // The last chapter has no end without the duration
+        assert_eq!(got.len(), 3);
+        assert_eq!(issues[0].line, Some(6));
+
+        let (jobs, issues) =
+            import_timestamps(comment, Some("https://youtu.be/x"), "", Some(240.0));
+        assert!(issues.is_empty());
+        assert_eq!(ranges(&jobs)[3].2, 240.0);
+
+        let (jobs, issues) = import_timestamps("0:00 a\n0:10 b", None, "", None);
+        assert!(jobs.is_empty());
+        assert_eq!(issues[0].line, None);
+    }
+
+    #[test]
+    fn exports_in_the_format_of_the_picked_extension() {
+        let format = |name: &str| export_format(Path::new(name), ClipListFormat::Csv);
+        assert_eq!(format("/tmp/clips.EDL"), ClipListFormat::Edl);
+        assert_eq!(format("/tmp/clips.json"), ClipListFormat::Json);
+        assert_eq!(format("/tmp/clips"), ClipListFormat::Csv);
+        assert_eq!(format("/tmp/clips.txt"), ClipListFormat::Csv);
+    }
+
+    #[test]
+    fn imports_plain_lines() {
+        let text = "# shared clips\nhttps://a.com/v 1:00 1:30 Best bit\nhttps://a.com/v 2m 2m10s\nhttps://b.com/v 10\n";
+        assert_eq!(
+            detect_format(None, "https://a.com/v 1:00 1:30"),
+            ClipListFormat::Lines
+        );
+        let (jobs, issues) = import_lines(text);
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(
+            ranges(&jobs),
+            vec![
+                (
+                    "https://a.com/v".to_string(),
+                    60.0,
+                    90.0,
+                    Some("Best bit".to_string())
+                ),
+                ("https://a.com/v".to_string(), 120.0, 130.0, None),
+            ]
+        );
+        assert_eq!(issues, vec![issue(4, "Expected: url start end [label]")]);
+    }
+
+    #[test]
+    fn rejects_newer_json_and_unusable_sources() {
+        let (jobs, issues) = parse(
+            r#"{"version": 99, "jobs": []}"#,
+            ClipListFormat::Json,
+            &ImportRequest::default(),
+        );
+        assert!(jobs.is_empty());
+        assert!(issues[0].message.contains("newer version"));
+
+        let mut jobs = session();
+        jobs[1].url = "file:///etc/passwd".to_string();
+        let (jobs, issues) = check_jobs(jobs, |url| {
+            if url.starts_with("https://") {
+                Ok(url.to_string())
+            } else {
+                Err("Unsupported URL scheme: file".to_string())
+            }
+        });
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(issues[0].message, "Other: Unsupported URL scheme: file");
+    }
+}
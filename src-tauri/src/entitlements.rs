@@ -0,0 +1,292 @@
+//! Which features need which license tier. Commands call `require` before doing
+//! any work, so the frontend's Pro badges are only a hint, not the gate.
+
+use crate::{history, license, picked_video_height, ClipJob, MultiClipJob};
+use serde::ser::{SerializeStruct, Serializer};
+use serde::Serialize;
+use std::fmt;
+use tauri::AppHandle;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Tier {
+    Free,
+    Pro,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Feature {
+    MultiClip,
+    /// 4K and 8K output
+    UltraHdQuality,
+    Batch,
+}
+
+/// Outputs taller than this need `UltraHdQuality`
+const MAX_FREE_HEIGHT: u64 = 1440;
+
+/// Every gated feature and the tier it needs; anything not listed is free
+const REQUIREMENTS: &[(Feature, Tier)] = &[
+    (Feature::MultiClip, Tier::Pro),
+    (Feature::UltraHdQuality, Tier::Pro),
+    (Feature::Batch, Tier::Pro),
+];
+
+impl Feature {
+    fn label(self) -> &'static str {
+        match self {
+            Feature::MultiClip => "Multi-clip export",
+            Feature::UltraHdQuality => "4K and 8K quality",
+            Feature::Batch => "Batch downloading",
+        }
+    }
+
+    pub fn required_tier(self) -> Tier {
+        REQUIREMENTS
+            .iter()
+            .find(|(feature, _)| *feature == self)
+            .map(|(_, tier)| *tier)
+            .unwrap_or(Tier::Free)
+    }
+
+    /// The feature an output of `height` needs. Unknown heights count as UHD
+    /// so a request the gate can't size doesn't get past it.
+    pub fn for_height(height: Option<u64>) -> Option<Feature> {
+        match height {
+            Some(height) if height <= MAX_FREE_HEIGHT => None,
+            _ => Some(Feature::UltraHdQuality),
+        }
+    }
+
+    /// The feature a quality preset needs when the source isn't known, as for
+    /// batches; "Best" and unknown presets count as UHD
+    pub fn for_quality(quality: &str) -> Option<Feature> {
+        Feature::for_height(preset_height(quality))
+    }
+}
+
+/// Tallest output of a quality preset; `None` for "Best" and anything unknown
+fn preset_height(quality: &str) -> Option<u64> {
+    match quality {
+        "8K" => Some(4320),
+        "4K" => Some(2160),
+        "1440p" => Some(1440),
+        "1080p" => Some(1080),
+        "720p" => Some(720),
+        "480p" => Some(480),
+        "Audio Only" => Some(0),
+        _ => None,
+    }
+}
+
+/// Height a clip will be output at. A format picked by id goes by that format,
+/// a preset by its cap and "Best" by the source's tallest video. `height_of`
+/// looks those up and returns `None` when they aren't known.
+fn requested_height(
+    quality: &str,
+    format_id: Option<&str>,
+    height_of: impl FnOnce(Option<&str>) -> Option<u64>,
+) -> Option<u64> {
+    match (format_id, preset_height(quality)) {
+        (Some(id), _) => height_of(Some(id)),
+        (None, Some(height)) => Some(height),
+        (None, None) => height_of(None),
+    }
+}
+
+/// A feature was used without the tier it needs
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequiresTier {
+    pub feature: Feature,
+    pub tier: Tier,
+}
+
+impl fmt::Display for RequiresTier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.tier {
+            Tier::Pro => write!(f, "{} requires Clipme Pro", self.feature.label()),
+            Tier::Free => write!(f, "{} is unavailable", self.feature.label()),
+        }
+    }
+}
+
+/// Sent to the frontend as `{code: "requires_tier", feature, tier, message}`
+/// so it can tell a tier error from a failed job without matching the text
+impl Serialize for RequiresTier {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut error = serializer.serialize_struct("RequiresTier", 4)?;
+        error.serialize_field("code", "requires_tier")?;
+        error.serialize_field("feature", &self.feature)?;
+        error.serialize_field("tier", &self.tier)?;
+        error.serialize_field("message", &self.to_string())?;
+        error.end()
+    }
+}
+
+/// Error of a command that checks entitlements: a `RequiresTier` object or
+/// the usual message string
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum CommandError {
+    RequiresTier(RequiresTier),
+    Message(String),
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandError::RequiresTier(error) => error.fmt(f),
+            CommandError::Message(message) => f.write_str(message),
+        }
+    }
+}
+
+impl From<RequiresTier> for CommandError {
+    fn from(error: RequiresTier) -> Self {
+        CommandError::RequiresTier(error)
+    }
+}
+
+impl From<history::JobError> for CommandError {
+    fn from(error: history::JobError) -> Self {
+        CommandError::Message(error.message)
+    }
+}
+
+impl From<String> for CommandError {
+    fn from(message: String) -> Self {
+        CommandError::Message(message)
+    }
+}
+
+impl From<&str> for CommandError {
+    fn from(message: &str) -> Self {
+        CommandError::Message(message.to_string())
+    }
+}
+
+/// The tier of the verified license on this device
+pub fn tier(app: &AppHandle) -> Tier {
+    if license::is_pro(app) {
+        Tier::Pro
+    } else {
+        Tier::Free
+    }
+}
+
+fn check(tier: Tier, features: &[Feature]) -> Result<(), RequiresTier> {
+    match features
+        .iter()
+        .find(|feature| feature.required_tier() > tier)
+    {
+        Some(&feature) => Err(RequiresTier {
+            feature,
+            tier: feature.required_tier(),
+        }),
+        None => Ok(()),
+    }
+}
+
+pub fn require(app: &AppHandle, features: &[Feature]) -> Result<(), RequiresTier> {
+    // Most jobs use nothing gated; skip reading the license for those
+    if check(Tier::Free, features).is_ok() {
+        return Ok(());
+    }
+    check(tier(app), features)
+}
+
+fn clip_features(
+    app: &AppHandle,
+    url: &str,
+    quality: &str,
+    format_id: Option<&str>,
+) -> Vec<Feature> {
+    let height = requested_height(quality, format_id, |id| picked_video_height(app, url, id));
+    Feature::for_height(height).into_iter().collect()
+}
+
+pub fn require_clip(app: &AppHandle, job: &ClipJob) -> Result<(), RequiresTier> {
+    let format_id = job.format_id.as_deref().or(job.video_format_id.as_deref());
+    require(app, &clip_features(app, &job.url, &job.quality, format_id))
+}
+
+pub fn require_multi_clip(app: &AppHandle, job: &MultiClipJob) -> Result<(), RequiresTier> {
+    let format_id = job.format_id.as_deref().or(job.video_format_id.as_deref());
+    let mut features = vec![Feature::MultiClip];
+    features.extend(clip_features(app, &job.url, &job.quality, format_id));
+    require(app, &features)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gates_features_by_tier() {
+        let quality = |quality: &str| Vec::from_iter(Feature::for_quality(quality));
+        assert_eq!(check(Tier::Free, &[]), Ok(()));
+        assert_eq!(check(Tier::Free, &quality("1080p")), Ok(()));
+        assert_eq!(
+            check(Tier::Free, &quality("8K")),
+            Err(RequiresTier {
+                feature: Feature::UltraHdQuality,
+                tier: Tier::Pro
+            })
+        );
+        assert_eq!(
+            check(Tier::Pro, &[Feature::MultiClip, Feature::UltraHdQuality]),
+            Ok(())
+        );
+
+        let error = check(Tier::Free, &[Feature::Batch]).unwrap_err();
+        assert_eq!(error.to_string(), "Batch downloading requires Clipme Pro");
+    }
+
+    #[test]
+    fn tier_errors_reach_the_frontend_as_objects() {
+        let error = CommandError::from(RequiresTier {
+            feature: Feature::UltraHdQuality,
+            tier: Tier::Pro,
+        });
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            serde_json::json!({
+                "code": "requires_tier",
+                "feature": "ultra_hd_quality",
+                "tier": "pro",
+                "message": "4K and 8K quality requires Clipme Pro",
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(CommandError::from("Download cancelled")).unwrap(),
+            serde_json::json!("Download cancelled")
+        );
+    }
+
+    #[test]
+    fn sizes_requests_by_what_they_output() {
+        let source = |id: Option<&str>| match id {
+            None => Some(2160),
+            Some("137") => Some(1080),
+            Some("401") => Some(2160),
+            Some(_) => None,
+        };
+        let ultra_hd = |quality, id| Feature::for_height(requested_height(quality, id, source));
+
+        assert_eq!(ultra_hd("720p", None), None);
+        assert_eq!(ultra_hd("Best", None), Some(Feature::UltraHdQuality));
+        assert_eq!(ultra_hd("4K", None), Some(Feature::UltraHdQuality));
+        // A picked format goes by its own height, whatever the preset says
+        assert_eq!(ultra_hd("720p", Some("401")), Some(Feature::UltraHdQuality));
+        assert_eq!(ultra_hd("Best", Some("137")), None);
+        // Formats and presets the gate can't size are treated as UHD
+        assert_eq!(ultra_hd("720p", Some("999")), Some(Feature::UltraHdQuality));
+        assert_eq!(ultra_hd("Ultra", None), Some(Feature::UltraHdQuality));
+        assert_eq!(
+            Feature::for_height(requested_height("Best", None, |_| Some(1080))),
+            None
+        );
+        assert_eq!(Feature::for_quality("Best"), Some(Feature::UltraHdQuality));
+    }
+}
@@ -0,0 +1,584 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::{get_ffmpeg, get_ffprobe, get_ytdlp, preview, probe_local_file, validation};
+
+const MAX_FRAMES: u32 = 300;
+const MAX_COLUMNS: u32 = 10;
+/// Least recently used filmstrips are evicted beyond this
+const CACHE_MAX_BYTES: u64 = 256 * 1024 * 1024;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FilmstripFrame {
+    time: f64,
+    x: u32,
+    y: u32,
+}
+
+/// Sprite sheet plus the position of every frame in it
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FilmstripIndex {
+    source: String,
+    sprite: String,
+    tile_width: u32,
+    tile_height: u32,
+    columns: u32,
+    rows: u32,
+    frames: Vec<FilmstripFrame>,
+}
+
+/// Emitted as soon as a frame is available, before the sprite is assembled.
+/// `image` is either a single frame or a storyboard sheet; `x`/`y` locate the
+/// frame inside it.
+#[derive(Clone, Serialize, Debug)]
+struct FilmstripFrameEvent {
+    request_id: u64,
+    index: usize,
+    time: f64,
+    image: String,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+/// Stable cache key; local files also key on size and mtime so a replaced
+/// file gets a new filmstrip
+fn filmstrip_key(source: &str, count: u32, width: u32) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(source.as_bytes());
+    hasher.update([0]);
+    if let Ok(meta) = fs::metadata(source) {
+        hasher.update(meta.len().to_le_bytes());
+        let modified = meta
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        hasher.update(modified.to_le_bytes());
+    }
+    hasher.update(count.to_le_bytes());
+    hasher.update(width.to_le_bytes());
+    hex::encode(&hasher.finalize()[..8])
+}
+
+fn filmstrip_dir(app: &AppHandle, source: &str, count: u32, width: u32) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .app_cache_dir()
+        .map_err(|e| format!("Failed to get cache dir: {}", e))?
+        .join("filmstrips")
+        .join(filmstrip_key(source, count, width));
+    if !dir.exists() {
+        fs::create_dir_all(&dir).map_err(|e| format!("Failed to create filmstrip dir: {}", e))?;
+    }
+    Ok(dir)
+}
+
+/// Evict least recently used filmstrips until the cache fits in `max_bytes`.
+/// A filmstrip's index.json mtime marks its last use; `keep` is never evicted.
+fn enforce_cache_limit(root: &Path, max_bytes: u64, keep: &Path) {
+    let Ok(read_dir) = fs::read_dir(root) else {
+        return;
+    };
+    let mut entries: Vec<(SystemTime, u64, PathBuf)> = read_dir
+        .filter_map(Result::ok)
+        .map(|e| e.path())
+        .filter(|path| path.is_dir())
+        .map(|path| {
+            let size = fs::read_dir(&path)
+                .map(|files| {
+                    files
+                        .filter_map(Result::ok)
+                        .filter_map(|f| f.metadata().ok())
+                        .map(|m| m.len())
+                        .sum()
+                })
+                .unwrap_or(0);
+            let last_used = fs::metadata(path.join("index.json"))
+                .or_else(|_| fs::metadata(&path))
+                .and_then(|m| m.modified())
+                .unwrap_or(UNIX_EPOCH);
+            (last_used, size, path)
+        })
+        .collect();
+
+    let mut total: u64 = entries.iter().map(|(_, size, _)| size).sum();
+    entries.sort_by_key(|(last_used, _, _)| *last_used);
+    for (_, size, path) in entries {
+        if total <= max_bytes {
+            break;
+        }
+        if path == keep {
+            continue;
+        }
+        if fs::remove_dir_all(&path).is_ok() {
+            total = total.saturating_sub(size);
+        }
+    }
+}
+
+/// Evenly spaced sample times, each in the middle of its slice of the video
+fn sample_times(duration: f64, count: u32) -> Vec<f64> {
+    (0..count)
+        .map(|i| duration * (i as f64 + 0.5) / count as f64)
+        .collect()
+}
+
+/// How many frames ffmpeg wrote. The image2 muxer numbers them without gaps,
+/// so the first missing file ends the run.
+fn written_frames(dir: &Path, count: u32) -> u32 {
+    (0..count)
+        .find(|index| !frame_path(dir, *index).is_file())
+        .unwrap_or(count)
+}
+
+fn frame_path(dir: &Path, index: u32) -> PathBuf {
+    dir.join(format!("frame_{:03}.jpg", index))
+}
+
+/// Extract `count` frames from a local file (or downloaded preview) in one
+/// ffmpeg pass and tile them. Frames near the end can be missing when the
+/// container's duration overstates the video stream; the sprite and index
+/// then cover the frames that were written.
+fn local_filmstrip(
+    app: &AppHandle,
+    source: &str,
+    count: u32,
+    width: u32,
+    dir: &Path,
+    request_id: u64,
+) -> Result<FilmstripIndex, String> {
+    let probe = probe_local_file(&get_ffprobe(app)?, source)?;
+    let duration = probe["format"]["duration"]
+        .as_str()
+        .and_then(|s| s.parse::<f64>().ok())
+        .filter(|d| *d > 0.0)
+        .ok_or("Could not determine video duration")?;
+    let video = probe["streams"]
+        .as_array()
+        .and_then(|s| s.iter().find(|s| s["codec_type"].as_str() == Some("video")))
+        .ok_or("Source has no video stream")?;
+    let src_width = video["width"].as_u64().unwrap_or(16).max(1) as f64;
+    let src_height = video["height"].as_u64().unwrap_or(9) as f64;
+    // Even height keeps every encoder happy
+    let tile_height = (((width as f64 * src_height / src_width) / 2.0).round() as u32 * 2).max(2);
+
+    let ffmpeg = get_ffmpeg(app)?;
+    let times = sample_times(duration, count);
+    // Seeking half an interval in lines the fps grid up with `sample_times`
+    let mut child = ffmpeg
+        .command()
+        .args(["-y", "-nostats", "-progress", "pipe:1"])
+        .args([
+            "-ss",
+            &format!("{:.3}", duration / count as f64 / 2.0),
+            "-i",
+            source,
+        ])
+        .args([
+            "-an",
+            "-vf",
+            &format!("fps={}/{},scale={}:{}", count, duration, width, tile_height),
+            "-frames:v",
+            &count.to_string(),
+            "-start_number",
+            "0",
+            "-q:v",
+            "4",
+        ])
+        .arg(dir.join("frame_%03d.jpg"))
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to start ffmpeg: {}", e))?;
+
+    let emit_frame = |index: u32| {
+        let _ = app.emit(
+            "filmstrip-frame",
+            FilmstripFrameEvent {
+                request_id,
+                index: index as usize,
+                time: times[index as usize],
+                image: frame_path(dir, index).to_string_lossy().to_string(),
+                x: 0,
+                y: 0,
+                width,
+                height: tile_height,
+            },
+        );
+    };
+    // `frame=N` means frames before N are on disk
+    let mut emitted = 0;
+    if let Some(stdout) = child.stdout.take() {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            let Some(done) = line
+                .strip_prefix("frame=")
+                .and_then(|n| n.parse::<u32>().ok())
+            else {
+                continue;
+            };
+            for index in emitted..done.min(count) {
+                emit_frame(index);
+            }
+            emitted = emitted.max(done.min(count));
+        }
+    }
+    let status = child
+        .wait()
+        .map_err(|e| format!("Failed to wait on ffmpeg: {}", e))?;
+
+    let written = written_frames(dir, count);
+    if !status.success() || written == 0 {
+        return Err("Failed to extract filmstrip frames".to_string());
+    }
+    for index in emitted..written {
+        emit_frame(index);
+    }
+    if written < count {
+        log::debug!("Filmstrip has {} of {} frames", written, count);
+    }
+
+    let columns = written.min(MAX_COLUMNS);
+    let rows = written.div_ceil(columns);
+    let sprite_path = dir.join("sprite.jpg");
+    let status = ffmpeg
+        .command()
+        .arg("-y")
+        .arg("-i")
+        .arg(dir.join("frame_%03d.jpg"))
+        .args([
+            "-vf",
+            &format!("tile={}x{}", columns, rows),
+            "-frames:v",
+            "1",
+            "-q:v",
+            "4",
+        ])
+        .arg(&sprite_path)
+        .status()
+        .map_err(|e| format!("Failed to start ffmpeg: {}", e))?;
+    if !status.success() {
+        return Err("Failed to assemble filmstrip sprite".to_string());
+    }
+
+    Ok(FilmstripIndex {
+        source: source.to_string(),
+        sprite: sprite_path.to_string_lossy().to_string(),
+        tile_width: width,
+        tile_height,
+        columns,
+        rows,
+        frames: tile_frames(&times[..written as usize], columns, width, tile_height),
+    })
+}
+
+/// Row-major positions of local frames in the sprite
+fn tile_frames(times: &[f64], columns: u32, width: u32, height: u32) -> Vec<FilmstripFrame> {
+    times
+        .iter()
+        .enumerate()
+        .map(|(index, time)| FilmstripFrame {
+            time: *time,
+            x: (index as u32 % columns) * width,
+            y: (index as u32 / columns) * height,
+        })
+        .collect()
+}
+
+/// Storyboard layout as exposed by yt-dlp (`sb0`, `sb1`, ... formats)
+struct Storyboard {
+    tile_width: u32,
+    tile_height: u32,
+    columns: u32,
+    rows: u32,
+    interval: f64,
+    sheets: Vec<String>,
+}
+
+/// Pick the smallest storyboard at least `width` wide, or the largest one
+fn select_storyboard(json_val: &serde_json::Value, width: u32) -> Option<Storyboard> {
+    let mut boards: Vec<Storyboard> = json_val["formats"]
+        .as_array()?
+        .iter()
+        .filter(|fmt| fmt["format_note"].as_str() == Some("storyboard"))
+        .filter_map(|fmt| {
+            let columns = fmt["columns"].as_u64().filter(|c| *c > 0)? as u32;
+            let rows = fmt["rows"].as_u64().filter(|r| *r > 0)? as u32;
+            let fragments = fmt["fragments"].as_array()?;
+            let sheets: Vec<String> = fragments
+                .iter()
+                .filter_map(|f| f["url"].as_str().map(String::from))
+                .collect();
+            let per_sheet = (columns * rows) as f64;
+            let interval = fmt["fps"]
+                .as_f64()
+                .filter(|fps| *fps > 0.0)
+                .map(|fps| 1.0 / fps)
+                .or_else(|| fragments.first()?["duration"].as_f64().map(|d| d / per_sheet))
+                .filter(|interval| *interval > 0.0)?;
+            Some(Storyboard {
+                tile_width: fmt["width"].as_u64().filter(|w| *w > 0)? as u32,
+                tile_height: fmt["height"].as_u64().filter(|h| *h > 0)? as u32,
+                columns,
+                rows,
+                interval,
+                sheets,
+            })
+        })
+        .filter(|board| !board.sheets.is_empty())
+        .collect();
+
+    boards.sort_by_key(|board| board.tile_width);
+    let index = boards
+        .iter()
+        .position(|board| board.tile_width >= width)
+        .unwrap_or(boards.len().checked_sub(1)?);
+    Some(boards.swap_remove(index))
+}
+
+/// Build a filmstrip for a remote video from its storyboard sheets
+async fn storyboard_filmstrip(
+    app: &AppHandle,
+    source: &str,
+    count: u32,
+    width: u32,
+    dir: &Path,
+    request_id: u64,
+) -> Result<FilmstripIndex, String> {
+    let output = get_ytdlp(app)?
+        .command()
+        .args(validation::ytdlp_args(
+            ["--dump-json", "--no-playlist", "--no-warnings"],
+            source,
+        ))
+        .output()
+        .map_err(|e| format!("Failed to execute yt-dlp: {}", e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("yt-dlp error: {}", stderr));
+    }
+    let json_val: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| format!("Failed to parse JSON: {}", e))?;
+    let duration = json_val["duration"].as_f64().unwrap_or(0.0);
+    let board = select_storyboard(&json_val, width)
+        .ok_or("No storyboard available for this video, use the preview file instead")?;
+    if duration <= 0.0 {
+        return Err("Could not determine video duration".to_string());
+    }
+
+    let per_sheet = board.columns * board.rows;
+    let total_tiles = per_sheet * board.sheets.len() as u32;
+    let sheet_height = board.rows * board.tile_height;
+    let times = sample_times(duration, count);
+    let tiles: Vec<u32> = times
+        .iter()
+        .map(|t| ((t / board.interval) as u32).min(total_tiles - 1))
+        .collect();
+
+    // Only the sheets that contain a sampled frame are needed
+    let last_sheet = tiles.iter().max().map(|t| t / per_sheet).unwrap_or(0) as usize;
+    let client = reqwest::Client::new();
+    let mut downloaded: Vec<PathBuf> = Vec::new();
+    for (sheet_index, sheet_url) in board.sheets.iter().take(last_sheet + 1).enumerate() {
+        let bytes = client
+            .get(sheet_url)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| format!("Failed to download storyboard: {}", e))?
+            .bytes()
+            .await
+            .map_err(|e| format!("Failed to download storyboard: {}", e))?;
+        let sheet_path = dir.join(format!("sheet_{:03}.jpg", sheet_index));
+        fs::write(&sheet_path, &bytes).map_err(|e| format!("Failed to save storyboard: {}", e))?;
+
+        for (index, tile) in tiles.iter().enumerate() {
+            if (tile / per_sheet) as usize != sheet_index {
+                continue;
+            }
+            let within = tile % per_sheet;
+            let _ = app.emit(
+                "filmstrip-frame",
+                FilmstripFrameEvent {
+                    request_id,
+                    index,
+                    time: times[index],
+                    image: sheet_path.to_string_lossy().to_string(),
+                    x: (within % board.columns) * board.tile_width,
+                    y: (within / board.columns) * board.tile_height,
+                    width: board.tile_width,
+                    height: board.tile_height,
+                },
+            );
+        }
+        downloaded.push(sheet_path);
+    }
+
+    // Stack the sheets into one sprite; the last sheet may be partially filled
+    let sheet_width = board.columns * board.tile_width;
+    let sprite_path = dir.join("sprite.jpg");
+    let status = get_ffmpeg(app)?
+        .command()
+        .arg("-y")
+        .arg("-i")
+        .arg(dir.join("sheet_%03d.jpg"))
+        .args([
+            "-vf",
+            &format!(
+                "pad={}:{}:0:0:black,tile=1x{}",
+                sheet_width,
+                sheet_height,
+                downloaded.len()
+            ),
+            "-frames:v",
+            "1",
+            "-q:v",
+            "4",
+        ])
+        .arg(&sprite_path)
+        .status()
+        .map_err(|e| format!("Failed to start ffmpeg: {}", e))?;
+    if !status.success() {
+        return Err("Failed to assemble filmstrip sprite".to_string());
+    }
+
+    let frames = times
+        .into_iter()
+        .zip(tiles)
+        .map(|(time, tile)| {
+            let sheet = tile / per_sheet;
+            let within = tile % per_sheet;
+            FilmstripFrame {
+                time,
+                x: (within % board.columns) * board.tile_width,
+                y: sheet * sheet_height + (within / board.columns) * board.tile_height,
+            }
+        })
+        .collect();
+
+    Ok(FilmstripIndex {
+        source: source.to_string(),
+        sprite: sprite_path.to_string_lossy().to_string(),
+        tile_width: board.tile_width,
+        tile_height: board.tile_height,
+        columns: board.columns,
+        rows: board.rows * downloaded.len() as u32,
+        frames,
+    })
+}
+
+/// Generate (or load from cache) a timeline filmstrip for a local file, a
+/// downloaded preview or a remote URL with storyboards. Frames are streamed as
+/// `filmstrip-frame` events while the sprite is being built.
+#[tauri::command]
+pub async fn generate_filmstrip(
+    app: AppHandle,
+    source: String,
+    count: u32,
+    width: u32,
+    request_id: u64,
+) -> Result<FilmstripIndex, String> {
+    let source = validation::validate_source(&app, &source)?.as_arg();
+    let count = count.clamp(1, MAX_FRAMES);
+    let width = width.clamp(32, 640);
+    let dir = filmstrip_dir(&app, &source, count, width)?;
+    let index_path = dir.join("index.json");
+
+    if let Some(index) = fs::read_to_string(&index_path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<FilmstripIndex>(&content).ok())
+        .filter(|index| Path::new(&index.sprite).exists())
+    {
+        preview::touch(&index_path);
+        return Ok(index);
+    }
+
+    log::debug!("Generating filmstrip for {} ({} frames @ {}px)", source, count, width);
+
+    let result = if Path::new(&source).is_file() {
+        local_filmstrip(&app, &source, count, width, &dir, request_id)
+    } else {
+        storyboard_filmstrip(&app, &source, count, width, &dir, request_id).await
+    };
+
+    let index = match result {
+        Ok(index) => index,
+        Err(e) => {
+            let _ = fs::remove_dir_all(&dir);
+            return Err(e);
+        }
+    };
+
+    let json_str = serde_json::to_string(&index)
+        .map_err(|e| format!("Failed to serialize filmstrip index: {}", e))?;
+    fs::write(&index_path, json_str)
+        .map_err(|e| format!("Failed to write filmstrip index: {}", e))?;
+    if let Some(root) = dir.parent() {
+        enforce_cache_limit(root, CACHE_MAX_BYTES, &dir);
+    }
+
+    Ok(index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn board(width: u64, columns: u64, rows: u64) -> serde_json::Value {
+        json!({
+            "format_note": "storyboard",
+            "width": width,
+            "height": width * 9 / 16,
+            "columns": columns,
+            "rows": rows,
+            "fragments": [{ "url": "https://i.ytimg.com/sb/M0.jpg", "duration": 100.0 }]
+        })
+    }
+
+    #[test]
+    fn picks_the_smallest_storyboard_wide_enough() {
+        let json = json!({ "formats": [board(48, 10, 10), board(160, 5, 5), board(320, 3, 3)] });
+        let picked = select_storyboard(&json, 120).unwrap();
+        assert_eq!(picked.tile_width, 160);
+        assert_eq!(picked.interval, 4.0);
+        assert_eq!(select_storyboard(&json, 640).unwrap().tile_width, 320);
+    }
+
+    #[test]
+    fn counts_frames_up_to_the_first_gap() {
+        let dir = std::env::temp_dir().join(format!("clipme-filmstrip-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        assert_eq!(written_frames(&dir, 5), 0);
+        for index in [0, 1, 2, 4] {
+            fs::write(frame_path(&dir, index), b"jpg").unwrap();
+        }
+        assert_eq!(written_frames(&dir, 5), 3);
+        assert_eq!(written_frames(&dir, 2), 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn tiles_only_the_written_frames() {
+        let times = sample_times(100.0, 25);
+        let frames = tile_frames(&times[..12], 10, 160, 90);
+        assert_eq!(frames.len(), 12);
+        assert_eq!((frames[9].x, frames[9].y), (1440, 0));
+        assert_eq!((frames[11].x, frames[11].y), (160, 90));
+        assert_eq!(frames[11].time, 46.0);
+    }
+
+    #[test]
+    fn drops_zero_sized_storyboards() {
+        let json = json!({ "formats": [board(160, 0, 5), board(160, 5, 0), board(0, 5, 5)] });
+        assert!(select_storyboard(&json, 120).is_none());
+    }
+}
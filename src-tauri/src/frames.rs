@@ -0,0 +1,405 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tauri::AppHandle;
+
+use crate::{get_ffprobe, metadata_cache, parse_frame_rate, validation};
+
+/// Seconds of packets read around a time when looking for frames
+const FRAME_WINDOW_SECS: f64 = 1.0;
+/// Keyframes can be far apart (long GOPs in screen recordings)
+const KEYFRAME_WINDOW_SECS: f64 = 20.0;
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SnapMode {
+    #[default]
+    Nearest,
+    Before,
+    After,
+}
+
+/// Timing facts about a source's first video stream
+#[derive(Debug, Clone, Copy)]
+struct StreamTiming {
+    fps: f64,
+    time_base_num: u64,
+    time_base_den: u64,
+    start_time: f64,
+    /// `r_frame_rate` and `avg_frame_rate` disagree, so frame times must be read, not computed
+    variable_frame_rate: bool,
+}
+
+#[derive(Serialize, Debug, Clone, Copy)]
+pub struct FrameSnap {
+    time: f64,
+    frame_duration: f64,
+    exact: bool,
+}
+
+/// Format a time for ffmpeg/yt-dlp with fixed precision instead of `f64::to_string`
+/// (which can produce `1e-7` or 17 significant digits)
+pub fn format_time_arg(seconds: f64) -> String {
+    let formatted = format!("{:.6}", seconds.max(0.0));
+    formatted
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+fn probe_stream_timing(app: &AppHandle, source: &str) -> Result<StreamTiming, String> {
+    let output = get_ffprobe(app)?
+        .command()
+        .args([
+            "-v",
+            "quiet",
+            "-select_streams",
+            "v:0",
+            "-show_entries",
+            "stream=time_base,r_frame_rate,avg_frame_rate,start_time",
+            "-of",
+            "json",
+            source,
+        ])
+        .output()
+        .map_err(|e| format!("Failed to execute ffprobe: {}", e))?;
+    if !output.status.success() {
+        return Err("Failed to read stream timing".to_string());
+    }
+
+    let json_val: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| format!("Failed to parse ffprobe output: {}", e))?;
+    let stream = json_val["streams"]
+        .as_array()
+        .and_then(|s| s.first())
+        .ok_or("Source has no video stream")?;
+
+    let r_rate = stream["r_frame_rate"].as_str().and_then(parse_frame_rate);
+    let avg_rate = stream["avg_frame_rate"].as_str().and_then(parse_frame_rate);
+    let fps = avg_rate
+        .filter(|r| *r > 0.0)
+        .or(r_rate)
+        .filter(|r| *r > 0.0)
+        .ok_or("Could not determine frame rate")?;
+    let (tb_num, tb_den) = stream["time_base"]
+        .as_str()
+        .and_then(|tb| tb.split_once('/'))
+        .and_then(|(n, d)| Some((n.parse().ok()?, d.parse().ok()?)))
+        .unwrap_or((1, 1000));
+
+    Ok(StreamTiming {
+        fps,
+        time_base_num: tb_num,
+        time_base_den: tb_den,
+        start_time: stream["start_time"]
+            .as_str()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(0.0),
+        variable_frame_rate: match (r_rate, avg_rate) {
+            (Some(r), Some(avg)) => (r - avg).abs() > 0.01,
+            _ => false,
+        },
+    })
+}
+
+/// Packet timestamps (and keyframe flags) of the video stream inside a window
+fn read_frame_times(
+    app: &AppHandle,
+    source: &str,
+    from: f64,
+    to: f64,
+) -> Result<Vec<(f64, bool)>, String> {
+    let interval = format!("{}%{}", format_time_arg(from), format_time_arg(to));
+    let output = get_ffprobe(app)?
+        .command()
+        .args([
+            "-v",
+            "quiet",
+            "-select_streams",
+            "v:0",
+            "-read_intervals",
+            &interval,
+            "-show_entries",
+            "packet=pts_time,flags",
+            "-of",
+            "json",
+            source,
+        ])
+        .output()
+        .map_err(|e| format!("Failed to execute ffprobe: {}", e))?;
+    if !output.status.success() {
+        return Err("Failed to read frame timestamps".to_string());
+    }
+
+    let json_val: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| format!("Failed to parse ffprobe output: {}", e))?;
+    let mut times: Vec<(f64, bool)> = json_val["packets"]
+        .as_array()
+        .map(|packets| {
+            packets
+                .iter()
+                .filter_map(|p| {
+                    let pts = p["pts_time"].as_str()?.parse::<f64>().ok()?;
+                    let key = p["flags"].as_str().is_some_and(|f| f.starts_with('K'));
+                    Some((pts, key))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    // Packets arrive in decode order; B-frames make that differ from display order
+    times.sort_by(|a, b| a.0.total_cmp(&b.0));
+    Ok(times)
+}
+
+/// Choose a timestamp from `candidates` relative to `time`
+fn pick(candidates: impl Iterator<Item = f64>, time: f64, mode: SnapMode) -> Option<f64> {
+    // Treat sub-microsecond differences as equal so exact hits aren't skipped
+    const EPSILON: f64 = 1e-6;
+    let mut best: Option<f64> = None;
+    for t in candidates {
+        let better = match mode {
+            SnapMode::Nearest => best.is_none_or(|b| (t - time).abs() < (b - time).abs()),
+            SnapMode::Before => t <= time + EPSILON && best.is_none_or(|b| t > b),
+            SnapMode::After => t >= time - EPSILON && best.is_none_or(|b| t < b),
+        };
+        if better {
+            best = Some(t);
+        }
+    }
+    best
+}
+
+/// Length to pass to ffmpeg's `-t` for a frame-aligned range. ffmpeg keeps every
+/// frame whose timestamp is below start + duration, so ending half a frame early
+/// keeps the frame before `end` and reliably drops the one that starts at `end`.
+pub fn output_duration(start: f64, end: f64, frame_duration: Option<f64>) -> f64 {
+    let duration = end - start;
+    match frame_duration {
+        Some(frame) if duration > frame => duration - frame / 2.0,
+        _ => duration,
+    }
+}
+
+/// Snap onto the constant frame-rate grid, rounded to the stream's time base
+fn snap_to_grid(timing: &StreamTiming, time: f64, mode: SnapMode) -> f64 {
+    let frames = (time - timing.start_time) * timing.fps;
+    let index = match mode {
+        SnapMode::Nearest => frames.round(),
+        SnapMode::Before => (frames + 1e-6).floor(),
+        SnapMode::After => (frames - 1e-6).ceil(),
+    }
+    .max(0.0);
+    let seconds = timing.start_time + index / timing.fps;
+    let tb = timing.time_base_num as f64 / timing.time_base_den.max(1) as f64;
+    (seconds / tb).round() * tb
+}
+
+/// Snap `time` to the start of a frame of a local file
+pub fn snap_local(
+    app: &AppHandle,
+    source: &str,
+    time: f64,
+    mode: SnapMode,
+) -> Result<FrameSnap, String> {
+    let timing = probe_stream_timing(app, source)?;
+    snap_with_timing(app, source, &timing, time, mode)
+}
+
+/// Snap against already probed stream timing, so callers snapping several
+/// times in one source probe it once
+fn snap_with_timing(
+    app: &AppHandle,
+    source: &str,
+    timing: &StreamTiming,
+    time: f64,
+    mode: SnapMode,
+) -> Result<FrameSnap, String> {
+    let frame_duration = 1.0 / timing.fps;
+    let window = FRAME_WINDOW_SECS.max(frame_duration * 2.0);
+    let frame_times = read_frame_times(app, source, (time - window).max(0.0), time + window)?;
+
+    match pick(frame_times.iter().map(|(t, _)| *t), time, mode) {
+        Some(snapped) => Ok(FrameSnap {
+            time: snapped,
+            frame_duration,
+            exact: true,
+        }),
+        None => Ok(FrameSnap {
+            time: snap_to_grid(timing, time, mode),
+            frame_duration,
+            exact: !timing.variable_frame_rate,
+        }),
+    }
+}
+
+/// Frame rate of a remote source's best video format, from the metadata cache
+fn remote_fps(app: &AppHandle, url: &str) -> Option<f64> {
+    let metadata = metadata_cache::lookup(app, url, u64::MAX)?;
+    metadata
+        .available_formats
+        .iter()
+        .filter(|f| f.vcodec.is_some())
+        .max_by_key(|f| f.height.unwrap_or(0))
+        .and_then(|f| f.fps)
+}
+
+/// Normalize a clip range to frame boundaries before it reaches ffmpeg or yt-dlp.
+/// Local files use real frame timestamps; remote sources use the frame grid of
+/// the cached format info. Anything that can't be probed is returned unchanged.
+pub fn snap_clip_range(
+    app: &AppHandle,
+    source: &str,
+    start: f64,
+    end: f64,
+) -> (f64, f64, Option<f64>) {
+    if Path::new(source).is_file() {
+        let snapped = probe_stream_timing(app, source).and_then(|timing| {
+            Ok((
+                snap_with_timing(app, source, &timing, start, SnapMode::Nearest)?,
+                snap_with_timing(app, source, &timing, end, SnapMode::Nearest)?,
+            ))
+        });
+        if let Ok((s, e)) = snapped {
+            if e.time > s.time {
+                return (s.time, e.time, Some(s.frame_duration));
+            }
+        }
+        return (start, end, None);
+    }
+
+    if let Some(fps) = remote_fps(app, source).filter(|fps| *fps > 0.0) {
+        let timing = StreamTiming {
+            fps,
+            time_base_num: 1,
+            time_base_den: 1_000_000,
+            start_time: 0.0,
+            variable_frame_rate: false,
+        };
+        let s = snap_to_grid(&timing, start, SnapMode::Nearest);
+        let e = snap_to_grid(&timing, end, SnapMode::Nearest);
+        if e > s {
+            return (s, e, Some(1.0 / fps));
+        }
+    }
+    (start, end, None)
+}
+
+#[tauri::command]
+pub async fn snap_to_frame(
+    app: AppHandle,
+    source: String,
+    time: f64,
+    mode: Option<SnapMode>,
+) -> Result<FrameSnap, String> {
+    let source = validation::validate_source(&app, &source)?.as_arg();
+    let mode = mode.unwrap_or_default();
+    if Path::new(&source).is_file() {
+        return snap_local(&app, &source, time, mode);
+    }
+
+    let fps = remote_fps(&app, &source)
+        .filter(|fps| *fps > 0.0)
+        .ok_or("Frame rate unknown for this source, load its metadata first")?;
+    let timing = StreamTiming {
+        fps,
+        time_base_num: 1,
+        time_base_den: 1_000_000,
+        start_time: 0.0,
+        variable_frame_rate: false,
+    };
+    Ok(FrameSnap {
+        time: snap_to_grid(&timing, time, mode),
+        frame_duration: 1.0 / fps,
+        exact: false,
+    })
+}
+
+/// Closest keyframe to `time` in a local file, searching before, after or both ways
+#[tauri::command]
+pub async fn nearest_keyframe(
+    app: AppHandle,
+    source: String,
+    time: f64,
+    mode: Option<SnapMode>,
+) -> Result<f64, String> {
+    let source = validation::validate_source(&app, &source)?.as_arg();
+    if !Path::new(&source).is_file() {
+        return Err("Keyframes can only be read from local files".to_string());
+    }
+    let mode = mode.unwrap_or_default();
+    let from = match mode {
+        SnapMode::After => time,
+        _ => time - KEYFRAME_WINDOW_SECS,
+    };
+    let to = match mode {
+        SnapMode::Before => time + FRAME_WINDOW_SECS,
+        _ => time + KEYFRAME_WINDOW_SECS,
+    };
+    let frame_times = read_frame_times(&app, &source, from.max(0.0), to)?;
+    let keyframes = frame_times.iter().filter(|(_, key)| *key).map(|(t, _)| *t);
+    pick(keyframes, time, mode).ok_or_else(|| "No keyframe found near that time".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timing(fps: f64, time_base_den: u64, start_time: f64) -> StreamTiming {
+        StreamTiming {
+            fps,
+            time_base_num: 1,
+            time_base_den,
+            start_time,
+            variable_frame_rate: false,
+        }
+    }
+
+    #[test]
+    fn formats_times_without_exponents() {
+        assert_eq!(format_time_arg(0.0000001), "0");
+        assert_eq!(format_time_arg(12.5), "12.5");
+        assert_eq!(format_time_arg(3.0), "3");
+        assert_eq!(format_time_arg(1.0 / 3.0), "0.333333");
+        assert_eq!(format_time_arg(-2.0), "0");
+    }
+
+    #[test]
+    fn snaps_to_the_frame_grid() {
+        let assert_snap = |timing: &StreamTiming, time, mode, expected: f64| {
+            let snapped = snap_to_grid(timing, time, mode);
+            assert!(
+                (snapped - expected).abs() < 1e-9,
+                "{} != {}",
+                snapped,
+                expected
+            );
+        };
+        let ntsc = timing(30000.0 / 1001.0, 90000, 0.0);
+        assert_snap(&ntsc, 1.0, SnapMode::Nearest, 1.001);
+        assert_snap(&ntsc, 1.0, SnapMode::Before, 29.0 * 1001.0 / 30000.0);
+        assert_snap(&ntsc, 1.001, SnapMode::After, 1.001);
+
+        let offset = timing(25.0, 1000, 0.02);
+        assert_snap(&offset, 0.05, SnapMode::Before, 0.02);
+        assert_snap(&offset, 0.05, SnapMode::After, 0.06);
+        assert_snap(&offset, 0.0, SnapMode::Nearest, 0.02);
+    }
+
+    #[test]
+    fn picks_frames_relative_to_a_time() {
+        let frames = [0.0, 0.04, 0.08, 0.12];
+        let at = |time, mode| pick(frames.iter().copied(), time, mode);
+        assert_eq!(at(0.05, SnapMode::Nearest), Some(0.04));
+        assert_eq!(at(0.05, SnapMode::Before), Some(0.04));
+        assert_eq!(at(0.05, SnapMode::After), Some(0.08));
+        assert_eq!(at(0.08, SnapMode::After), Some(0.08));
+        assert_eq!(at(0.2, SnapMode::After), None);
+        assert_eq!(pick(std::iter::empty(), 1.0, SnapMode::Nearest), None);
+    }
+
+    #[test]
+    fn ends_half_a_frame_early() {
+        assert!((output_duration(1.0, 3.0, Some(0.04)) - 1.98).abs() < 1e-9);
+        assert_eq!(output_duration(1.0, 3.0, None), 2.0);
+        assert_eq!(output_duration(1.0, 1.02, Some(0.04)), 1.02 - 1.0);
+    }
+}
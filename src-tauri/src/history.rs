@@ -0,0 +1,1415 @@
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager, State, Url};
+
+use crate::{
+    entitlements, metadata_cache, render_clip, resolve_output_dir, run_clip_job,
+    run_multi_clip_job, toolchain, AppState, ClipJob, MultiClipJob,
+};
+
+const DB_FILE: &str = "history.db";
+/// History before the database; imported once and kept as a backup
+const LEGACY_FILE: &str = "download_history.json";
+const DEFAULT_PAGE_SIZE: u32 = 50;
+const MAX_PAGE_SIZE: u32 = 500;
+/// How deep reconciliation looks for moved files below the download folder
+const MAX_SEARCH_DEPTH: usize = 6;
+
+/// Schema changes, applied in order; `PRAGMA user_version` records how many ran
+const MIGRATIONS: &[&str] = &[
+    r#"
+CREATE TABLE history (
+    id TEXT PRIMARY KEY,
+    title TEXT NOT NULL,
+    url TEXT NOT NULL,
+    thumbnail TEXT,
+    duration REAL NOT NULL,
+    quality TEXT NOT NULL,
+    format TEXT NOT NULL,
+    file_path TEXT NOT NULL,
+    file_size INTEGER,
+    downloaded_at TEXT NOT NULL,
+    site TEXT NOT NULL
+);
+CREATE INDEX history_downloaded_at ON history(downloaded_at);
+CREATE VIRTUAL TABLE history_fts USING fts5(
+    title, url, content='history', content_rowid='rowid'
+);
+CREATE TRIGGER history_ai AFTER INSERT ON history BEGIN
+    INSERT INTO history_fts(rowid, title, url) VALUES (new.rowid, new.title, new.url);
+END;
+CREATE TRIGGER history_ad AFTER DELETE ON history BEGIN
+    INSERT INTO history_fts(history_fts, rowid, title, url)
+    VALUES ('delete', old.rowid, old.title, old.url);
+END;
+CREATE TRIGGER history_au AFTER UPDATE ON history BEGIN
+    INSERT INTO history_fts(history_fts, rowid, title, url)
+    VALUES ('delete', old.rowid, old.title, old.url);
+    INSERT INTO history_fts(rowid, title, url) VALUES (new.rowid, new.title, new.url);
+END;
+"#,
+    r#"
+ALTER TABLE history ADD COLUMN start_time REAL;
+ALTER TABLE history ADD COLUMN end_time REAL;
+ALTER TABLE history ADD COLUMN file_hash TEXT;
+ALTER TABLE history ADD COLUMN status TEXT NOT NULL DEFAULT 'ok';
+"#,
+    r#"
+ALTER TABLE history ADD COLUMN job_id TEXT;
+ALTER TABLE history ADD COLUMN job TEXT;
+ALTER TABLE history ADD COLUMN started_at TEXT;
+ALTER TABLE history ADD COLUMN elapsed_ms INTEGER;
+ALTER TABLE history ADD COLUMN tools TEXT;
+ALTER TABLE history ADD COLUMN error TEXT;
+ALTER TABLE history ADD COLUMN error_code TEXT;
+CREATE INDEX history_job_id ON history(job_id);
+"#,
+];
+
+// Download History Item
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DownloadHistoryItem {
+    pub id: String,
+    pub title: String,
+    pub url: String,
+    pub thumbnail: Option<String>,
+    pub duration: f64,
+    pub quality: String,
+    pub format: String,
+    #[serde(rename = "filePath")]
+    pub file_path: String,
+    #[serde(rename = "fileSize")]
+    pub file_size: Option<u64>,
+    #[serde(rename = "downloadedAt")]
+    pub downloaded_at: String,
+    /// Clip range in the source, so the file can be re-created
+    #[serde(rename = "startTime", default)]
+    pub start_time: Option<f64>,
+    #[serde(rename = "endTime", default)]
+    pub end_time: Option<f64>,
+    #[serde(default)]
+    pub status: FileStatus,
+    /// Shared by the files of one multi-clip job
+    #[serde(rename = "jobId", default)]
+    pub job_id: Option<String>,
+    /// Everything needed to run the job again
+    #[serde(default)]
+    pub job: Option<JobParams>,
+    #[serde(rename = "startedAt", default)]
+    pub started_at: Option<String>,
+    #[serde(rename = "elapsedMs", default)]
+    pub elapsed_ms: Option<u64>,
+    /// Tool name -> version used for the job
+    #[serde(default)]
+    pub tools: BTreeMap<String, String>,
+    #[serde(default)]
+    pub error: Option<String>,
+    #[serde(rename = "errorCode", default)]
+    pub error_code: Option<String>,
+}
+
+/// Parameters of a recorded job
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum JobParams {
+    Clip(ClipJob),
+    MultiClip(MultiClipJob),
+}
+
+impl PartialEq for JobParams {
+    fn eq(&self, other: &Self) -> bool {
+        serde_json::to_value(self).ok() == serde_json::to_value(other).ok()
+    }
+}
+
+/// Whether an entry's file was found at its recorded path, or never produced
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FileStatus {
+    #[default]
+    Ok,
+    Missing,
+    /// The job never produced a file
+    Failed,
+}
+
+impl FileStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            FileStatus::Ok => "ok",
+            FileStatus::Missing => "missing",
+            FileStatus::Failed => "failed",
+        }
+    }
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct RelocatedEntry {
+    id: String,
+    from: String,
+    to: String,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct ReconcileReport {
+    checked: usize,
+    /// Ids whose file couldn't be found anywhere
+    missing: Vec<String>,
+    relocated: Vec<RelocatedEntry>,
+}
+
+/// Filters for `get_download_history`; every field is optional
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub struct HistoryQuery {
+    /// Full-text search over title and URL
+    pub search: Option<String>,
+    /// Inclusive bounds on `downloadedAt` (ISO 8601)
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub quality: Option<String>,
+    pub format: Option<String>,
+    /// Host without `www.`, e.g. `youtube.com`; `local` for files
+    pub site: Option<String>,
+    pub offset: Option<u32>,
+    pub limit: Option<u32>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct HistoryPage {
+    pub items: Vec<DownloadHistoryItem>,
+    /// Matches across all pages
+    pub total: u64,
+    pub offset: u32,
+    pub limit: u32,
+}
+
+/// Distinct values for the filter dropdowns
+#[derive(Serialize, Debug)]
+pub struct HistoryFilters {
+    qualities: Vec<String>,
+    formats: Vec<String>,
+    sites: Vec<String>,
+}
+
+/// Source site used for filtering: the URL host without `www.`/`m.`
+pub fn site_of(url: &str) -> String {
+    let Some(host) = Url::parse(url)
+        .ok()
+        .filter(|u| matches!(u.scheme(), "http" | "https"))
+        .and_then(|u| u.host_str().map(str::to_lowercase))
+    else {
+        return "local".to_string();
+    };
+    let host = host
+        .strip_prefix("www.")
+        .or_else(|| host.strip_prefix("m."))
+        .unwrap_or(&host);
+    match host {
+        "youtu.be" | "music.youtube.com" => "youtube.com".to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// Turn user input into an FTS5 query: every word must match as a prefix.
+/// Words are quoted so FTS syntax (`OR`, `-`, `:`) is taken literally.
+fn fts_query(search: &str) -> Option<String> {
+    let terms: Vec<String> = search
+        .split_whitespace()
+        .map(|word| format!("\"{}\"*", word.replace('"', "\"\"")))
+        .collect();
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+fn read_legacy(path: &Path) -> Result<Vec<DownloadHistoryItem>, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read history: {}", e))?;
+    serde_json::from_str(&content).map_err(|e| format!("Failed to parse history: {}", e))
+}
+
+/// Bring the schema up to date. The first migration also imports the legacy
+/// JSON history in the same transaction, so it happens exactly once.
+fn migrate(conn: &mut Connection, legacy: Option<&Path>) -> Result<(), String> {
+    let version: usize = conn
+        .query_row("PRAGMA user_version", [], |row| row.get(0))
+        .map_err(|e| format!("Failed to read history version: {}", e))?;
+    if version >= MIGRATIONS.len() {
+        return Ok(());
+    }
+
+    let legacy_items = match legacy.filter(|path| version == 0 && path.exists()) {
+        Some(path) => match read_legacy(path) {
+            Ok(items) => items,
+            Err(e) => {
+                // Keep the unreadable file for recovery instead of dropping it
+                let corrupt = path.with_extension("json.corrupt");
+                log::warn!("{}; keeping it as {:?}", e, corrupt);
+                let _ = fs::rename(path, &corrupt);
+                Vec::new()
+            }
+        },
+        None => Vec::new(),
+    };
+
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start history migration: {}", e))?;
+    for (index, sql) in MIGRATIONS.iter().enumerate().skip(version) {
+        tx.execute_batch(sql)
+            .map_err(|e| format!("History migration {} failed: {}", index + 1, e))?;
+    }
+    for item in &legacy_items {
+        insert_item(&tx, item, true)?;
+    }
+    tx.pragma_update(None, "user_version", MIGRATIONS.len())
+        .map_err(|e| format!("Failed to update history version: {}", e))?;
+    tx.commit()
+        .map_err(|e| format!("Failed to commit history migration: {}", e))?;
+
+    if let Some(path) = legacy.filter(|path| path.exists()) {
+        log::info!("Imported {} history items from JSON", legacy_items.len());
+        let _ = fs::rename(path, path.with_extension("json.migrated"));
+    }
+    Ok(())
+}
+
+/// Insert or, for an existing id, update (or skip) the entry. An upsert rather
+/// than `INSERT OR REPLACE`, which would bypass the FTS delete trigger.
+fn insert_item(
+    conn: &Connection,
+    item: &DownloadHistoryItem,
+    keep_existing: bool,
+) -> Result<(), String> {
+    let on_conflict = if keep_existing {
+        "DO NOTHING"
+    } else {
+        "DO UPDATE SET title = excluded.title, url = excluded.url,
+            thumbnail = excluded.thumbnail, duration = excluded.duration,
+            quality = excluded.quality, format = excluded.format,
+            file_path = excluded.file_path, file_size = excluded.file_size,
+            downloaded_at = excluded.downloaded_at, site = excluded.site,
+            start_time = excluded.start_time, end_time = excluded.end_time,
+            file_hash = NULL, status = excluded.status, job_id = excluded.job_id,
+            job = excluded.job, started_at = excluded.started_at,
+            elapsed_ms = excluded.elapsed_ms, tools = excluded.tools,
+            error = excluded.error, error_code = excluded.error_code"
+    };
+    let job = item
+        .job
+        .as_ref()
+        .and_then(|job| serde_json::to_string(job).ok());
+    let tools = serde_json::to_string(&item.tools).ok();
+    conn.execute(
+        &format!(
+            "INSERT INTO history (id, title, url, thumbnail, duration, quality, format,
+                file_path, file_size, downloaded_at, site, start_time, end_time, status,
+                job_id, job, started_at, elapsed_ms, tools, error, error_code)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14,
+                ?15, ?16, ?17, ?18, ?19, ?20, ?21)
+             ON CONFLICT(id) {}",
+            on_conflict
+        ),
+        params![
+            item.id,
+            item.title,
+            item.url,
+            item.thumbnail,
+            item.duration,
+            item.quality,
+            item.format,
+            item.file_path,
+            item.file_size,
+            item.downloaded_at,
+            site_of(&item.url),
+            item.start_time,
+            item.end_time,
+            item.status.as_str(),
+            item.job_id,
+            job,
+            item.started_at,
+            item.elapsed_ms,
+            tools,
+            item.error,
+            item.error_code,
+        ],
+    )
+    .map_err(|e| format!("Failed to save history: {}", e))?;
+    Ok(())
+}
+
+fn item_from_row(row: &Row) -> rusqlite::Result<DownloadHistoryItem> {
+    Ok(DownloadHistoryItem {
+        id: row.get("id")?,
+        title: row.get("title")?,
+        url: row.get("url")?,
+        thumbnail: row.get("thumbnail")?,
+        duration: row.get("duration")?,
+        quality: row.get("quality")?,
+        format: row.get("format")?,
+        file_path: row.get("file_path")?,
+        file_size: row.get("file_size")?,
+        downloaded_at: row.get("downloaded_at")?,
+        start_time: row.get("start_time")?,
+        end_time: row.get("end_time")?,
+        status: match row.get::<_, String>("status")?.as_str() {
+            "missing" => FileStatus::Missing,
+            "failed" => FileStatus::Failed,
+            _ => FileStatus::Ok,
+        },
+        job_id: row.get("job_id")?,
+        // Records written by an older version may not parse; they just can't be re-run
+        job: row
+            .get::<_, Option<String>>("job")?
+            .and_then(|job| serde_json::from_str(&job).ok()),
+        started_at: row.get("started_at")?,
+        elapsed_ms: row.get("elapsed_ms")?,
+        tools: row
+            .get::<_, Option<String>>("tools")?
+            .and_then(|tools| serde_json::from_str(&tools).ok())
+            .unwrap_or_default(),
+        error: row.get("error")?,
+        error_code: row.get("error_code")?,
+    })
+}
+
+/// A date-only upper bound includes that whole day; compared as text,
+/// `2024-03-03` sorts before every timestamp on the 3rd
+fn end_of_day(to: &str) -> String {
+    if to.len() == 10 && !to.contains('T') {
+        format!("{}T23:59:59.999Z", to)
+    } else {
+        to.to_string()
+    }
+}
+
+/// WHERE clause and parameters for `query`
+fn filter_clause(query: &HistoryQuery) -> (String, Vec<String>) {
+    let mut conditions = Vec::new();
+    let mut values = Vec::new();
+    if let Some(search) = query.search.as_deref().and_then(fts_query) {
+        conditions.push("rowid IN (SELECT rowid FROM history_fts WHERE history_fts MATCH ?)");
+        values.push(search);
+    }
+    let to = query.to.as_deref().map(end_of_day);
+    let filters = [
+        ("downloaded_at >= ?", &query.from),
+        ("downloaded_at <= ?", &to),
+        ("quality = ?", &query.quality),
+        ("format = ?", &query.format),
+        ("site = ?", &query.site),
+    ];
+    for (condition, value) in filters {
+        if let Some(value) = value.as_ref().filter(|v| !v.is_empty()) {
+            conditions.push(condition);
+            values.push(value.clone());
+        }
+    }
+    let clause = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", conditions.join(" AND "))
+    };
+    (clause, values)
+}
+
+/// Newest first, filtered and paginated
+fn query(conn: &Connection, query: &HistoryQuery) -> Result<HistoryPage, String> {
+    let (clause, values) = filter_clause(query);
+    let offset = query.offset.unwrap_or(0);
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+
+    let total: u64 = conn
+        .query_row(
+            &format!("SELECT COUNT(*) FROM history {}", clause),
+            params_from_iter(&values),
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Failed to count history: {}", e))?;
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT * FROM history {} ORDER BY downloaded_at DESC, rowid DESC LIMIT {} OFFSET {}",
+            clause, limit, offset
+        ))
+        .map_err(|e| format!("Failed to query history: {}", e))?;
+    let items = stmt
+        .query_map(params_from_iter(&values), item_from_row)
+        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+        .map_err(|e| format!("Failed to read history: {}", e))?;
+
+    Ok(HistoryPage {
+        items,
+        total,
+        offset,
+        limit,
+    })
+}
+
+fn distinct(conn: &Connection, column: &str) -> Result<Vec<String>, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT DISTINCT {0} FROM history ORDER BY {0}",
+            column
+        ))
+        .map_err(|e| format!("Failed to query history: {}", e))?;
+    let values = stmt
+        .query_map([], |row| row.get(0))
+        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<String>>>())
+        .map_err(|e| format!("Failed to read history: {}", e))?;
+    Ok(values)
+}
+
+pub fn get_item(conn: &Connection, id: &str) -> Result<Option<DownloadHistoryItem>, String> {
+    conn.query_row("SELECT * FROM history WHERE id = ?1", [id], item_from_row)
+        .optional()
+        .map_err(|e| format!("Failed to read history: {}", e))
+}
+
+/// Point an entry at `path`, recording the size and hash used to find the
+/// file again if it's moved
+fn record_file(conn: &Connection, id: &str, path: &Path) -> Result<(), String> {
+    let size = fs::metadata(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+        .len();
+    let hash = toolchain::sha256_file(path)?;
+    conn.execute(
+        "UPDATE history SET file_path = ?2, file_size = ?3, file_hash = ?4, status = 'ok'
+         WHERE id = ?1",
+        params![id, path.to_string_lossy(), size, hash],
+    )
+    .map_err(|e| format!("Failed to update history: {}", e))?;
+    Ok(())
+}
+
+fn set_status(conn: &Connection, id: &str, status: FileStatus) -> Result<(), String> {
+    conn.execute(
+        "UPDATE history SET status = ?2 WHERE id = ?1",
+        params![id, status.as_str()],
+    )
+    .map_err(|e| format!("Failed to update history: {}", e))?;
+    Ok(())
+}
+
+/// Regular files below `root` by size. Symlinks aren't followed.
+pub fn index_files(root: &Path) -> HashMap<u64, Vec<PathBuf>> {
+    let mut index: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+    let mut pending = vec![(root.to_path_buf(), 0)];
+    while let Some((dir, depth)) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if file_type.is_dir() && depth < MAX_SEARCH_DEPTH {
+                pending.push((entry.path(), depth + 1));
+            } else if file_type.is_file() {
+                if let Ok(meta) = entry.metadata() {
+                    index.entry(meta.len()).or_default().push(entry.path());
+                }
+            }
+        }
+    }
+    index
+}
+
+/// Check every entry against the disk. Files still in place get a fingerprint
+/// if they lack one; missing files are looked for below `root` by size, then
+/// confirmed by hash; anything not found is marked missing.
+fn reconcile(conn: &mut Connection, root: &Path) -> Result<ReconcileReport, String> {
+    let entries: Vec<(String, String, Option<u64>, Option<String>)> = conn
+        .prepare("SELECT id, file_path, file_size, file_hash FROM history WHERE status != 'failed'")
+        .and_then(|mut stmt| {
+            stmt.query_map([], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })?
+            .collect()
+        })
+        .map_err(|e| format!("Failed to read history: {}", e))?;
+
+    // Files that already belong to an entry can't be another entry's match
+    let mut claimed: HashSet<PathBuf> = entries
+        .iter()
+        .map(|(_, path, _, _)| PathBuf::from(path))
+        .filter(|path| path.is_file())
+        .collect();
+    let mut index = None;
+    let mut report = ReconcileReport {
+        checked: entries.len(),
+        ..Default::default()
+    };
+
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to update history: {}", e))?;
+    for (id, file_path, size, hash) in entries {
+        let path = Path::new(&file_path);
+        if path.is_file() {
+            if hash.is_none() || size.is_none() {
+                record_file(&tx, &id, path)?;
+            } else {
+                set_status(&tx, &id, FileStatus::Ok)?;
+            }
+            continue;
+        }
+
+        let found = match (size, hash) {
+            (Some(size), Some(hash)) => index
+                .get_or_insert_with(|| index_files(root))
+                .get(&size)
+                .and_then(|candidates| {
+                    candidates.iter().find(|candidate| {
+                        !claimed.contains(*candidate)
+                            && toolchain::sha256_file(candidate).is_ok_and(|h| h == hash)
+                    })
+                })
+                .cloned(),
+            _ => None,
+        };
+        match found {
+            Some(new_path) => {
+                record_file(&tx, &id, &new_path)?;
+                report.relocated.push(RelocatedEntry {
+                    id,
+                    from: file_path,
+                    to: new_path.to_string_lossy().to_string(),
+                });
+                claimed.insert(new_path);
+            }
+            None => {
+                set_status(&tx, &id, FileStatus::Missing)?;
+                report.missing.push(id);
+            }
+        }
+    }
+    tx.commit()
+        .map_err(|e| format!("Failed to update history: {}", e))?;
+    Ok(report)
+}
+
+/// UTC timestamp in the same form as JavaScript's `toISOString`, so stored
+/// dates sort as text
+pub fn iso8601(time: SystemTime) -> String {
+    let millis = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default();
+    let (days, ms_of_day) = (millis.div_euclid(86_400_000), millis.rem_euclid(86_400_000));
+    // Civil date from days since the epoch (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        ms_of_day / 3_600_000,
+        ms_of_day / 60_000 % 60,
+        ms_of_day / 1000 % 60,
+        ms_of_day % 1000
+    )
+}
+
+/// What kind of failure ended a job; its code is stored for filtering and
+/// bug reports
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ErrorKind {
+    Cancelled,
+    Toolchain,
+    InvalidInput,
+    EncodeFailed,
+    DownloadFailed,
+    Io,
+    Unknown,
+}
+
+impl ErrorKind {
+    pub fn code(self) -> &'static str {
+        match self {
+            ErrorKind::Cancelled => "cancelled",
+            ErrorKind::Toolchain => "toolchain",
+            ErrorKind::InvalidInput => "invalid_input",
+            ErrorKind::EncodeFailed => "encode_failed",
+            ErrorKind::DownloadFailed => "download_failed",
+            ErrorKind::Io => "io",
+            ErrorKind::Unknown => "unknown",
+        }
+    }
+}
+
+/// A failed render: the message for the user and the kind for history.
+/// Plain string errors convert as `Unknown`; failure sites that know better
+/// build one with `JobError::new`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JobError {
+    pub kind: ErrorKind,
+    pub message: String,
+}
+
+impl JobError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for JobError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// Tag a string error with its kind: `result.kind(ErrorKind::Io)?`
+pub trait WithKind<T> {
+    fn kind(self, kind: ErrorKind) -> Result<T, JobError>;
+}
+
+impl<T, E: Into<String>> WithKind<T> for Result<T, E> {
+    fn kind(self, kind: ErrorKind) -> Result<T, JobError> {
+        self.map_err(|e| JobError::new(kind, e))
+    }
+}
+
+impl From<String> for JobError {
+    fn from(message: String) -> Self {
+        Self::new(ErrorKind::Unknown, message)
+    }
+}
+
+impl From<&str> for JobError {
+    fn from(message: &str) -> Self {
+        Self::new(ErrorKind::Unknown, message)
+    }
+}
+
+/// How a job ran, shared by all of its entries
+struct JobRun {
+    job_id: String,
+    started_at: String,
+    finished_at: String,
+    elapsed_ms: u64,
+    tools: BTreeMap<String, String>,
+}
+
+fn thumbnail_for(url: &str) -> Option<String> {
+    metadata_cache::youtube_video_id(url)
+        .map(|id| format!("https://i.ytimg.com/vi/{}/mqdefault.jpg", id))
+}
+
+/// History entries for a finished job: one per output file, or a single
+/// failed entry carrying the error
+fn job_entries(
+    params: &JobParams,
+    run: &JobRun,
+    outcome: Result<&[String], (&JobError, bool)>,
+) -> Vec<DownloadHistoryItem> {
+    // (title, url, quality, format, ranges)
+    let (title, url, quality, format, ranges): (&str, &str, &str, &str, Vec<(f64, f64)>) =
+        match params {
+            JobParams::Clip(job) => (
+                &job.title,
+                &job.url,
+                &job.quality,
+                &job.format,
+                vec![(job.start, job.end)],
+            ),
+            JobParams::MultiClip(job) => (
+                &job.title,
+                &job.url,
+                &job.quality,
+                &job.format,
+                job.segments.iter().map(|s| (s.start, s.end)).collect(),
+            ),
+        };
+    let multi = matches!(params, JobParams::MultiClip(_));
+    let entry = |id: String, title: String, range: Option<(f64, f64)>| DownloadHistoryItem {
+        id,
+        title,
+        url: url.to_string(),
+        thumbnail: thumbnail_for(url),
+        duration: match range {
+            Some((start, end)) => end - start,
+            None => ranges.iter().map(|(start, end)| end - start).sum(),
+        },
+        quality: quality.to_string(),
+        format: format.to_string(),
+        file_path: String::new(),
+        file_size: None,
+        downloaded_at: run.finished_at.clone(),
+        start_time: range.map(|(start, _)| start),
+        end_time: range.map(|(_, end)| end),
+        status: FileStatus::Ok,
+        job_id: Some(run.job_id.clone()),
+        job: Some(params.clone()),
+        started_at: Some(run.started_at.clone()),
+        elapsed_ms: Some(run.elapsed_ms),
+        tools: run.tools.clone(),
+        error: None,
+        error_code: None,
+    };
+
+    match outcome {
+        Ok(outputs) => outputs
+            .iter()
+            .enumerate()
+            .map(|(index, path)| {
+                let (id, title) = if multi {
+                    (
+                        format!("{}-{}", run.job_id, index + 1),
+                        format!("{} (Clip {})", title, index + 1),
+                    )
+                } else {
+                    (run.job_id.clone(), title.to_string())
+                };
+                DownloadHistoryItem {
+                    file_path: path.clone(),
+                    ..entry(id, title, ranges.get(index).copied())
+                }
+            })
+            .collect(),
+        Err((error, cancelled)) => {
+            let range = if multi { None } else { ranges.first().copied() };
+            // A killed process fails like any other, so the cancel flag wins
+            let kind = if cancelled {
+                ErrorKind::Cancelled
+            } else {
+                error.kind
+            };
+            vec![DownloadHistoryItem {
+                status: FileStatus::Failed,
+                error: Some(error.message.clone()),
+                error_code: Some(kind.code().to_string()),
+                ..entry(run.job_id.clone(), title.to_string(), range)
+            }]
+        }
+    }
+}
+
+/// Times a job and writes its history entries when it ends, so every job is
+/// recorded even if the window that started it is gone
+pub struct JobRecorder {
+    app: AppHandle,
+    params: JobParams,
+    job_id: String,
+    started_at: String,
+    started: Instant,
+}
+
+impl JobRecorder {
+    pub fn start(app: &AppHandle, params: JobParams) -> Self {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        let now = SystemTime::now();
+        let millis = now
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or_default();
+        Self {
+            app: app.clone(),
+            params,
+            job_id: format!("{}-{}", millis, COUNTER.fetch_add(1, Ordering::Relaxed)),
+            started_at: iso8601(now),
+            started: Instant::now(),
+        }
+    }
+
+    /// Record the outcome. History problems are logged, never turned into job errors.
+    pub fn finish(self, outcome: Result<&[String], &JobError>) {
+        if let Err(e) = self.write(outcome) {
+            log::warn!("Failed to record job {} in history: {}", self.job_id, e);
+        }
+    }
+
+    fn write(&self, outcome: Result<&[String], &JobError>) -> Result<(), String> {
+        let cancelled = self
+            .app
+            .state::<AppState>()
+            .cancel_requested
+            .lock()
+            .map(|cancelled| *cancelled)
+            .unwrap_or(false);
+        let run = JobRun {
+            job_id: self.job_id.clone(),
+            started_at: self.started_at.clone(),
+            finished_at: iso8601(SystemTime::now()),
+            elapsed_ms: self.started.elapsed().as_millis() as u64,
+            tools: toolchain::tool_versions(&self.app),
+        };
+        let entries = job_entries(&self.params, &run, outcome.map_err(|e| (e, cancelled)));
+
+        let mut conn = open(&self.app)?;
+        let tx = conn
+            .transaction()
+            .map_err(|e| format!("Failed to save history: {}", e))?;
+        for entry in &entries {
+            insert_item(&tx, entry, false)?;
+            if entry.status == FileStatus::Ok {
+                let path = Path::new(&entry.file_path);
+                if path.is_file() {
+                    record_file(&tx, &entry.id, path)?;
+                } else {
+                    set_status(&tx, &entry.id, FileStatus::Missing)?;
+                }
+            }
+        }
+        tx.commit()
+            .map_err(|e| format!("Failed to save history: {}", e))?;
+        Ok(())
+    }
+}
+
+/// The job behind an entry. Entries imported from the old JSON history only
+/// have a URL and range, which is enough for a single clip.
+fn job_for(item: &DownloadHistoryItem) -> Option<JobParams> {
+    if let Some(job) = &item.job {
+        return Some(job.clone());
+    }
+    let (start, end) = (item.start_time?, item.end_time?);
+    Some(JobParams::Clip(ClipJob {
+        url: item.url.clone(),
+        title: item.title.clone(),
+        start,
+        end,
+        quality: item.quality.clone(),
+        format: item.format.clone(),
+        id: 0,
+        format_id: None,
+        video_format_id: None,
+        audio_tracks: Vec::new(),
+    }))
+}
+
+fn config_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_config_dir()
+        .map_err(|e| format!("Failed to get config dir: {}", e))
+}
+
+/// Open the history database, creating and migrating it as needed
+pub fn open(app: &AppHandle) -> Result<Connection, String> {
+    let dir = config_dir(app)?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create config dir: {}", e))?;
+    let mut conn = Connection::open(dir.join(DB_FILE))
+        .map_err(|e| format!("Failed to open history: {}", e))?;
+    conn.busy_timeout(std::time::Duration::from_secs(5))
+        .map_err(|e| format!("Failed to open history: {}", e))?;
+    migrate(&mut conn, Some(&dir.join(LEGACY_FILE)))?;
+    Ok(conn)
+}
+
+#[tauri::command]
+pub async fn get_download_history(
+    app: AppHandle,
+    query: Option<HistoryQuery>,
+) -> Result<HistoryPage, String> {
+    let conn = open(&app)?;
+    self::query(&conn, &query.unwrap_or_default())
+}
+
+#[tauri::command]
+pub async fn get_history_filters(app: AppHandle) -> Result<HistoryFilters, String> {
+    let conn = open(&app)?;
+    Ok(HistoryFilters {
+        qualities: distinct(&conn, "quality")?,
+        formats: distinct(&conn, "format")?,
+        sites: distinct(&conn, "site")?,
+    })
+}
+
+/// Mark missing files and find moved ones in the download folder
+#[tauri::command]
+pub async fn reconcile_history(
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<ReconcileReport, String> {
+    let root = resolve_output_dir(&app, &state)?;
+    let mut conn = open(&app)?;
+    let report = reconcile(&mut conn, &root)?;
+    log::info!(
+        "Reconciled history: {} checked, {} relocated, {} missing",
+        report.checked,
+        report.relocated.len(),
+        report.missing.len()
+    );
+    Ok(report)
+}
+
+/// Download a missing clip again from the entry's URL, range and quality
+#[tauri::command]
+pub async fn recreate_history_clip(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    id: String,
+) -> Result<DownloadHistoryItem, entitlements::CommandError> {
+    let conn = open(&app)?;
+    let item = get_item(&conn, &id)?.ok_or("History entry not found")?;
+    let (Some(start), Some(end)) = (item.start_time, item.end_time) else {
+        return Err("This entry has no stored clip range, so it can't be re-created".into());
+    };
+
+    // Same source settings as the original job where they were recorded
+    let (format_id, video_format_id, audio_tracks) = match &item.job {
+        Some(JobParams::Clip(job)) => (
+            job.format_id.clone(),
+            job.video_format_id.clone(),
+            job.audio_tracks.clone(),
+        ),
+        Some(JobParams::MultiClip(job)) => (
+            job.format_id.clone(),
+            job.video_format_id.clone(),
+            job.audio_tracks.clone(),
+        ),
+        None => (None, None, Vec::new()),
+    };
+    let job = ClipJob {
+        url: item.url.clone(),
+        title: item.title.clone(),
+        start,
+        end,
+        quality: item.quality.clone(),
+        format: item.format.clone(),
+        id: now_millis(),
+        format_id,
+        video_format_id,
+        audio_tracks,
+    };
+    entitlements::require_clip(&app, &job)?;
+    // Not recorded as a new job: the output replaces this entry's file
+    let output = render_clip(&app, &state, job)?;
+    record_file(&conn, &id, Path::new(&output))?;
+    Ok(get_item(&conn, &id)?.ok_or("History entry not found")?)
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// Run the job behind a history entry again. The new run is recorded as a job
+/// of its own; progress events use `session_id`.
+#[tauri::command]
+pub async fn rerun_job(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    id: String,
+    session_id: Option<u64>,
+) -> Result<Vec<String>, entitlements::CommandError> {
+    let item = get_item(&open(&app)?, &id)?.ok_or("History entry not found")?;
+    let job = job_for(&item).ok_or("This entry doesn't store enough to run it again")?;
+    let session_id = session_id.unwrap_or_else(now_millis);
+    log::info!("Re-running job {} from history entry {}", session_id, id);
+    match job {
+        JobParams::Clip(job) => run_clip_job(
+            &app,
+            &state,
+            ClipJob {
+                id: session_id,
+                ..job
+            },
+        )
+        .map(|path| vec![path]),
+        JobParams::MultiClip(job) => run_multi_clip_job(
+            &app,
+            &state,
+            MultiClipJob {
+                id: session_id,
+                ..job
+            },
+        ),
+    }
+}
+
+#[tauri::command]
+pub async fn clear_download_history(app: AppHandle) -> Result<(), String> {
+    let conn = open(&app)?;
+    conn.execute("DELETE FROM history", [])
+        .map_err(|e| format!("Failed to clear history: {}", e))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(id: &str, title: &str, url: &str, quality: &str, at: &str) -> DownloadHistoryItem {
+        DownloadHistoryItem {
+            id: id.to_string(),
+            title: title.to_string(),
+            url: url.to_string(),
+            thumbnail: None,
+            duration: 10.0,
+            quality: quality.to_string(),
+            format: "mp4".to_string(),
+            file_path: format!("/clips/{}.mp4", id),
+            file_size: Some(1024),
+            downloaded_at: at.to_string(),
+            start_time: Some(5.0),
+            end_time: Some(15.0),
+            status: FileStatus::Ok,
+            job_id: None,
+            job: None,
+            started_at: None,
+            elapsed_ms: None,
+            tools: BTreeMap::new(),
+            error: None,
+            error_code: None,
+        }
+    }
+
+    fn memory_db() -> Connection {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn, None).unwrap();
+        conn
+    }
+
+    fn ids(page: &HistoryPage) -> Vec<&str> {
+        page.items.iter().map(|i| i.id.as_str()).collect()
+    }
+
+    #[test]
+    fn imports_legacy_json_once() {
+        let dir = std::env::temp_dir().join(format!("clipme-history-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let legacy = dir.join(LEGACY_FILE);
+        let items = vec![
+            item(
+                "1",
+                "Old clip",
+                "https://youtu.be/abc",
+                "720p",
+                "2024-01-01T00:00:00.000Z",
+            ),
+            item(
+                "2",
+                "Older clip",
+                "https://vimeo.com/1",
+                "1080p",
+                "2023-01-01T00:00:00.000Z",
+            ),
+        ];
+        fs::write(&legacy, serde_json::to_string(&items).unwrap()).unwrap();
+
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn, Some(&legacy)).unwrap();
+        assert!(!legacy.exists());
+        assert!(dir.join("download_history.json.migrated").exists());
+        let page = query(&conn, &HistoryQuery::default()).unwrap();
+        assert_eq!(page.items, items);
+
+        // A second run is a no-op even if the file comes back
+        fs::write(&legacy, "[]").unwrap();
+        migrate(&mut conn, Some(&legacy)).unwrap();
+        assert!(legacy.exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn keeps_corrupt_legacy_file() {
+        let dir = std::env::temp_dir().join(format!("clipme-history-bad-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let legacy = dir.join(LEGACY_FILE);
+        fs::write(&legacy, "[{not json").unwrap();
+
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn, Some(&legacy)).unwrap();
+        assert!(dir.join("download_history.json.corrupt").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn searches_filters_and_pages() {
+        let conn = memory_db();
+        let entries = [
+            item(
+                "a",
+                "Cat compilation",
+                "https://www.youtube.com/watch?v=1",
+                "1080p",
+                "2024-03-01T10:00:00.000Z",
+            ),
+            item(
+                "b",
+                "Dog tricks",
+                "https://youtu.be/2",
+                "720p",
+                "2024-03-02T10:00:00.000Z",
+            ),
+            item(
+                "c",
+                "Cats vs dogs",
+                "https://vimeo.com/3",
+                "1080p",
+                "2024-03-03T10:00:00.000Z",
+            ),
+            item(
+                "d",
+                "Local edit",
+                "/home/me/edit.mp4",
+                "1080p",
+                "2024-03-04T10:00:00.000Z",
+            ),
+        ];
+        for entry in &entries {
+            insert_item(&conn, entry, false).unwrap();
+        }
+
+        let all = query(&conn, &HistoryQuery::default()).unwrap();
+        assert_eq!(ids(&all), ["d", "c", "b", "a"]);
+
+        let search = |text: &str| HistoryQuery {
+            search: Some(text.to_string()),
+            ..Default::default()
+        };
+        assert_eq!(ids(&query(&conn, &search("cat")).unwrap()), ["c", "a"]);
+        assert_eq!(ids(&query(&conn, &search("vimeo")).unwrap()), ["c"]);
+        // FTS operators are matched literally rather than failing the query
+        assert!(query(&conn, &search("\"dog OR -cat:"))
+            .unwrap()
+            .items
+            .is_empty());
+
+        let youtube = HistoryQuery {
+            site: Some("youtube.com".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(ids(&query(&conn, &youtube).unwrap()), ["b", "a"]);
+        let ranged = HistoryQuery {
+            quality: Some("1080p".to_string()),
+            from: Some("2024-03-02".to_string()),
+            to: Some("2024-03-03T23:59:59Z".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(ids(&query(&conn, &ranged).unwrap()), ["c"]);
+        let date_only = HistoryQuery {
+            to: Some("2024-03-03".to_string()),
+            ..ranged
+        };
+        assert_eq!(ids(&query(&conn, &date_only).unwrap()), ["c"]);
+
+        let page = query(
+            &conn,
+            &HistoryQuery {
+                offset: Some(1),
+                limit: Some(2),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(ids(&page), ["c", "b"]);
+        assert_eq!(page.total, 4);
+
+        // Re-saving an id replaces it and keeps the search index in sync
+        let mut renamed = entries[0].clone();
+        renamed.title = "Parrot".to_string();
+        insert_item(&conn, &renamed, false).unwrap();
+        assert_eq!(ids(&query(&conn, &search("cat")).unwrap()), ["c"]);
+        assert_eq!(ids(&query(&conn, &search("parrot")).unwrap()), ["a"]);
+    }
+
+    #[test]
+    fn reconciles_moved_and_missing_files() {
+        let root = std::env::temp_dir().join(format!("clipme-reconcile-{}", std::process::id()));
+        let moved_dir = root.join("sorted").join("cats");
+        fs::create_dir_all(&moved_dir).unwrap();
+        let write = |path: &Path, content: &str| {
+            fs::write(path, content).unwrap();
+            path.to_string_lossy().to_string()
+        };
+        let mut conn = memory_db();
+        let add = |conn: &Connection, id: &str, path: String| {
+            let mut entry = item(id, id, "https://youtu.be/x", "720p", "2024-01-01T00:00:00Z");
+            entry.file_path = path;
+            insert_item(conn, &entry, false).unwrap();
+            record_file(conn, id, Path::new(&entry.file_path)).unwrap();
+        };
+
+        let kept = write(&root.join("kept.mp4"), "same size A");
+        add(&conn, "kept", kept.clone());
+        let original = write(&root.join("moved.mp4"), "same size B");
+        add(&conn, "moved", original.clone());
+        let gone = write(&root.join("gone.mp4"), "deleted clip");
+        add(&conn, "gone", gone.clone());
+
+        let new_path = moved_dir.join("renamed.mp4");
+        fs::rename(&original, &new_path).unwrap();
+        fs::remove_file(&gone).unwrap();
+        // Same size as the moved file but different content
+        write(&root.join("decoy.mp4"), "same size C");
+
+        let report = reconcile(&mut conn, &root).unwrap();
+        assert_eq!(report.checked, 3);
+        assert_eq!(
+            report.relocated,
+            vec![RelocatedEntry {
+                id: "moved".to_string(),
+                from: original,
+                to: new_path.to_string_lossy().to_string(),
+            }]
+        );
+        assert_eq!(report.missing, vec!["gone".to_string()]);
+
+        let moved = get_item(&conn, "moved").unwrap().unwrap();
+        assert_eq!(moved.file_path, new_path.to_string_lossy());
+        assert_eq!(moved.status, FileStatus::Ok);
+        assert_eq!(
+            get_item(&conn, "gone").unwrap().unwrap().status,
+            FileStatus::Missing
+        );
+        assert_eq!(get_item(&conn, "kept").unwrap().unwrap().file_path, kept);
+
+        // A missing file that comes back is picked up again
+        write(Path::new(&gone), "deleted clip");
+        let report = reconcile(&mut conn, &root).unwrap();
+        assert!(report.missing.is_empty());
+        assert_eq!(
+            get_item(&conn, "gone").unwrap().unwrap().status,
+            FileStatus::Ok
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    fn run() -> JobRun {
+        JobRun {
+            job_id: "job".to_string(),
+            started_at: "2024-05-01T10:00:00.000Z".to_string(),
+            finished_at: "2024-05-01T10:00:09.500Z".to_string(),
+            elapsed_ms: 9500,
+            tools: BTreeMap::from([("yt-dlp".to_string(), "2024.04.09".to_string())]),
+        }
+    }
+
+    fn multi_job() -> JobParams {
+        serde_json::from_value(serde_json::json!({
+            "kind": "multi_clip",
+            "url": "https://youtu.be/dQw4w9WgXcQ",
+            "title": "Song",
+            "segments": [
+                { "id": "s1", "start": 1.0, "end": 4.0 },
+                { "id": "s2", "start": "0:10", "end": "0:12.5" }
+            ],
+            "quality": "1080p",
+            "format": "mp4",
+            "id": 7
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn records_jobs_with_their_parameters() {
+        let conn = memory_db();
+        let job = multi_job();
+        let outputs = vec!["/clips/a.mp4".to_string(), "/clips/b.mp4".to_string()];
+        let entries = job_entries(&job, &run(), Ok(&outputs));
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].id, "job-2");
+        assert_eq!(entries[1].title, "Song (Clip 2)");
+        assert_eq!(
+            (entries[1].start_time, entries[1].end_time),
+            (Some(10.0), Some(12.5))
+        );
+        assert_eq!(
+            entries[0].thumbnail.as_deref(),
+            Some("https://i.ytimg.com/vi/dQw4w9WgXcQ/mqdefault.jpg")
+        );
+        for entry in &entries {
+            insert_item(&conn, entry, false).unwrap();
+        }
+
+        // Everything survives a round trip, including the replayable job
+        let stored = get_item(&conn, "job-2").unwrap().unwrap();
+        assert_eq!(stored, entries[1]);
+        assert_eq!(job_for(&stored), Some(job.clone()));
+
+        let error = JobError::new(ErrorKind::DownloadFailed, "Clip 2 download failed");
+        let failed = job_entries(&job, &run(), Err((&error, false)));
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].status, FileStatus::Failed);
+        assert_eq!(failed[0].error.as_deref(), Some("Clip 2 download failed"));
+        assert_eq!(failed[0].error_code.as_deref(), Some("download_failed"));
+        assert_eq!(failed[0].duration, 5.5);
+        assert_eq!(failed[0].start_time, None);
+
+        let killed = job_entries(&job, &run(), Err((&error, true)));
+        assert_eq!(killed[0].error_code.as_deref(), Some("cancelled"));
+    }
+
+    #[test]
+    fn legacy_entries_replay_as_single_clips() {
+        let legacy = item(
+            "old",
+            "Old",
+            "https://vimeo.com/1",
+            "720p",
+            "2023-01-01T00:00:00Z",
+        );
+        match job_for(&legacy) {
+            Some(JobParams::Clip(job)) => assert_eq!((job.start, job.end), (5.0, 15.0)),
+            other => panic!("unexpected job: {:?}", other),
+        }
+        let no_range = DownloadHistoryItem {
+            start_time: None,
+            ..legacy
+        };
+        assert_eq!(job_for(&no_range), None);
+    }
+
+    #[test]
+    fn codes_errors_by_kind_not_wording() {
+        let error = |kind, message| {
+            let error = JobError::new(kind, message);
+            job_entries(&multi_job(), &run(), Err((&error, false)))[0]
+                .error_code
+                .clone()
+        };
+        // The same text gets whatever code its failure site gave it
+        assert_eq!(
+            error(ErrorKind::EncodeFailed, "Failed").as_deref(),
+            Some("encode_failed")
+        );
+        assert_eq!(
+            error(ErrorKind::Toolchain, "Failed").as_deref(),
+            Some("toolchain")
+        );
+        assert_eq!(
+            JobError::from("Failed to lock state").kind,
+            ErrorKind::Unknown
+        );
+    }
+
+    #[test]
+    fn formats_timestamps_like_javascript() {
+        let at = |millis: u64| iso8601(UNIX_EPOCH + std::time::Duration::from_millis(millis));
+        assert_eq!(at(0), "1970-01-01T00:00:00.000Z");
+        assert_eq!(at(951_782_400_000), "2000-02-29T00:00:00.000Z");
+        assert_eq!(at(1_714_557_609_123), "2024-05-01T10:00:09.123Z");
+    }
+
+    #[test]
+    fn groups_sites() {
+        assert_eq!(site_of("https://m.youtube.com/watch?v=x"), "youtube.com");
+        assert_eq!(site_of("https://youtu.be/x"), "youtube.com");
+        assert_eq!(site_of("https://www.twitch.tv/videos/1"), "twitch.tv");
+        assert_eq!(site_of("C:\\clips\\a.mp4"), "local");
+    }
+}
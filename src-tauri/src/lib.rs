@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::io::{BufRead, BufReader, Read};
 use std::path::PathBuf;
@@ -11,6 +12,28 @@ use tauri::State;
 #[cfg(target_os = "windows")]
 use std::os::windows::process::CommandExt;
 
+mod analysis;
+mod clip_list;
+mod entitlements;
+mod filmstrip;
+mod frames;
+mod history;
+mod license;
+mod links;
+mod logging;
+mod metadata_cache;
+mod naming;
+mod playlist;
+mod preview;
+mod project;
+mod timecode;
+mod toolchain;
+mod validation;
+mod waveform;
+mod ytdlp_update;
+
+use history::{ErrorKind, JobError, WithKind};
+
 /// Helper to create a command that doesn't spawn a visible window on Windows
 fn create_windowless_command(program: &std::path::Path) -> Command {
     #[allow(unused_mut)]
@@ -39,6 +62,10 @@ struct AppState {
     download_pid: Mutex<Option<u32>>,
     download_path: Mutex<Option<PathBuf>>,
     current_file_path: Mutex<Option<PathBuf>>,
+    cancel_requested: Mutex<bool>,
+    preview_generation: Mutex<u64>,
+    /// Files and folders the user picked or dropped; other local paths are refused
+    allowed_local_paths: Mutex<Vec<PathBuf>>,
 }
 
 /// Get the target triple for the current platform
@@ -63,53 +90,491 @@ fn get_exe_extension() -> &'static str {
     return "";
 }
 
-/// Get the path to a bundled sidecar binary, or fall back to system PATH.
-/// Returns the bundled binary path if it exists, otherwise returns just the binary name
-/// so it will be looked up in the system PATH.
-fn get_binary_path(app: &AppHandle, name: &str) -> PathBuf {
-    let target_triple = get_target_triple();
-    let exe_ext = get_exe_extension();
-    let binary_name = format!("{}-{}{}", name, target_triple, exe_ext);
-    
-    // Try to resolve the bundled binary path
-    if let Ok(path) = app.path().resolve(
-        format!("binaries/{}", binary_name),
-        tauri::path::BaseDirectory::Resource
-    ) {
-        // Check if the file actually exists
-        if path.exists() {
-            println!("Using bundled binary: {:?}", path);
-            return path;
-        }
-    }
-    
-    // Fallback: use system PATH
-    let system_name = format!("{}{}", name, exe_ext);
-    println!("Bundled binary not found, falling back to system: {}", system_name);
-    PathBuf::from(system_name)
+/// Get the path to a sidecar binary: the user's override from settings, the
+/// bundled copy (verified against the checksum manifest) or the system `PATH`.
+fn get_binary_path(app: &AppHandle, name: &str) -> Result<PathBuf, String> {
+    toolchain::resolve_binary(app, name).map(|resolved| resolved.path)
 }
 
-/// Get the yt-dlp binary path (bundled or system)
-fn get_ytdlp_path(app: &AppHandle) -> PathBuf {
-    get_binary_path(app, "yt-dlp")
+/// A sidecar plus the extra arguments configured for it in settings
+fn get_sidecar(app: &AppHandle, name: &str) -> Result<toolchain::Sidecar, String> {
+    Ok(toolchain::Sidecar {
+        path: get_binary_path(app, name)?,
+        extra_args: toolchain::extra_args(app, name),
+    })
 }
 
-/// Get the ffmpeg binary path (bundled or system)
-fn get_ffmpeg_path(app: &AppHandle) -> PathBuf {
-    get_binary_path(app, "ffmpeg")
+/// Get yt-dlp (custom, bundled or system)
+fn get_ytdlp(app: &AppHandle) -> Result<toolchain::Sidecar, String> {
+    get_sidecar(app, "yt-dlp")
 }
 
-/// Get the ffprobe binary path (bundled or system)
-fn get_ffprobe_path(app: &AppHandle) -> PathBuf {
-    get_binary_path(app, "ffprobe")
+/// Get ffmpeg (custom, bundled or system)
+fn get_ffmpeg(app: &AppHandle) -> Result<toolchain::Sidecar, String> {
+    get_sidecar(app, "ffmpeg")
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// Get ffprobe (custom, bundled or system)
+fn get_ffprobe(app: &AppHandle) -> Result<toolchain::Sidecar, String> {
+    get_sidecar(app, "ffprobe")
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct VideoMetadata {
     title: String,
     duration: f64,
     formats: Vec<String>,
+    available_formats: Vec<FormatInfo>,
+    audio_tracks: Vec<AudioTrack>,
     preview_url: Option<String>,
+    /// Unix time after which a signed `preview_url` stops working
+    preview_expires_at: Option<u64>,
+    /// The URL with tracking and timing parameters removed
+    #[serde(default)]
+    canonical_url: Option<String>,
+    /// Clip range pre-filled from `?t=`, `start`/`end` or `#t=` in the pasted URL
+    #[serde(default)]
+    start_time: Option<f64>,
+    #[serde(default)]
+    end_time: Option<f64>,
+    /// Channel or uploader name, for filename templates
+    #[serde(default)]
+    uploader: Option<String>,
+    /// The site's id for the video
+    #[serde(default)]
+    video_id: Option<String>,
+}
+
+/// An audio track that can be kept in a clip. For remote sources `id` is the
+/// yt-dlp format id, for local files it is the ffprobe stream index.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AudioTrack {
+    id: String,
+    language: Option<String>,
+    codec: Option<String>,
+    channels: Option<u64>,
+    title: Option<String>,
+    is_default: bool,
+}
+
+/// A single downloadable format (yt-dlp) or stream (ffprobe) of a source
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct FormatInfo {
+    format_id: String,
+    container: Option<String>,
+    vcodec: Option<String>,
+    acodec: Option<String>,
+    width: Option<u64>,
+    height: Option<u64>,
+    fps: Option<f64>,
+    dynamic_range: Option<String>,
+    tbr: Option<f64>,
+    filesize: Option<u64>,
+    filesize_approx: Option<u64>,
+    language: Option<String>,
+    format_note: Option<String>,
+}
+
+/// yt-dlp reports missing codecs as the literal string "none"
+fn codec_field(value: &serde_json::Value) -> Option<String> {
+    value
+        .as_str()
+        .filter(|s| !s.is_empty() && *s != "none")
+        .map(String::from)
+}
+
+/// Build the typed format list from a yt-dlp `--dump-json` object.
+/// Storyboards and other image-only entries are skipped.
+fn parse_ytdlp_formats(json_val: &serde_json::Value) -> Vec<FormatInfo> {
+    let Some(formats_arr) = json_val["formats"].as_array() else {
+        return Vec::new();
+    };
+
+    formats_arr
+        .iter()
+        .filter_map(|fmt| {
+            let format_id = fmt["format_id"].as_str()?.to_string();
+            let vcodec = codec_field(&fmt["vcodec"]);
+            let acodec = codec_field(&fmt["acodec"]);
+            if fmt["ext"].as_str() == Some("mhtml") {
+                return None;
+            }
+            Some(FormatInfo {
+                format_id,
+                container: fmt["ext"].as_str().map(String::from),
+                vcodec,
+                acodec,
+                width: fmt["width"].as_u64(),
+                height: fmt["height"].as_u64(),
+                fps: fmt["fps"].as_f64(),
+                dynamic_range: fmt["dynamic_range"].as_str().map(String::from),
+                tbr: fmt["tbr"].as_f64(),
+                filesize: fmt["filesize"].as_u64(),
+                filesize_approx: fmt["filesize_approx"].as_u64(),
+                language: fmt["language"].as_str().map(String::from),
+                format_note: fmt["format_note"].as_str().map(String::from),
+            })
+        })
+        .collect()
+}
+
+/// Build the format list for a local file from ffprobe `-show_streams` output.
+/// Each stream becomes one entry whose `format_id` is its ffprobe stream index.
+fn parse_ffprobe_streams(json_val: &serde_json::Value) -> Vec<FormatInfo> {
+    let container = json_val["format"]["format_name"]
+        .as_str()
+        .and_then(|s| s.split(',').next())
+        .map(String::from);
+    let Some(streams) = json_val["streams"].as_array() else {
+        return Vec::new();
+    };
+
+    streams
+        .iter()
+        .filter_map(|stream| {
+            let index = stream["index"].as_u64()?;
+            let codec = stream["codec_name"].as_str().map(String::from);
+            let (vcodec, acodec) = match stream["codec_type"].as_str()? {
+                "video" => (codec, None),
+                "audio" => (None, codec),
+                _ => return None,
+            };
+            // Cover art is exposed as a single-frame video stream
+            if stream["disposition"]["attached_pic"].as_u64() == Some(1) {
+                return None;
+            }
+            let fps = stream["avg_frame_rate"]
+                .as_str()
+                .and_then(parse_frame_rate)
+                .filter(|fps| *fps > 0.0);
+            let dynamic_range = vcodec.as_ref().map(|_| {
+                match stream["color_transfer"].as_str() {
+                    Some("smpte2084") | Some("arib-std-b67") => "HDR".to_string(),
+                    _ => "SDR".to_string(),
+                }
+            });
+            let tbr = stream["bit_rate"]
+                .as_str()
+                .and_then(|s| s.parse::<f64>().ok())
+                .map(|bps| bps / 1000.0);
+            Some(FormatInfo {
+                format_id: index.to_string(),
+                container: container.clone(),
+                vcodec,
+                acodec,
+                width: stream["width"].as_u64(),
+                height: stream["height"].as_u64(),
+                fps,
+                dynamic_range,
+                tbr,
+                filesize: None,
+                filesize_approx: None,
+                language: stream["tags"]["language"].as_str().map(String::from),
+                format_note: stream["tags"]["title"].as_str().map(String::from),
+            })
+        })
+        .collect()
+}
+
+/// Run ffprobe over a local file and return its `-show_format -show_streams` JSON
+fn probe_local_file(ffprobe: &toolchain::Sidecar, path: &str) -> Result<serde_json::Value, String> {
+    let output = ffprobe.command()
+        .args([
+            "-v",
+            "quiet",
+            "-print_format",
+            "json",
+            "-show_format",
+            "-show_streams",
+            path,
+        ])
+        .output()
+        .map_err(|e| format!("Failed to execute ffprobe: {}", e))?;
+
+    if !output.status.success() {
+        return Err("Failed to read video file metadata".to_string());
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    serde_json::from_str(&stdout).map_err(|e| format!("Failed to parse ffprobe output: {}", e))
+}
+
+/// Audio streams of a local file, in stream order
+fn local_audio_tracks(probe: &serde_json::Value) -> Vec<AudioTrack> {
+    let Some(streams) = probe["streams"].as_array() else {
+        return Vec::new();
+    };
+
+    streams
+        .iter()
+        .filter(|stream| stream["codec_type"].as_str() == Some("audio"))
+        .filter_map(|stream| {
+            Some(AudioTrack {
+                id: stream["index"].as_u64()?.to_string(),
+                language: stream["tags"]["language"]
+                    .as_str()
+                    .filter(|lang| *lang != "und")
+                    .map(String::from),
+                codec: stream["codec_name"].as_str().map(String::from),
+                channels: stream["channels"].as_u64(),
+                title: stream["tags"]["title"].as_str().map(String::from),
+                is_default: stream["disposition"]["default"].as_u64() == Some(1),
+            })
+        })
+        .collect()
+}
+
+/// Audio tracks of a remote source: the highest-bitrate audio-only format per language.
+/// Sources without language info collapse into a single untagged track.
+fn remote_audio_tracks(json_val: &serde_json::Value) -> Vec<AudioTrack> {
+    let Some(formats_arr) = json_val["formats"].as_array() else {
+        return Vec::new();
+    };
+
+    let mut best: Vec<(f64, AudioTrack)> = Vec::new();
+    for fmt in formats_arr {
+        if codec_field(&fmt["vcodec"]).is_some() {
+            continue;
+        }
+        let (Some(format_id), Some(codec)) = (fmt["format_id"].as_str(), codec_field(&fmt["acodec"])) else {
+            continue;
+        };
+        let language = fmt["language"].as_str().map(String::from);
+        let tbr = fmt["abr"].as_f64().or(fmt["tbr"].as_f64()).unwrap_or(0.0);
+        // yt-dlp ranks the original track above dubs via language_preference
+        let is_default = fmt["language_preference"].as_i64().unwrap_or(-1) >= 10;
+        let track = AudioTrack {
+            id: format_id.to_string(),
+            language: language.clone(),
+            codec: Some(codec),
+            channels: fmt["audio_channels"].as_u64(),
+            title: fmt["format_note"].as_str().map(String::from),
+            is_default,
+        };
+
+        match best.iter_mut().find(|(_, t)| t.language == language) {
+            Some(entry) if entry.0 < tbr => *entry = (tbr, track),
+            Some(_) => {}
+            None => best.push((tbr, track)),
+        }
+    }
+
+    let mut tracks: Vec<AudioTrack> = best.into_iter().map(|(_, t)| t).collect();
+    if tracks.len() == 1 {
+        tracks[0].is_default = true;
+    }
+    tracks
+}
+
+/// ffmpeg `-map` arguments for a local clip: the first video stream (unless
+/// `keep_video` is false) followed by each requested audio stream, re-tagged
+/// with its source language so players can tell the tracks apart.
+/// An empty selection keeps ffmpeg's default stream choice.
+fn local_stream_map_args(
+    probe: &serde_json::Value,
+    audio_tracks: &[String],
+    keep_video: bool,
+) -> Result<Vec<String>, String> {
+    if audio_tracks.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let available = local_audio_tracks(probe);
+    let mut args = Vec::new();
+    if keep_video {
+        args.extend(["-map".to_string(), "0:v:0?".to_string()]);
+    }
+
+    for (out_index, id) in audio_tracks.iter().enumerate() {
+        let track = available
+            .iter()
+            .find(|t| &t.id == id)
+            .ok_or_else(|| format!("Audio track {} not found in source", id))?;
+        args.extend(["-map".to_string(), format!("0:{}", track.id)]);
+        if let Some(ref language) = track.language {
+            args.push(format!("-metadata:s:a:{}", out_index));
+            args.push(format!("language={}", language));
+        }
+        if let Some(ref title) = track.title {
+            args.push(format!("-metadata:s:a:{}", out_index));
+            args.push(format!("title={}", title));
+        }
+        args.push(format!("-disposition:a:{}", out_index));
+        args.push(if out_index == 0 { "default" } else { "0" }.to_string());
+    }
+
+    Ok(args)
+}
+
+/// Parse an ffprobe rational like "30000/1001" into frames per second
+fn parse_frame_rate(rate: &str) -> Option<f64> {
+    match rate.split_once('/') {
+        Some((num, den)) => {
+            let num = num.parse::<f64>().ok()?;
+            let den = den.parse::<f64>().ok()?;
+            if den == 0.0 {
+                None
+            } else {
+                Some(num / den)
+            }
+        }
+        None => rate.parse::<f64>().ok(),
+    }
+}
+
+/// Quality labels offered for a source whose tallest video stream is `max_height`
+fn quality_labels(max_height: u64, include_audio_only: bool) -> Vec<String> {
+    let mut formats: Vec<String> = vec!["Best".to_string()];
+    for (min_height, label) in [
+        (4320, "8K"),
+        (2160, "4K"),
+        (1440, "1440p"),
+        (1080, "1080p"),
+        (720, "720p"),
+        (480, "480p"),
+    ] {
+        if max_height >= min_height {
+            formats.push(label.to_string());
+        }
+    }
+    if include_audio_only {
+        formats.push("Audio Only".to_string());
+    }
+    formats
+}
+
+/// Resolve the yt-dlp `-f` selector for a clip. An explicit `format_id` wins,
+/// then a `video_format_id` plus audio format ids, then the quality preset.
+/// Several audio ids are merged as separate tracks (needs `--audio-multistreams`).
+fn resolve_format_selector(
+    quality: &str,
+    format_id: Option<&str>,
+    video_format_id: Option<&str>,
+    audio_format_ids: &[String],
+) -> Result<String, String> {
+    let ids = [format_id, video_format_id]
+        .into_iter()
+        .flatten()
+        .chain(audio_format_ids.iter().map(String::as_str));
+    for id in ids {
+        if !is_valid_format_id(id) {
+            return Err(format!("Invalid format id: {}", id));
+        }
+    }
+
+    if let Some(id) = format_id {
+        return Ok(id.to_string());
+    }
+
+    let audio = audio_format_ids.join("+");
+    match (video_format_id, audio.is_empty()) {
+        (Some(video), false) => return Ok(format!("{}+{}", video, audio)),
+        (Some(video), true) => return Ok(format!("{}+bestaudio/{}", video, video)),
+        (None, false) if quality == "Audio Only" => return Ok(audio),
+        (None, false) => return Ok(format!("{}+{}", quality_video_selector(quality), audio)),
+        (None, true) => {}
+    }
+
+    let format_arg = match quality {
+        "8K" => "bestvideo[height>=4320]+bestaudio/bestvideo[height>=2160]+bestaudio/best",
+        "4K" => "bestvideo[height=2160]+bestaudio/bestvideo[height>=2160]+bestaudio/best",
+        "1440p" => "bestvideo[height=1440]+bestaudio/bestvideo[height<=1440]+bestaudio/best",
+        "1080p" => "bestvideo[height=1080][vcodec^=avc]+bestaudio[ext=m4a]/bestvideo[height=1080]+bestaudio/best[height<=1080]",
+        "720p" => "bestvideo[height=720][vcodec^=avc]+bestaudio[ext=m4a]/bestvideo[height=720]+bestaudio/best[height<=720]",
+        "480p" => "bestvideo[height=480][vcodec^=avc]+bestaudio[ext=m4a]/bestvideo[height=480]+bestaudio/best[height<=480]",
+        "Audio Only" => "bestaudio/best",
+        _ => "bestvideo+bestaudio/best",
+    };
+    Ok(format_arg.to_string())
+}
+
+/// The video format picked by id, from the cached metadata of `url`
+fn selected_video_format(
+    app: &AppHandle,
+    url: &str,
+    format_id: Option<&str>,
+    video_format_id: Option<&str>,
+) -> Option<FormatInfo> {
+    let id = format_id.or(video_format_id)?;
+    metadata_cache::lookup(app, url, u64::MAX)?
+        .available_formats
+        .into_iter()
+        .find(|f| f.format_id == id)
+}
+
+/// Video formats of a source: probed for local files, from cached metadata otherwise
+fn source_formats(app: &AppHandle, url: &str) -> Option<Vec<FormatInfo>> {
+    let source = validation::validate_source(app, url).ok()?;
+    if source.is_local() {
+        let probe = probe_local_file(&get_ffprobe(app).ok()?, &source.as_arg()).ok()?;
+        return Some(parse_ffprobe_streams(&probe));
+    }
+    Some(metadata_cache::lookup(app, url, u64::MAX)?.available_formats)
+}
+
+/// Height of the format picked by id, or of the source's tallest video when
+/// none is picked; `None` when it isn't known
+fn picked_video_height(app: &AppHandle, url: &str, format_id: Option<&str>) -> Option<u64> {
+    let formats = source_formats(app, url)?;
+    match format_id {
+        Some(id) => formats.iter().find(|f| f.format_id == id)?.height,
+        None => formats
+            .iter()
+            .filter(|f| f.vcodec.is_some())
+            .filter_map(|f| f.height)
+            .max(),
+    }
+}
+
+/// Whether a download is re-encoded to HEVC afterwards. Presets go by their
+/// label; a format picked by id goes by its own height and codec, so a 1080p
+/// AV1 pick or a stream that is already HEVC is kept as downloaded.
+fn needs_hevc_transcode(quality: &str, explicit: bool, selected: Option<&FormatInfo>) -> bool {
+    if !explicit {
+        return matches!(quality, "8K" | "4K" | "1440p" | "Best");
+    }
+    selected.is_some_and(|format| {
+        let is_hevc = format
+            .vcodec
+            .as_deref()
+            .is_some_and(|codec| codec.starts_with("hvc1") || codec.starts_with("hev1"));
+        format.height.is_some_and(|height| height >= 1440) && !is_hevc
+    })
+}
+
+/// Every quality preset the app offers
+const QUALITY_PRESETS: &[&str] = &[
+    "Best",
+    "8K",
+    "4K",
+    "1440p",
+    "1080p",
+    "720p",
+    "480p",
+    "Audio Only",
+];
+
+/// Video-only part of a quality preset, used when the audio side is picked explicitly
+fn quality_video_selector(quality: &str) -> &'static str {
+    match quality {
+        "8K" => "bestvideo[height>=4320]",
+        "4K" => "bestvideo[height>=2160]",
+        "1440p" => "bestvideo[height<=1440]",
+        "1080p" => "bestvideo[height<=1080]",
+        "720p" => "bestvideo[height<=720]",
+        "480p" => "bestvideo[height<=480]",
+        _ => "bestvideo",
+    }
+}
+
+/// Format ids are plain tokens like "137", "hls-1080p" or "dash-audio_eng=128000".
+/// Selector syntax (`+`, `/`, `[`) is rejected so callers can't smuggle in expressions.
+fn is_valid_format_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= 128
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '='))
 }
 
 #[derive(Clone, Serialize, Debug)]
@@ -123,50 +588,33 @@ pub struct DownloadProgress {
 }
 
 #[tauri::command]
-async fn get_video_metadata(app: AppHandle, url: String) -> Result<VideoMetadata, String> {
-    println!("Fetching metadata for: {}", url);
+async fn get_video_metadata(
+    app: AppHandle,
+    url: String,
+    force_refresh: Option<bool>,
+) -> Result<VideoMetadata, String> {
+    log::info!("Fetching metadata for: {}", url);
 
-    // Get binary paths (bundled or system fallback)
-    let ffprobe_path = get_ffprobe_path(&app);
-    let ytdlp_path = get_ytdlp_path(&app);
+    let source = validation::validate_source(&app, &url)?;
+    // Fetch and cache by the canonical URL so `?t=` and share variants hit one entry
+    let link = links::normalize_url(&source.as_arg());
+    let url = link.url.clone();
 
     // Check if input is a local file
     let path = std::path::Path::new(&url);
-    if path.exists() && path.is_file() {
-        println!("Detected local file: {:?}", path);
+    if source.is_local() {
+        log::info!("Detected local file: {:?}", path);
+        let ffprobe = get_ffprobe(&app)?;
 
         // Use ffprobe to get metadata
-        let output = create_windowless_command(&ffprobe_path)
-            .args(&[
-                "-v",
-                "quiet",
-                "-print_format",
-                "json",
-                "-show_format",
-                "-show_streams",
-                "-select_streams",
-                "v:0",
-                &url,
-            ])
-            .output()
-            .map_err(|e| {
-                format!(
-                    "Failed to execute ffprobe: {}",
-                    e
-                )
-            })?;
-
-        if !output.status.success() {
-            return Err("Failed to read video file metadata".to_string());
-        }
-
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let json_val: serde_json::Value = serde_json::from_str(&stdout)
-            .map_err(|e| format!("Failed to parse ffprobe output: {}", e))?;
+        let json_val = probe_local_file(&ffprobe, &url)?;
 
         let format = &json_val["format"];
-        let streams = json_val["streams"].as_array();
-        let stream = streams.and_then(|s| s.first());
+        let available_formats = parse_ffprobe_streams(&json_val);
+        let audio_tracks = local_audio_tracks(&json_val);
+        let stream = json_val["streams"]
+            .as_array()
+            .and_then(|s| s.iter().find(|s| s["codec_type"].as_str() == Some("video")));
 
         let title = path
             .file_name()
@@ -185,63 +633,89 @@ async fn get_video_metadata(app: AppHandle, url: String) -> Result<VideoMetadata
         let width = get_dim("width").unwrap_or(1920);
         let height = get_dim("height").unwrap_or(1080);
 
-        println!("Local video dimensions: {}x{}", width, height);
+        log::info!("Local video dimensions: {}x{}", width, height);
 
-        let max_height = height;
-        let mut formats: Vec<String> = vec!["Best".to_string()];
-        if max_height >= 4320 {
-            formats.push("8K".to_string());
-        }
-        if max_height >= 2160 {
-            formats.push("4K".to_string());
-        }
-        if max_height >= 1440 {
-            formats.push("1440p".to_string());
-        }
-        if max_height >= 1080 {
-            formats.push("1080p".to_string());
-        }
-        if max_height >= 720 {
-            formats.push("720p".to_string());
-        }
-        if max_height >= 480 {
-            formats.push("480p".to_string());
-        }
-        formats.push("Audio Only".to_string());
+        let formats = quality_labels(height, true);
 
         return Ok(VideoMetadata {
             title,
             duration,
             formats,
+            available_formats,
+            audio_tracks,
             preview_url: Some(url), // Local path is the preview URL
+            preview_expires_at: None,
+            canonical_url: None,
+            start_time: None,
+            end_time: None,
+            uploader: None,
+            video_id: None,
         });
     }
 
+    let settings = read_app_settings(&app).unwrap_or_default();
+    let cache_ttl = settings
+        .metadata_cache_ttl_secs
+        .unwrap_or(metadata_cache::DEFAULT_TTL_SECS);
+    let cache_max_bytes = settings
+        .metadata_cache_max_bytes
+        .unwrap_or(metadata_cache::DEFAULT_MAX_BYTES);
+
+    if !force_refresh.unwrap_or(false) {
+        if let Some(cached) = metadata_cache::lookup(&app, &url, cache_ttl) {
+            if !preview::needs_refresh(cached.preview_expires_at) {
+                if let Some(ref preview_path) = cached.preview_url {
+                    if !preview_path.starts_with("http") {
+                        preview::touch(std::path::Path::new(preview_path));
+                    }
+                }
+                preview::watch_expiry(&app, &url, &cached);
+                return Ok(with_link(cached, link));
+            }
+        }
+    }
+
+    let ytdlp = get_ytdlp(&app)?;
     let is_youtube = url.contains("youtube.com") || url.contains("youtu.be");
 
     let output = if is_youtube {
-        create_windowless_command(&ytdlp_path)
-            .args(&["--dump-json", "--flat-playlist", "--no-warnings", &url])
+        ytdlp.command()
+            .args(validation::ytdlp_args(
+                ["--dump-json", "--no-playlist", "--no-warnings"],
+                &url,
+            ))
             .output()
             .map_err(|e| format!("Failed to execute yt-dlp: {}", e))?
     } else {
-        // For non-YouTube (TikTok, Insta), we download the video to temp to ensure playback
-        let temp_dir = std::env::temp_dir();
-        // Use a consistent name pattern but include ID to avoid collision if needed
-        let output_template = temp_dir.join("clipme_preview_%(id)s.%(ext)s");
+        // For non-YouTube (TikTok, Insta) stream URLs rarely play in the webview,
+        // so fetch a small rendition into the managed preview cache instead.
+        // `--no-playlist` does nothing for playlist-only URLs, so check first
+        // rather than downloading a preview of every entry.
+        if is_playlist_url(&ytdlp, &url)? {
+            return Err(
+                "This URL is a playlist or channel, open it as a playlist instead".to_string(),
+            );
+        }
+        let preview_dir = preview::preview_dir(&app)?;
+        let output_template = preview_dir.join("%(extractor)s_%(id)s.%(ext)s");
         let template_str = output_template.to_string_lossy().to_string();
 
-        println!("Downloading preview to: {}", template_str);
-
-        create_windowless_command(&ytdlp_path)
-            .args(&[
-                "--print-json",
-                "--no-warnings",
-                "-o",
-                &template_str,
-                "--force-overwrites",
+        log::info!("Downloading preview to: {}", template_str);
+
+        ytdlp.command()
+            .args(validation::ytdlp_args(
+                [
+                    "--print-json",
+                    "--no-playlist",
+                    "--no-warnings",
+                    "-f",
+                    preview::PREVIEW_DOWNLOAD_SELECTOR,
+                    "-o",
+                    &template_str,
+                    "--force-overwrites",
+                ],
                 &url,
-            ])
+            ))
             .output()
             .map_err(|e| format!("Failed to execute yt-dlp: {}", e))?
     };
@@ -251,9 +725,15 @@ async fn get_video_metadata(app: AppHandle, url: String) -> Result<VideoMetadata
         return Err(format!("yt-dlp error: {}", stderr));
     }
 
+    // Playlist-only URLs still print one object per entry; use get_playlist for those
     let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut lines = stdout.lines().filter(|line| !line.trim().is_empty());
+    let first_line = lines.next().unwrap_or_default();
+    if lines.next().is_some() {
+        return Err("This URL is a playlist or channel, open it as a playlist instead".to_string());
+    }
     let json_val: serde_json::Value =
-        serde_json::from_str(&stdout).map_err(|e| format!("Failed to parse JSON: {}", e))?;
+        serde_json::from_str(first_line).map_err(|e| format!("Failed to parse JSON: {}", e))?;
 
     let title = json_val["title"]
         .as_str()
@@ -261,44 +741,24 @@ async fn get_video_metadata(app: AppHandle, url: String) -> Result<VideoMetadata
         .to_string();
     let duration = json_val["duration"].as_f64().unwrap_or(0.0);
 
+    let available_formats = parse_ytdlp_formats(&json_val);
+    let audio_tracks = remote_audio_tracks(&json_val);
+
     // Parse max height from available formats
-    let mut max_height: u64 = 0;
-    if let Some(formats_arr) = json_val["formats"].as_array() {
-        for fmt in formats_arr {
-            if let Some(h) = fmt["height"].as_u64() {
-                if h > max_height {
-                    max_height = h;
-                }
-            }
-        }
-    }
+    let mut max_height: u64 = available_formats
+        .iter()
+        .filter_map(|f| f.height)
+        .max()
+        .unwrap_or(0);
     // Fallback: check video's direct height field
     if max_height == 0 {
         max_height = json_val["height"].as_u64().unwrap_or(1080);
     }
 
-    println!("Detected max video height: {}p", max_height);
+    log::info!("Detected max video height: {}p", max_height);
 
     // Build dynamic format list based on max height
-    let mut formats: Vec<String> = vec!["Best".to_string()];
-    if max_height >= 4320 {
-        formats.push("8K".to_string());
-    }
-    if max_height >= 2160 {
-        formats.push("4K".to_string());
-    }
-    if max_height >= 1440 {
-        formats.push("1440p".to_string());
-    }
-    if max_height >= 1080 {
-        formats.push("1080p".to_string());
-    }
-    if max_height >= 720 {
-        formats.push("720p".to_string());
-    }
-    if max_height >= 480 {
-        formats.push("480p".to_string());
-    }
+    let formats = quality_labels(max_height, false);
 
     // Extract preview_url
     let mut preview_url = None;
@@ -306,40 +766,99 @@ async fn get_video_metadata(app: AppHandle, url: String) -> Result<VideoMetadata
     if !is_youtube {
         // If we downloaded it, the filename is the path
         if let Some(path) = json_val["filename"].as_str() {
-            preview_url = Some(path.to_string());
+            let max_bytes = settings
+                .preview_cache_max_bytes
+                .unwrap_or(preview::DEFAULT_CACHE_MAX_BYTES);
+            let playable = preview::finalize_preview(&app, std::path::Path::new(path), &json_val, max_bytes);
+            preview_url = Some(playable.to_string_lossy().to_string());
         }
     }
 
-    // Fallback (for YouTube or if download didn't return filename)
+    // Only a muxed stream plays without separate audio; split (DASH) sources
+    // get no preview URL rather than a silent or video-less one
     if preview_url.is_none() {
-        // Existing logic: find best mp4 url
-        if let Some(formats_arr) = json_val["formats"].as_array() {
-            for fmt in formats_arr.iter().rev() {
-                if let Some(url) = fmt["url"].as_str() {
-                    if url.starts_with("http") {
-                        preview_url = Some(url.to_string());
-                        break;
-                    }
-                }
-            }
-        }
+        preview_url = preview::select_preview_format(&json_val);
     }
 
-    // Final fallback
-    if preview_url.is_none() {
+    // Sources without a format list are a single stream, e.g. a direct file link
+    if preview_url.is_none() && json_val["formats"].as_array().is_none() {
         preview_url = json_val["url"].as_str().map(|s| s.to_string());
     }
 
-    Ok(VideoMetadata {
+    let preview_expires_at = preview_url
+        .as_deref()
+        .filter(|u| u.starts_with("http"))
+        .and_then(preview::stream_url_expiry);
+
+    let metadata = VideoMetadata {
         title,
         duration,
         formats,
+        available_formats,
+        audio_tracks,
         preview_url,
-    })
+        preview_expires_at,
+        canonical_url: None,
+        start_time: None,
+        end_time: None,
+        uploader: json_val["uploader"]
+            .as_str()
+            .or(json_val["channel"].as_str())
+            .map(String::from),
+        video_id: json_val["id"].as_str().map(String::from),
+    };
+
+    if let Err(e) = metadata_cache::store(&app, &url, &json_val, &metadata, cache_max_bytes) {
+        log::info!("Failed to cache metadata: {}", e);
+    }
+
+    preview::watch_expiry(&app, &url, &metadata);
+    Ok(with_link(metadata, link))
+}
+
+/// Attach the per-request link details; these are never cached
+fn with_link(metadata: VideoMetadata, link: links::NormalizedLink) -> VideoMetadata {
+    VideoMetadata {
+        canonical_url: Some(link.url),
+        start_time: link.start,
+        end_time: link.end,
+        ..metadata
+    }
+}
+
+/// Whether yt-dlp sees `url` as a playlist or channel, from a flat probe that
+/// downloads nothing
+fn is_playlist_url(ytdlp: &toolchain::Sidecar, url: &str) -> Result<bool, String> {
+    let output = ytdlp
+        .command()
+        .args(validation::ytdlp_args(
+            [
+                "--flat-playlist",
+                "--dump-single-json",
+                "--no-playlist",
+                "--no-warnings",
+            ],
+            url,
+        ))
+        .output()
+        .map_err(|e| format!("Failed to execute yt-dlp: {}", e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("yt-dlp error: {}", stderr));
+    }
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| format!("Failed to parse JSON: {}", e))?;
+    Ok(matches!(
+        json["_type"].as_str(),
+        Some("playlist") | Some("multi_video")
+    ))
 }
 
 #[tauri::command]
 async fn set_download_path(state: State<'_, AppState>, path: String) -> Result<(), String> {
+    if !std::path::Path::new(&path).is_absolute() {
+        return Err("Download folder must be an absolute path".to_string());
+    }
     let mut path_lock = state
         .download_path
         .lock()
@@ -405,7 +924,7 @@ async fn reset_app(app: AppHandle) -> Result<(), String> {
     }
 
     // Remove license file
-    let license_file = config_dir.join("license.json");
+    let license_file = config_dir.join(license::LICENSE_FILE);
     if license_file.exists() {
         fs::remove_file(&license_file).ok();
     }
@@ -416,10 +935,31 @@ async fn reset_app(app: AppHandle) -> Result<(), String> {
 #[derive(Serialize, Deserialize, Default, Debug, Clone)]
 pub struct AppSettings {
     pub preferred_quality: Option<String>,
+    pub metadata_cache_ttl_secs: Option<u64>,
+    pub metadata_cache_max_bytes: Option<u64>,
+    pub preview_cache_max_bytes: Option<u64>,
+    /// Release feed for yt-dlp updates; defaults to yt-dlp's GitHub releases
+    pub ytdlp_update_feed: Option<String>,
+    /// Tool name -> binary used instead of the bundled or system one
+    #[serde(default)]
+    pub tool_paths: HashMap<String, String>,
+    /// Tool name -> arguments passed before our own on every run
+    #[serde(default)]
+    pub tool_args: HashMap<String, Vec<String>>,
+    /// Output name template; see `naming` for the variables
+    #[serde(default)]
+    pub filename_template: Option<String>,
+    #[serde(default)]
+    pub collision_policy: Option<naming::CollisionPolicy>,
 }
 
 #[tauri::command]
 async fn get_app_settings(app: AppHandle) -> Result<AppSettings, String> {
+    read_app_settings(&app)
+}
+
+/// Load settings from disk, falling back to defaults when none were saved yet
+fn read_app_settings(app: &AppHandle) -> Result<AppSettings, String> {
     let config_dir = app
         .path()
         .app_config_dir()
@@ -440,6 +980,11 @@ async fn get_app_settings(app: AppHandle) -> Result<AppSettings, String> {
 
 #[tauri::command]
 async fn save_app_settings(app: AppHandle, settings: AppSettings) -> Result<(), String> {
+    toolchain::validate_tool_settings(&app, &settings)?;
+    if let Some(template) = &settings.filename_template {
+        naming::validate(template)?;
+    }
+
     let config_dir = app
         .path()
         .app_config_dir()
@@ -459,136 +1004,22 @@ async fn save_app_settings(app: AppHandle, settings: AppSettings) -> Result<(),
     Ok(())
 }
 
-// License verification structures
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct LicenseInfo {
-    pub is_valid: bool,
-    pub is_pro: bool,
-    pub license_key: Option<String>,
-    pub email: Option<String>,
-}
-
-impl Default for LicenseInfo {
-    fn default() -> Self {
-        LicenseInfo {
-            is_valid: false,
-            is_pro: false,
-            license_key: None,
-            email: None,
-        }
-    }
-}
-
-const GUMROAD_PRODUCT_ID: &str = "VkMvNrW6QMqbIgvlt4L6xw==";
-
-#[tauri::command]
-async fn verify_license(app: AppHandle, license_key: String) -> Result<LicenseInfo, String> {
-    println!("Verifying license: {}", license_key);
-
-    // Use async reqwest client
-    let client = reqwest::Client::new();
-    let response = client
-        .post("https://api.gumroad.com/v2/licenses/verify")
-        .form(&[
-            ("product_id", GUMROAD_PRODUCT_ID),
-            ("license_key", license_key.as_str()),
-        ])
-        .send()
-        .await
-        .map_err(|e| format!("Failed to verify license: {}", e))?;
-
-    let json: serde_json::Value = response
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse response: {}", e))?;
-
-    println!("Gumroad response: {:?}", json);
-
-    let success = json["success"].as_bool().unwrap_or(false);
-
-    if success {
-        let purchase = &json["purchase"];
-        let email = purchase["email"].as_str().map(String::from);
-        let refunded = purchase["refunded"].as_bool().unwrap_or(false);
-        let subscription_ended = purchase["subscription_ended_at"].as_str().is_some();
-        let cancelled =
-            purchase["subscription_cancelled_at"].as_str().is_some() && subscription_ended;
-
-        // License is valid if not refunded and subscription hasn't ended
-        let is_valid = !refunded && !cancelled;
-
-        let license_info = LicenseInfo {
-            is_valid,
-            is_pro: is_valid,
-            license_key: Some(license_key.clone()),
-            email,
-        };
-
-        // Store license if valid
-        if is_valid {
-            let config_dir = app
-                .path()
-                .app_config_dir()
-                .map_err(|e| format!("Failed to get config dir: {}", e))?;
-            if !config_dir.exists() {
-                fs::create_dir_all(&config_dir)
-                    .map_err(|e| format!("Failed to create config dir: {}", e))?;
-            }
-            let license_file = config_dir.join("license.json");
-            let json_str = serde_json::to_string(&license_info)
-                .map_err(|e| format!("Failed to serialize license: {}", e))?;
-            fs::write(&license_file, json_str)
-                .map_err(|e| format!("Failed to save license: {}", e))?;
-        }
-
-        Ok(license_info)
-    } else {
-        let message = json["message"].as_str().unwrap_or("Invalid license key");
-        Err(message.to_string())
-    }
-}
-
-#[tauri::command]
-async fn get_license_status(app: AppHandle) -> Result<LicenseInfo, String> {
-    let config_dir = app
-        .path()
-        .app_config_dir()
-        .map_err(|e| format!("Failed to get config dir: {}", e))?;
-    let license_file = config_dir.join("license.json");
-
-    if license_file.exists() {
-        let content = fs::read_to_string(&license_file)
-            .map_err(|e| format!("Failed to read license: {}", e))?;
-        let license_info: LicenseInfo = serde_json::from_str(&content)
-            .map_err(|e| format!("Failed to parse license: {}", e))?;
-        Ok(license_info)
-    } else {
-        Ok(LicenseInfo::default())
-    }
-}
-
-#[tauri::command]
-async fn clear_license(app: AppHandle) -> Result<(), String> {
-    let config_dir = app
-        .path()
-        .app_config_dir()
-        .map_err(|e| format!("Failed to get config dir: {}", e))?;
-    let license_file = config_dir.join("license.json");
-    if license_file.exists() {
-        fs::remove_file(&license_file).map_err(|e| format!("Failed to remove license: {}", e))?;
-    }
-    Ok(())
-}
-
 #[tauri::command]
 async fn cancel_download(state: State<'_, AppState>) -> Result<(), String> {
-    println!("Cancelling download...");
+    log::info!("Cancelling download...");
+    {
+        let mut cancel_lock = state
+            .cancel_requested
+            .lock()
+            .map_err(|_| "Failed to lock state")?;
+        *cancel_lock = true;
+    }
     let mut pid_lock = state
         .download_pid
         .lock()
         .map_err(|_| "Failed to lock state")?;
     if let Some(pid) = *pid_lock {
-        println!("Killing process {}", pid);
+        log::info!("Killing process {}", pid);
         #[cfg(not(windows))]
         {
             let _ = create_windowless_command_str("kill").arg(pid.to_string()).output();
@@ -607,7 +1038,7 @@ async fn cancel_download(state: State<'_, AppState>) -> Result<(), String> {
             .lock()
             .map_err(|_| "Failed to lock state")?;
         if let Some(ref path) = *file_lock {
-            println!("Cleaning up file: {:?}", path);
+            log::info!("Cleaning up file: {:?}", path);
             // Delete main file
             let _ = fs::remove_file(path);
 
@@ -619,7 +1050,7 @@ async fn cancel_download(state: State<'_, AppState>) -> Result<(), String> {
             // Delete .temp.mp4 variant (from two-step transcode)
             if path_str.ends_with(".mp4") {
                 let temp_path = path_str.replace(".mp4", ".temp.mp4");
-                println!("Cleaning up temp file: {}", temp_path);
+                log::info!("Cleaning up temp file: {}", temp_path);
                 let _ = fs::remove_file(&temp_path);
                 let _ = fs::remove_file(format!("{}.part", temp_path));
             }
@@ -639,29 +1070,6 @@ async fn cancel_download(state: State<'_, AppState>) -> Result<(), String> {
     Ok(())
 }
 
-fn sanitize_filename(name: &str) -> String {
-    name.chars()
-        .map(|c| {
-            if c.is_alphanumeric() || c == '-' || c == '_' || c == ' ' {
-                c
-            } else {
-                '_'
-            }
-        })
-        .collect()
-}
-
-fn parse_ffmpeg_time(time_str: &str) -> Option<f64> {
-    let parts: Vec<&str> = time_str.split(':').collect();
-    if parts.len() == 3 {
-        let h = parts[0].parse::<f64>().ok()?;
-        let m = parts[1].parse::<f64>().ok()?;
-        let s = parts[2].parse::<f64>().ok()?;
-        return Some(h * 3600.0 + m * 60.0 + s);
-    }
-    None
-}
-
 // Helper to read until \n or \r
 fn read_until_delimiter<R: Read>(
     mut reader: R,
@@ -702,51 +1110,138 @@ fn read_until_delimiter<R: Read>(
     })
 }
 
+/// Folder finished clips are written to: the user's choice or Downloads/YT_Clipper
+fn resolve_output_dir(app: &AppHandle, state: &AppState) -> Result<PathBuf, String> {
+    let path_lock = state
+        .download_path
+        .lock()
+        .map_err(|_| "Failed to lock state")?;
+    if let Some(ref custom_path) = *path_lock {
+        return Ok(custom_path.clone());
+    }
+    Ok(app
+        .path()
+        .download_dir()
+        .map_err(|e| e.to_string())?
+        .join("YT_Clipper"))
+}
+
+/// Everything needed to produce one clip; shared by single downloads and batches
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ClipJob {
+    pub url: String,
+    pub title: String,
+    #[serde(deserialize_with = "timecode::deserialize_seconds")]
+    pub start: f64,
+    #[serde(deserialize_with = "timecode::deserialize_seconds")]
+    pub end: f64,
+    pub quality: String,
+    pub format: String,
+    pub id: u64,
+    #[serde(default)]
+    pub format_id: Option<String>,
+    #[serde(default)]
+    pub video_format_id: Option<String>,
+    #[serde(default)]
+    pub audio_tracks: Vec<String>,
+}
+
 #[tauri::command]
 async fn download_clip(
     app: AppHandle,
     state: State<'_, AppState>,
     url: String,
     title: String,
-    start: f64,
-    end: f64,
+    start: timecode::TimeValue,
+    end: timecode::TimeValue,
     quality: String,
     format: String,
     id: u64,
-) -> Result<String, String> {
-    println!(
+    format_id: Option<String>,
+    video_format_id: Option<String>,
+    audio_format_id: Option<String>,
+    audio_tracks: Option<Vec<String>>,
+) -> Result<String, entitlements::CommandError> {
+    // Explicit track selection wins over the single audio format id
+    let audio_tracks = audio_tracks
+        .filter(|tracks| !tracks.is_empty())
+        .or_else(|| audio_format_id.map(|id| vec![id]))
+        .unwrap_or_default();
+
+    let job = ClipJob {
+        url,
+        title,
+        start: start.0,
+        end: end.0,
+        quality,
+        format,
+        id,
+        format_id,
+        video_format_id,
+        audio_tracks,
+    };
+    run_clip_job(&app, &state, job)
+}
+
+/// A new job starts un-cancelled, so the flag set by `cancel_download` tells
+/// a killed job apart from a failed one when it is recorded
+fn clear_cancel(state: &AppState) {
+    if let Ok(mut cancel) = state.cancel_requested.lock() {
+        *cancel = false;
+    }
+}
+
+/// Run a single clip job and record it in history, whatever the outcome.
+/// Returns the path of the finished file.
+fn run_clip_job(
+    app: &AppHandle,
+    state: &AppState,
+    job: ClipJob,
+) -> Result<String, entitlements::CommandError> {
+    entitlements::require_clip(app, &job)?;
+    clear_cancel(state);
+    let recorder = history::JobRecorder::start(app, history::JobParams::Clip(job.clone()));
+    let result = render_clip(app, state, job);
+    recorder.finish(result.as_ref().map(std::slice::from_ref));
+    Ok(result?)
+}
+
+/// Download or encode a single clip, emitting `download-progress` events.
+/// Returns the path of the finished file.
+fn render_clip(app: &AppHandle, state: &AppState, job: ClipJob) -> Result<String, JobError> {
+    let ClipJob {
+        url,
+        title,
+        start,
+        end,
+        quality,
+        format,
+        id,
+        format_id,
+        video_format_id,
+        audio_tracks,
+    } = job;
+
+    let source = validation::validate_source(app, &url).kind(ErrorKind::InvalidInput)?;
+    let url = source.as_arg();
+
+    // Cut on exact frame boundaries so clips don't gain or lose a frame at either end
+    let (start, end, frame_duration) = frames::snap_clip_range(app, &url, start, end);
+
+    log::info!(
         "Processing clip: {} ({}-{}) Quality: {} Format: {} ID: {}",
         url, start, end, quality, format, id
     );
 
+    let format_arg = resolve_format_selector(
+        &quality,
+        format_id.as_deref(),
+        video_format_id.as_deref(),
+        &audio_tracks,
+    ).kind(ErrorKind::InvalidInput)?;
+
     // Get binary paths (bundled or system fallback)
-    let ffmpeg_path = get_ffmpeg_path(&app);
-    let ytdlp_path = get_ytdlp_path(&app);
-
-    let safe_title = sanitize_filename(&title);
-    let timestamp = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
-
-    // Setup path
-    let output_dir = {
-        let path_lock = state
-            .download_path
-            .lock()
-            .map_err(|_| "Failed to lock state")?;
-        if let Some(ref custom_path) = *path_lock {
-            custom_path.clone()
-        } else {
-            app.path()
-                .download_dir()
-                .map_err(|e| e.to_string())?
-                .join("YT_Clipper")
-        }
-    };
-    if !output_dir.exists() {
-        std::fs::create_dir_all(&output_dir).map_err(|e| e.to_string())?;
-    }
+    let ffmpeg = get_ffmpeg(app).kind(ErrorKind::Toolchain)?;
 
     // Use selected format for extension
     let ext = if format.is_empty() {
@@ -754,8 +1249,20 @@ async fn download_clip(
     } else {
         format.to_lowercase()
     };
-    let filename = format!("{}_clip_{}.{}", safe_title, timestamp, ext);
-    let output_path = output_dir.join(&filename);
+    let fields = naming::NameFields {
+        start,
+        end,
+        ..naming::fields_for(app, &url, &title, &quality)
+    };
+    let output_path = match naming::output_target(app, state, &fields, &ext, &[])
+        .kind(ErrorKind::Io)?
+    {
+        naming::Target::Write(path) => path,
+        naming::Target::Skip(path) => {
+            log::info!("Keeping existing {}", path.display());
+            return Ok(path.to_string_lossy().to_string());
+        }
+    };
     let output_path_str = output_path.to_string_lossy().to_string();
 
     {
@@ -766,11 +1273,11 @@ async fn download_clip(
         *file_lock = Some(output_path.clone());
     }
 
-    let is_local_file = std::path::Path::new(&url).exists();
+    let is_local_file = source.is_local();
     let total_duration = end - start;
 
     if is_local_file {
-        println!("Local file clipping mode");
+        log::info!("Local file clipping mode");
 
         let _ = app.emit(
             "download-progress",
@@ -789,11 +1296,21 @@ async fn download_clip(
             "-i".to_string(),
             url.clone(),
             "-ss".to_string(),
-            start.to_string(),
+            frames::format_time_arg(start),
             "-t".to_string(),
-            total_duration.to_string(),
+            frames::format_time_arg(frames::output_duration(start, end, frame_duration)),
         ];
 
+        if !audio_tracks.is_empty() {
+            let ffprobe = get_ffprobe(app).kind(ErrorKind::Toolchain)?;
+            let probe = probe_local_file(&ffprobe, &url)?;
+            ffmpeg_args.extend(local_stream_map_args(
+                &probe,
+                &audio_tracks,
+                quality != "Audio Only",
+            ).kind(ErrorKind::InvalidInput)?);
+        }
+
         let mut video_filters = Vec::new();
 
         match quality.as_str() {
@@ -858,13 +1375,14 @@ async fn download_clip(
 
         ffmpeg_args.push(output_path_str.clone());
 
-        println!("Running FFmpeg: {:?}", ffmpeg_args);
+        log::info!("Running FFmpeg: {:?}", ffmpeg_args);
 
-        let mut child = create_windowless_command(&ffmpeg_path)
+        let mut child = ffmpeg.command()
             .args(&ffmpeg_args)
             .stderr(Stdio::piped())
             .spawn()
-            .map_err(|e| format!("Failed to start ffmpeg: {}", e))?;
+            .map_err(|e| format!("Failed to start ffmpeg: {}", e))
+            .kind(ErrorKind::EncodeFailed)?;
 
         let pid = child.id();
         {
@@ -891,7 +1409,8 @@ async fn download_clip(
 
         let status = child
             .wait()
-            .map_err(|e| format!("Failed to wait on ffmpeg: {}", e))?;
+            .map_err(|e| format!("Failed to wait on ffmpeg: {}", e))
+            .kind(ErrorKind::EncodeFailed)?;
 
         {
             let mut pid_lock = state
@@ -902,7 +1421,7 @@ async fn download_clip(
         }
 
         if !status.success() {
-            return Err("Local clip encoding failed".to_string());
+            return Err(JobError::new(ErrorKind::EncodeFailed, "Local clip encoding failed"));
         }
 
         let _ = app.emit(
@@ -917,25 +1436,33 @@ async fn download_clip(
             },
         );
 
-        return Ok("Local clip complete".to_string());
+        {
+            let mut file_lock = state
+                .current_file_path
+                .lock()
+                .map_err(|_| "Failed to lock state")?;
+            *file_lock = None;
+        }
+
+        return Ok(output_path_str);
     }
 
     // --- YT-DLP LOGIC FOR REMOTE URLS ---
 
-    let section_range = format!("*{}-{}", start, end);
-
-    let format_arg = match quality.as_str() {
-        "8K" => "bestvideo[height>=4320]+bestaudio/bestvideo[height>=2160]+bestaudio/best",
-        "4K" => "bestvideo[height=2160]+bestaudio/bestvideo[height>=2160]+bestaudio/best",
-        "1440p" => "bestvideo[height=1440]+bestaudio/bestvideo[height<=1440]+bestaudio/best",
-        "1080p" => "bestvideo[height=1080][vcodec^=avc]+bestaudio[ext=m4a]/bestvideo[height=1080]+bestaudio/best[height<=1080]",
-        "720p" => "bestvideo[height=720][vcodec^=avc]+bestaudio[ext=m4a]/bestvideo[height=720]+bestaudio/best[height<=720]",
-        "480p" => "bestvideo[height=480][vcodec^=avc]+bestaudio[ext=m4a]/bestvideo[height=480]+bestaudio/best[height<=480]",
-        "Audio Only" => "bestaudio/best",
-        _ => "bestvideo+bestaudio/best", 
-    };
+    let section_range = format!(
+        "*{}-{}",
+        frames::format_time_arg(start),
+        frames::format_time_arg(end)
+    );
 
-    let is_high_res = quality == "8K" || quality == "4K" || quality == "1440p" || quality == "Best";
+    let explicit_format = format_id.is_some() || video_format_id.is_some();
+    let selected = selected_video_format(
+        app,
+        &url,
+        format_id.as_deref(),
+        video_format_id.as_deref(),
+    );
+    let is_high_res = needs_hevc_transcode(&quality, explicit_format, selected.as_ref());
 
     // For high-res remote, we typically download then transcode to HEVC.
     // If output format is NOT MP4/MKV/MOV, HEVC might be weird.
@@ -955,16 +1482,19 @@ async fn download_clip(
         (output_path_str.clone(), output_path_str.clone())
     };
 
-    println!("Outputting to: {} (final: {})", download_path, final_path);
+    log::info!("Outputting to: {} (final: {})", download_path, final_path);
     let progress_template = "PROGRESS|%(progress._percent_str)s|%(progress._speed_str)s|%(progress._eta_str)s|%(progress._total_bytes_estimate_str)s";
 
     let mut args = vec![
         "--download-sections".to_string(),
         section_range,
         "-o".to_string(),
-        download_path.clone(),
+        // yt-dlp treats `%` in the output path as a template field
+        download_path.replace('%', "%%"),
+        // Collisions were already settled by the collision policy
+        "--force-overwrites".to_string(),
         "-f".to_string(),
-        format_arg.to_string(),
+        format_arg,
         "--merge-output-format".to_string(),
         ext.to_string(), // Use the requested format
         "--newline".to_string(),
@@ -974,14 +1504,19 @@ async fn download_clip(
         progress_template.to_string(),
     ];
 
-    args.push(url);
+    if audio_tracks.len() > 1 {
+        args.push("--audio-multistreams".to_string());
+    }
+
+    let args = validation::ytdlp_args(args, &url);
 
-    let mut child = create_windowless_command(&ytdlp_path)
+    let mut child = get_ytdlp(app).kind(ErrorKind::Toolchain)?.command()
         .args(&args)
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
         .spawn()
-        .map_err(|e| format!("Failed to start download: {}", e))?;
+        .map_err(|e| format!("Failed to start download: {}", e))
+        .kind(ErrorKind::DownloadFailed)?;
 
     let pid = child.id();
     {
@@ -1037,7 +1572,8 @@ async fn download_clip(
 
     let status = child
         .wait()
-        .map_err(|e| format!("Failed to wait on download: {}", e))?;
+        .map_err(|e| format!("Failed to wait on download: {}", e))
+        .kind(ErrorKind::DownloadFailed)?;
 
     {
         let mut pid_lock = state
@@ -1048,7 +1584,7 @@ async fn download_clip(
     }
 
     if !status.success() {
-        return Err("Download failed".to_string());
+        return Err(JobError::new(ErrorKind::DownloadFailed, "Download failed"));
     }
 
     // Emit final progress for download phase
@@ -1066,7 +1602,7 @@ async fn download_clip(
 
     // Step 2: Transcode to HEVC for high-res
     if is_high_res {
-        println!("Starting HEVC transcoding...");
+        log::info!("Starting HEVC transcoding...");
         let _ = app.emit(
             "download-progress",
             DownloadProgress {
@@ -1131,11 +1667,12 @@ async fn download_clip(
             &final_path,
         ];
 
-        let mut transcode_child = create_windowless_command(&ffmpeg_path)
+        let mut transcode_child = ffmpeg.command()
             .args(&ffmpeg_args)
             .stderr(Stdio::piped())
             .spawn()
-            .map_err(|e| format!("Failed to start ffmpeg transcoding: {}", e))?;
+            .map_err(|e| format!("Failed to start ffmpeg transcoding: {}", e))
+            .kind(ErrorKind::EncodeFailed)?;
 
         let transcode_pid = transcode_child.id();
         {
@@ -1169,7 +1706,8 @@ async fn download_clip(
 
         let transcode_output = transcode_child
             .wait()
-            .map_err(|e| format!("Failed to wait on transcode: {}", e))?;
+            .map_err(|e| format!("Failed to wait on transcode: {}", e))
+            .kind(ErrorKind::EncodeFailed)?;
 
         {
             let mut pid_lock = state
@@ -1183,7 +1721,7 @@ async fn download_clip(
         let _ = fs::remove_file(&download_path);
 
         if !transcode_output.success() {
-            return Err("Transcoding failed".to_string());
+            return Err(JobError::new(ErrorKind::EncodeFailed, "Transcoding failed"));
         }
     }
 
@@ -1207,15 +1745,20 @@ async fn download_clip(
         },
     );
 
-    Ok("Download complete".to_string())
+    Ok(final_path)
 }
 
 // Segment struct for multi-clip
 #[derive(Serialize, Deserialize, Debug, Clone)]
-struct ClipSegment {
+pub struct ClipSegment {
     id: String,
+    #[serde(deserialize_with = "timecode::deserialize_seconds")]
     start: f64,
+    #[serde(deserialize_with = "timecode::deserialize_seconds")]
     end: f64,
+    /// Chapter or note carried by imported clip lists
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    label: Option<String>,
 }
 
 #[derive(Clone, Serialize, Debug)]
@@ -1229,6 +1772,23 @@ struct MultiClipProgress {
     session_id: u64,
 }
 
+/// Several ranges of one source, exported as separate files
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MultiClipJob {
+    pub url: String,
+    pub title: String,
+    pub segments: Vec<ClipSegment>,
+    pub quality: String,
+    pub format: String,
+    pub id: u64,
+    #[serde(default)]
+    pub format_id: Option<String>,
+    #[serde(default)]
+    pub video_format_id: Option<String>,
+    #[serde(default)]
+    pub audio_tracks: Vec<String>,
+}
+
 #[tauri::command]
 async fn download_multi_clip(
     app: AppHandle,
@@ -1239,55 +1799,125 @@ async fn download_multi_clip(
     quality: String,
     format: String,
     id: u64,
-) -> Result<Vec<String>, String> {
-    println!(
+    format_id: Option<String>,
+    video_format_id: Option<String>,
+    audio_format_id: Option<String>,
+    audio_tracks: Option<Vec<String>>,
+) -> Result<Vec<String>, entitlements::CommandError> {
+    // Explicit track selection wins over the single audio format id
+    let audio_tracks = audio_tracks
+        .filter(|tracks| !tracks.is_empty())
+        .or_else(|| audio_format_id.map(|id| vec![id]))
+        .unwrap_or_default();
+
+    let job = MultiClipJob {
+        url,
+        title,
+        segments,
+        quality,
+        format,
+        id,
+        format_id,
+        video_format_id,
+        audio_tracks,
+    };
+    run_multi_clip_job(&app, &state, job)
+}
+
+/// Run a multi-clip job and record it in history, whatever the outcome
+fn run_multi_clip_job(
+    app: &AppHandle,
+    state: &AppState,
+    job: MultiClipJob,
+) -> Result<Vec<String>, entitlements::CommandError> {
+    entitlements::require_multi_clip(app, &job)?;
+    clear_cancel(state);
+    let recorder = history::JobRecorder::start(app, history::JobParams::MultiClip(job.clone()));
+    let result = render_multi_clip(app, state, job);
+    recorder.finish(result.as_deref());
+    Ok(result?)
+}
+
+/// Export every segment, emitting `multi-clip-progress` events.
+/// Returns the finished files in segment order.
+fn render_multi_clip(
+    app: &AppHandle,
+    state: &AppState,
+    job: MultiClipJob,
+) -> Result<Vec<String>, JobError> {
+    let MultiClipJob {
+        url,
+        title,
+        segments,
+        quality,
+        format,
+        id,
+        format_id,
+        video_format_id,
+        audio_tracks,
+    } = job;
+
+    log::info!(
         "Processing multi-clip: {} ({} segments) Quality: {} Format: {} ID: {}",
         url, segments.len(), quality, format, id
     );
 
     if segments.is_empty() {
-        return Err("No segments provided".to_string());
+        return Err(JobError::new(ErrorKind::InvalidInput, "No segments provided"));
     }
+    let source = validation::validate_source(app, &url).kind(ErrorKind::InvalidInput)?;
+    let url = source.as_arg();
+
+    let format_arg = resolve_format_selector(
+        &quality,
+        format_id.as_deref(),
+        video_format_id.as_deref(),
+        &audio_tracks,
+    ).kind(ErrorKind::InvalidInput)?;
 
     let total_clips = segments.len();
     let mut results: Vec<String> = Vec::new();
+    // Every path this job has claimed, so no two segments share a file
+    let mut used_paths: Vec<PathBuf> = Vec::new();
 
     // Get binary paths
-    let ffmpeg_path = get_ffmpeg_path(&app);
-    let ytdlp_path = get_ytdlp_path(&app);
-
-    let safe_title = sanitize_filename(&title);
-    let timestamp = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
-
-    // Setup output directory
-    let output_dir = {
-        let path_lock = state
-            .download_path
-            .lock()
-            .map_err(|_| "Failed to lock state")?;
-        if let Some(ref custom_path) = *path_lock {
-            custom_path.clone()
-        } else {
-            app.path()
-                .download_dir()
-                .map_err(|e| e.to_string())?
-                .join("YT_Clipper")
-        }
-    };
-    if !output_dir.exists() {
-        std::fs::create_dir_all(&output_dir).map_err(|e| e.to_string())?;
-    }
+    let ffmpeg = get_ffmpeg(app).kind(ErrorKind::Toolchain)?;
+
+    // Shared by every segment, so they also share the timestamp
+    let base_fields = naming::fields_for(app, &url, &title, &quality);
 
-    let is_local_file = std::path::Path::new(&url).exists();
+    let is_local_file = source.is_local();
     let ext = if format.is_empty() { "mp4".to_string() } else { format.to_lowercase() };
 
+    let stream_map_args = if is_local_file && !audio_tracks.is_empty() {
+        let ffprobe = get_ffprobe(app).kind(ErrorKind::Toolchain)?;
+        let probe = probe_local_file(&ffprobe, &url)?;
+        local_stream_map_args(&probe, &audio_tracks, true).kind(ErrorKind::InvalidInput)?
+    } else {
+        Vec::new()
+    };
+
     for (index, segment) in segments.iter().enumerate() {
         let clip_num = index + 1;
-        let filename = format!("{}_clip{}_{}.{}", safe_title, clip_num, timestamp, ext);
-        let output_path = output_dir.join(&filename);
+        let fields = naming::NameFields {
+            start: segment.start,
+            end: segment.end,
+            segment_label: segment.label.clone(),
+            n: Some(clip_num),
+            ..base_fields.clone()
+        };
+        let output_path = match naming::output_target(app, state, &fields, &ext, &used_paths)
+            .kind(ErrorKind::Io)?
+        {
+            naming::Target::Write(path) => path,
+            naming::Target::Skip(path) => {
+                log::info!("Keeping existing {}", path.display());
+                results.push(path.to_string_lossy().to_string());
+                used_paths.push(path);
+                continue;
+            }
+        };
+        used_paths.push(output_path.clone());
         let output_path_str = output_path.to_string_lossy().to_string();
 
         // Emit progress for this clip
@@ -1312,18 +1942,22 @@ async fn download_multi_clip(
             *file_lock = Some(output_path.clone());
         }
 
-        let clip_duration = segment.end - segment.start;
+        let (start, end, frame_duration) =
+            frames::snap_clip_range(app, &url, segment.start, segment.end);
 
         if is_local_file {
             // Local file: use FFmpeg directly
-            let ffmpeg_args = vec![
+            let mut ffmpeg_args = vec![
                 "-y".to_string(),
                 "-i".to_string(),
                 url.clone(),
                 "-ss".to_string(),
-                segment.start.to_string(),
+                frames::format_time_arg(start),
                 "-t".to_string(),
-                clip_duration.to_string(),
+                frames::format_time_arg(frames::output_duration(start, end, frame_duration)),
+            ];
+            ffmpeg_args.extend(stream_map_args.iter().cloned());
+            ffmpeg_args.extend(vec![
                 "-c:v".to_string(),
                 "libx264".to_string(),
                 "-preset".to_string(),
@@ -1335,13 +1969,14 @@ async fn download_multi_clip(
                 "-b:a".to_string(),
                 "192k".to_string(),
                 output_path_str.clone(),
-            ];
+            ]);
 
-            let mut child = create_windowless_command(&ffmpeg_path)
+            let mut child = ffmpeg.command()
                 .args(&ffmpeg_args)
                 .stderr(Stdio::piped())
                 .spawn()
-                .map_err(|e| format!("Failed to start ffmpeg for clip {}: {}", clip_num, e))?;
+                .map_err(|e| format!("Failed to start ffmpeg for clip {}: {}", clip_num, e))
+                .kind(ErrorKind::EncodeFailed)?;
 
             let pid = child.id();
             {
@@ -1349,43 +1984,53 @@ async fn download_multi_clip(
                 *pid_lock = Some(pid);
             }
 
-            let status = child.wait().map_err(|e| format!("FFmpeg failed for clip {}: {}", clip_num, e))?;
+            let status = child
+
+                .wait()
+
+                .map_err(|e| format!("FFmpeg failed for clip {}: {}", clip_num, e))
+
+                .kind(ErrorKind::EncodeFailed)?;
 
             if !status.success() {
-                return Err(format!("Clip {} encoding failed", clip_num));
+                return Err(JobError::new(
+                    ErrorKind::EncodeFailed,
+                    format!("Clip {} encoding failed", clip_num),
+                ));
             }
         } else {
             // Remote URL: use yt-dlp
-            let section_range = format!("*{}-{}", segment.start, segment.end);
-            let format_arg = match quality.as_str() {
-                "1080p" => "bestvideo[height=1080][vcodec^=avc]+bestaudio[ext=m4a]/bestvideo[height=1080]+bestaudio/best[height<=1080]",
-                "720p" => "bestvideo[height=720][vcodec^=avc]+bestaudio[ext=m4a]/bestvideo[height=720]+bestaudio/best[height<=720]",
-                "480p" => "bestvideo[height=480][vcodec^=avc]+bestaudio[ext=m4a]/bestvideo[height=480]+bestaudio/best[height<=480]",
-                "Audio Only" => "bestaudio/best",
-                _ => "bestvideo+bestaudio/best",
-            };
-
-            let args = vec![
+            let section_range = format!(
+                "*{}-{}",
+                frames::format_time_arg(start),
+                frames::format_time_arg(end)
+            );
+            let mut args = vec![
                 "--download-sections".to_string(),
                 section_range,
                 "-o".to_string(),
-                output_path_str.clone(),
+                output_path_str.replace('%', "%%"),
+                "--force-overwrites".to_string(),
                 "-f".to_string(),
-                format_arg.to_string(),
+                format_arg.clone(),
                 "--merge-output-format".to_string(),
                 ext.to_string(),
                 "--newline".to_string(),
                 "--concurrent-fragments".to_string(),
                 "8".to_string(),
-                url.clone(),
             ];
+            if audio_tracks.len() > 1 {
+                args.push("--audio-multistreams".to_string());
+            }
+            let args = validation::ytdlp_args(args, &url);
 
-            let mut child = create_windowless_command(&ytdlp_path)
+            let mut child = get_ytdlp(app).kind(ErrorKind::Toolchain)?.command()
                 .args(&args)
                 .stdout(Stdio::piped())
                 .stderr(Stdio::piped())
                 .spawn()
-                .map_err(|e| format!("Failed to start yt-dlp for clip {}: {}", clip_num, e))?;
+                .map_err(|e| format!("Failed to start yt-dlp for clip {}: {}", clip_num, e))
+                .kind(ErrorKind::DownloadFailed)?;
 
             let pid = child.id();
             {
@@ -1393,10 +2038,19 @@ async fn download_multi_clip(
                 *pid_lock = Some(pid);
             }
 
-            let status = child.wait().map_err(|e| format!("yt-dlp failed for clip {}: {}", clip_num, e))?;
+            let status = child
+
+                .wait()
+
+                .map_err(|e| format!("yt-dlp failed for clip {}: {}", clip_num, e))
+
+                .kind(ErrorKind::DownloadFailed)?;
 
             if !status.success() {
-                return Err(format!("Clip {} download failed", clip_num));
+                return Err(JobError::new(
+                    ErrorKind::DownloadFailed,
+                    format!("Clip {} download failed", clip_num),
+                ));
             }
         }
 
@@ -1489,7 +2143,7 @@ fn parse_ffmpeg_progress(line: &str, total_duration: f64) -> Option<DownloadProg
     for part in parts {
         if part.starts_with("time=") {
             let val = part.trim_start_matches("time=");
-            current_time = parse_ffmpeg_time(val).unwrap_or(0.0);
+            current_time = timecode::parse_timecode(val, None).unwrap_or(0.0);
         }
         if part.starts_with("bitrate=") {
             speed = part.trim_start_matches("bitrate=").to_string();
@@ -1513,98 +2167,6 @@ fn parse_ffmpeg_progress(line: &str, total_duration: f64) -> Option<DownloadProg
     }
 }
 
-// Download History Item
-#[derive(Serialize, Deserialize, Debug, Clone)]
-struct DownloadHistoryItem {
-    id: String,
-    title: String,
-    url: String,
-    thumbnail: Option<String>,
-    duration: f64,
-    quality: String,
-    format: String,
-    #[serde(rename = "filePath")]
-    file_path: String,
-    #[serde(rename = "fileSize")]
-    file_size: Option<u64>,
-    #[serde(rename = "downloadedAt")]
-    downloaded_at: String,
-}
-
-#[tauri::command]
-async fn get_download_history(app: AppHandle) -> Result<Vec<DownloadHistoryItem>, String> {
-    let config_dir = app
-        .path()
-        .app_config_dir()
-        .map_err(|e| format!("Failed to get config dir: {}", e))?;
-    let history_file = config_dir.join("download_history.json");
-
-    if history_file.exists() {
-        let content = fs::read_to_string(&history_file)
-            .map_err(|e| format!("Failed to read history: {}", e))?;
-        let items: Vec<DownloadHistoryItem> = serde_json::from_str(&content)
-            .unwrap_or_else(|_| Vec::new());
-        Ok(items)
-    } else {
-        Ok(Vec::new())
-    }
-}
-
-#[tauri::command]
-async fn save_download_history(
-    app: AppHandle,
-    item: DownloadHistoryItem,
-) -> Result<(), String> {
-    let config_dir = app
-        .path()
-        .app_config_dir()
-        .map_err(|e| format!("Failed to get config dir: {}", e))?;
-    
-    if !config_dir.exists() {
-        fs::create_dir_all(&config_dir)
-            .map_err(|e| format!("Failed to create config dir: {}", e))?;
-    }
-    
-    let history_file = config_dir.join("download_history.json");
-    
-    // Load existing history
-    let mut items: Vec<DownloadHistoryItem> = if history_file.exists() {
-        let content = fs::read_to_string(&history_file).unwrap_or_else(|_| "[]".to_string());
-        serde_json::from_str(&content).unwrap_or_else(|_| Vec::new())
-    } else {
-        Vec::new()
-    };
-    
-    // Add new item at the beginning
-    items.insert(0, item);
-    
-    // Keep only last 50 items
-    items.truncate(50);
-    
-    // Save
-    let json_str = serde_json::to_string_pretty(&items)
-        .map_err(|e| format!("Failed to serialize history: {}", e))?;
-    fs::write(&history_file, json_str)
-        .map_err(|e| format!("Failed to save history: {}", e))?;
-    
-    Ok(())
-}
-
-#[tauri::command]
-async fn clear_download_history(app: AppHandle) -> Result<(), String> {
-    let config_dir = app
-        .path()
-        .app_config_dir()
-        .map_err(|e| format!("Failed to get config dir: {}", e))?;
-    let history_file = config_dir.join("download_history.json");
-    
-    if history_file.exists() {
-        fs::remove_file(&history_file)
-            .map_err(|e| format!("Failed to clear history: {}", e))?;
-    }
-    Ok(())
-}
-
 #[tauri::command]
 async fn open_file_location(path: String) -> Result<(), String> {
     let path = std::path::Path::new(&path);
@@ -1613,8 +2175,6 @@ async fn open_file_location(path: String) -> Result<(), String> {
         return Err("File not found".to_string());
     }
     
-    let _parent = path.parent().unwrap_or(path);
-    
     #[cfg(target_os = "macos")]
     {
         std::process::Command::new("open")
@@ -1635,6 +2195,7 @@ async fn open_file_location(path: String) -> Result<(), String> {
     
     #[cfg(target_os = "linux")]
     {
+        let parent = path.parent().unwrap_or(path);
         std::process::Command::new("xdg-open")
             .arg(parent)
             .spawn()
@@ -1658,6 +2219,9 @@ pub fn run() {
             download_pid: Mutex::new(None),
             download_path: Mutex::new(None),
             current_file_path: Mutex::new(None),
+            cancel_requested: Mutex::new(false),
+            preview_generation: Mutex::new(0),
+            allowed_local_paths: Mutex::new(Vec::new()),
         })
         .invoke_handler(tauri::generate_handler![
             get_video_metadata,
@@ -1668,17 +2232,69 @@ pub fn run() {
             check_onboarding_complete,
             set_onboarding_complete,
             reset_app,
-            verify_license,
-            get_license_status,
-            clear_license,
+            license::verify_license,
+            license::get_license_status,
+            license::clear_license,
+            logging::get_logs,
+            logging::export_diagnostics,
+            naming::preview_filename,
             get_app_settings,
             save_app_settings,
-            get_download_history,
-            save_download_history,
-            clear_download_history,
+            history::get_download_history,
+            history::get_history_filters,
+            history::rerun_job,
+            clip_list::export_clip_list,
+            clip_list::import_clip_list,
+            project::save_project,
+            project::open_project,
+            project::relink_project_source,
+            project::autosave_project,
+            project::get_autosave,
+            project::discard_autosave,
+            project::get_recent_projects,
+            history::clear_download_history,
+            history::reconcile_history,
+            history::recreate_history_clip,
             open_file_location,
-            download_multi_clip
+            download_multi_clip,
+            playlist::get_playlist,
+            playlist::download_batch,
+            metadata_cache::purge_metadata_cache,
+            preview::refresh_preview_url,
+            filmstrip::generate_filmstrip,
+            waveform::get_waveform,
+            analysis::analyze_cut_points,
+            frames::snap_to_frame,
+            frames::nearest_keyframe,
+            validation::pick_local_video,
+            toolchain::get_toolchain_info,
+            ytdlp_update::get_ytdlp_update_status,
+            ytdlp_update::update_ytdlp,
+            ytdlp_update::rollback_ytdlp,
+            links::take_deep_link_clips,
+            timecode::parse_time,
+            timecode::format_time
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .setup(|app| {
+            logging::init(app.handle());
+            // Previews only live for one session; drop leftovers from a crash
+            preview::clear_previews(app.handle());
+            links::register(app.handle());
+            Ok(())
+        })
+        .on_window_event(|window, event| {
+            // Dropped files count as user-chosen local sources
+            if let tauri::WindowEvent::DragDrop(tauri::DragDropEvent::Drop { paths, .. }) = event {
+                for path in paths {
+                    validation::allow_local_path(window.app_handle(), path);
+                }
+            }
+        })
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|app, event| {
+            if let tauri::RunEvent::Exit = event {
+                preview::clear_previews(app);
+            }
+        });
 }
@@ -0,0 +1,886 @@
+//! Pro licenses as signed tokens. The license server checks a key with Gumroad
+//! and answers in Gumroad's verify format plus a `token`: claims signed with its
+//! ed25519 key. Only what that signature covers is trusted, so editing
+//! `license.json` can't grant Pro, and a token stops working once it expires
+//! unless it's revalidated online.
+
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use ed25519_dalek::{Signature, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+pub const LICENSE_FILE: &str = "license.json";
+/// Fallback device id for systems without a machine id
+const DEVICE_FILE: &str = "device_id";
+const GUMROAD_PRODUCT_ID: &str = "VkMvNrW6QMqbIgvlt4L6xw==";
+/// Where keys are checked. Release builds point this at the license server,
+/// which forwards to Gumroad's verify endpoint and adds the signed token.
+const VERIFY_URL: &str = match option_env!("CLIPME_LICENSE_URL") {
+    Some(url) => url,
+    None => "https://api.gumroad.com/v2/licenses/verify",
+};
+/// The license server's ed25519 public key (base64). This default is a
+/// development key; release builds set `CLIPME_LICENSE_PUBLIC_KEY`.
+const PUBLIC_KEY: &str = match option_env!("CLIPME_LICENSE_PUBLIC_KEY") {
+    Some(key) => key,
+    None => "GpBCZDOMn9ME0ooopdseUhqajeSg49coNGPPevFmjaU=",
+};
+#[cfg(all(not(debug_assertions), not(clipme_license_configured)))]
+compile_error!(
+    "Release builds need CLIPME_LICENSE_URL and CLIPME_LICENSE_PUBLIC_KEY; the defaults are for development"
+);
+const DAY: u64 = 24 * 60 * 60;
+/// Tokens older than this are revalidated online on the next check
+const REVALIDATE_AFTER: u64 = 7 * DAY;
+/// How long past expiry a token is still honoured while the server can't be reached
+const GRACE_PERIOD: u64 = 14 * DAY;
+/// Tolerated clock difference between the server and this machine
+const CLOCK_SKEW: u64 = 5 * 60;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct LicenseInfo {
+    pub is_valid: bool,
+    pub is_pro: bool,
+    pub license_key: Option<String>,
+    pub email: Option<String>,
+    /// Unix time the current token expires
+    #[serde(default)]
+    pub expires_at: Option<u64>,
+    /// The token expired and the license server couldn't be reached; Pro
+    /// stays on until the grace period ends
+    #[serde(default)]
+    pub offline: bool,
+}
+
+/// What the license server signs
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LicenseClaims {
+    pub product_id: String,
+    pub license_key: String,
+    #[serde(default)]
+    pub email: Option<String>,
+    /// `device_id()` of the machine the token was issued to
+    pub device_id: String,
+    pub issued_at: u64,
+    pub expires_at: u64,
+}
+
+impl LicenseClaims {
+    fn info(&self, offline: bool) -> LicenseInfo {
+        LicenseInfo {
+            is_valid: true,
+            is_pro: true,
+            license_key: Some(self.license_key.clone()),
+            email: self.email.clone(),
+            expires_at: Some(self.expires_at),
+            offline,
+        }
+    }
+}
+
+/// Contents of `license.json`
+#[derive(Debug, Clone, PartialEq)]
+enum StoredLicense {
+    Token(String),
+    /// Unsigned license from before tokens, or one confirmed by Gumroad directly.
+    /// Only the key is used, to fetch a token or confirm it again.
+    Legacy(String),
+}
+
+/// How fresh a verified token is
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Standing {
+    Current,
+    /// Valid, but should be revalidated
+    Due,
+    /// Expired; honoured only while the server can't be reached
+    Grace,
+    Expired,
+}
+
+fn standing(claims: &LicenseClaims, now: u64) -> Standing {
+    if now < claims.issued_at.saturating_add(REVALIDATE_AFTER) && now < claims.expires_at {
+        Standing::Current
+    } else if now < claims.expires_at {
+        Standing::Due
+    } else if now < claims.expires_at.saturating_add(GRACE_PERIOD) {
+        Standing::Grace
+    } else {
+        Standing::Expired
+    }
+}
+
+/// Everything a check depends on, so tests can point it at a mock server
+struct Verifier {
+    url: String,
+    key: VerifyingKey,
+    device_id: String,
+}
+
+fn public_key() -> Result<VerifyingKey, String> {
+    let bytes: [u8; 32] = STANDARD
+        .decode(PUBLIC_KEY)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or("Invalid license public key")?;
+    VerifyingKey::from_bytes(&bytes).map_err(|e| format!("Invalid license public key: {}", e))
+}
+
+/// Check a `payload.signature` token and its binding to this product and device.
+/// Expiry is left to `standing`, since an expired token can still be revalidated.
+fn verify_token(
+    token: &str,
+    key: &VerifyingKey,
+    device_id: &str,
+    now: u64,
+) -> Result<LicenseClaims, String> {
+    let (payload, signature) = token.split_once('.').ok_or("Malformed license token")?;
+    let signature = URL_SAFE_NO_PAD
+        .decode(signature)
+        .ok()
+        .and_then(|bytes| Signature::from_slice(&bytes).ok())
+        .ok_or("Malformed license signature")?;
+    key.verify_strict(payload.as_bytes(), &signature)
+        .map_err(|_| "License signature doesn't match".to_string())?;
+
+    let claims: LicenseClaims = URL_SAFE_NO_PAD
+        .decode(payload)
+        .ok()
+        .and_then(|json| serde_json::from_slice(&json).ok())
+        .ok_or("Malformed license claims")?;
+    if claims.product_id != GUMROAD_PRODUCT_ID {
+        return Err("License is for a different product".to_string());
+    }
+    if claims.device_id != device_id {
+        return Err("License was activated on a different device".to_string());
+    }
+    if claims.issued_at > now + CLOCK_SKEW || claims.expires_at <= claims.issued_at {
+        return Err("License token has invalid dates".to_string());
+    }
+    Ok(claims)
+}
+
+/// Answer of the license server
+#[derive(Debug, PartialEq)]
+enum Online {
+    Valid {
+        token: String,
+    },
+    /// A good purchase, but no token: the endpoint is Gumroad itself rather
+    /// than a signing license server
+    Confirmed,
+    /// Refunded, charged back or the subscription ended
+    Revoked,
+    /// Unknown key or wrong product
+    Rejected(String),
+}
+
+/// Ask the license server about `license_key`. `Err` means it couldn't be
+/// reached or answered nonsense, which the grace period covers.
+async fn check_online(
+    url: &str,
+    license_key: &str,
+    device_id: &str,
+    activating: bool,
+) -> Result<Online, String> {
+    let client = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+    let response = client
+        .post(url)
+        .form(&[
+            ("product_id", GUMROAD_PRODUCT_ID),
+            ("license_key", license_key),
+            ("device_id", device_id),
+            // Only a new activation counts as a use of the key
+            (
+                "increment_uses_count",
+                if activating { "true" } else { "false" },
+            ),
+        ])
+        .send()
+        .await
+        .map_err(|e| format!("Failed to reach license server: {}", e))?;
+    let status = response.status();
+    // Gumroad answers unknown keys with a 404 and a JSON body
+    let json: serde_json::Value = response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse license response ({}): {}", status, e))?;
+
+    if !json["success"].as_bool().unwrap_or(false) {
+        if status.is_server_error() {
+            return Err(format!("License server error ({})", status));
+        }
+        let message = json["message"].as_str().unwrap_or("Invalid license key");
+        return Ok(Online::Rejected(message.to_string()));
+    }
+    let purchase = &json["purchase"];
+    let revoked = purchase["refunded"].as_bool().unwrap_or(false)
+        || purchase["chargebacked"].as_bool().unwrap_or(false)
+        || purchase["subscription_ended_at"].as_str().is_some();
+    if revoked {
+        return Ok(Online::Revoked);
+    }
+    match json["token"].as_str() {
+        Some(token) => Ok(Online::Valid {
+            token: token.to_string(),
+        }),
+        None => Ok(Online::Confirmed),
+    }
+}
+
+/// What to do with `license.json` after a check
+#[derive(Debug, PartialEq)]
+enum Store {
+    Keep,
+    Replace(String),
+    /// Save a key confirmed without a token, in the pre-token format
+    Legacy(String),
+    Remove,
+}
+
+/// Keys confirmed online without a token this session, and when. Nothing signed
+/// backs these, so they live in memory only and are confirmed again each launch.
+fn confirmed_keys() -> &'static Mutex<HashMap<String, u64>> {
+    static KEYS: OnceLock<Mutex<HashMap<String, u64>>> = OnceLock::new();
+    KEYS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn confirm_key(license_key: &str, now: u64) {
+    if let Ok(mut keys) = confirmed_keys().lock() {
+        keys.insert(license_key.to_string(), now);
+    }
+}
+
+fn confirmed_at(license_key: &str) -> Option<u64> {
+    confirmed_keys()
+        .lock()
+        .ok()
+        .and_then(|keys| keys.get(license_key).copied())
+}
+
+fn confirmed_info(license_key: &str) -> LicenseInfo {
+    LicenseInfo {
+        is_valid: true,
+        is_pro: true,
+        license_key: Some(license_key.to_string()),
+        ..LicenseInfo::default()
+    }
+}
+
+/// Work out the license from what's stored, going online when the token is
+/// due for revalidation
+async fn evaluate(
+    stored: Option<StoredLicense>,
+    verifier: &Verifier,
+    now: u64,
+) -> (LicenseInfo, Store) {
+    let (license_key, fallback) = match stored {
+        None => return (LicenseInfo::default(), Store::Keep),
+        Some(StoredLicense::Legacy(key)) => (key, None),
+        Some(StoredLicense::Token(token)) => {
+            match verify_token(&token, &verifier.key, &verifier.device_id, now) {
+                Ok(claims) => match standing(&claims, now) {
+                    Standing::Current => return (claims.info(false), Store::Keep),
+                    standing => (claims.license_key.clone(), Some((claims, standing))),
+                },
+                Err(e) => {
+                    log::warn!("Discarding stored license: {}", e);
+                    return (LicenseInfo::default(), Store::Remove);
+                }
+            }
+        }
+    };
+
+    let online = check_online(&verifier.url, &license_key, &verifier.device_id, false).await;
+    match online {
+        Ok(Online::Valid { token }) => {
+            match verify_token(&token, &verifier.key, &verifier.device_id, now) {
+                Ok(claims) => {
+                    log::info!("License revalidated until {}", claims.expires_at);
+                    (claims.info(false), Store::Replace(token))
+                }
+                Err(e) => {
+                    log::warn!("License server sent an unusable token: {}", e);
+                    offline_result(fallback)
+                }
+            }
+        }
+        Ok(Online::Confirmed) => {
+            log::info!("License confirmed without a token");
+            confirm_key(&license_key, now);
+            (confirmed_info(&license_key), Store::Keep)
+        }
+        Ok(Online::Revoked) | Ok(Online::Rejected(_)) => {
+            log::info!("License is no longer valid; removing it");
+            (LicenseInfo::default(), Store::Remove)
+        }
+        Err(e) => {
+            log::warn!("License revalidation failed: {}", e);
+            offline_result(fallback)
+        }
+    }
+}
+
+/// The license while the server can't confirm it
+fn offline_result(fallback: Option<(LicenseClaims, Standing)>) -> (LicenseInfo, Store) {
+    match fallback {
+        Some((claims, Standing::Current | Standing::Due)) => (claims.info(false), Store::Keep),
+        Some((claims, Standing::Grace)) => (claims.info(true), Store::Keep),
+        // Kept so a later revalidation can restore it
+        _ => (LicenseInfo::default(), Store::Keep),
+    }
+}
+
+/// Activate `license_key` on this device
+async fn activate(
+    license_key: &str,
+    verifier: &Verifier,
+    now: u64,
+) -> Result<(LicenseInfo, Store), String> {
+    match check_online(&verifier.url, license_key, &verifier.device_id, true).await? {
+        Online::Valid { token } => {
+            let claims = verify_token(&token, &verifier.key, &verifier.device_id, now)?;
+            if claims.license_key != license_key {
+                return Err("License server returned a token for another key".to_string());
+            }
+            Ok((claims.info(false), Store::Replace(token)))
+        }
+        Online::Confirmed => {
+            confirm_key(license_key, now);
+            Ok((
+                confirmed_info(license_key),
+                Store::Legacy(license_key.to_string()),
+            ))
+        }
+        Online::Revoked => Ok((
+            LicenseInfo {
+                license_key: Some(license_key.to_string()),
+                ..LicenseInfo::default()
+            },
+            Store::Keep,
+        )),
+        Online::Rejected(message) => Err(message),
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn config_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_config_dir()
+        .map_err(|e| format!("Failed to get config dir: {}", e))
+}
+
+fn read_stored(app: &AppHandle) -> Result<Option<StoredLicense>, String> {
+    let Ok(content) = fs::read_to_string(config_dir(app)?.join(LICENSE_FILE)) else {
+        return Ok(None);
+    };
+    let json: serde_json::Value = serde_json::from_str(&content).unwrap_or_default();
+    if let Some(token) = json["token"].as_str() {
+        Ok(Some(StoredLicense::Token(token.to_string())))
+    } else {
+        Ok(json["license_key"]
+            .as_str()
+            .map(|key| StoredLicense::Legacy(key.to_string())))
+    }
+}
+
+fn apply_store(app: &AppHandle, store: Store) -> Result<(), String> {
+    let file = config_dir(app)?.join(LICENSE_FILE);
+    match store {
+        Store::Keep => Ok(()),
+        Store::Replace(token) => {
+            fs::create_dir_all(file.parent().unwrap_or(&file))
+                .map_err(|e| format!("Failed to create config dir: {}", e))?;
+            let content = serde_json::json!({ "token": token }).to_string();
+            fs::write(&file, content).map_err(|e| format!("Failed to save license: {}", e))
+        }
+        Store::Legacy(license_key) => {
+            fs::create_dir_all(file.parent().unwrap_or(&file))
+                .map_err(|e| format!("Failed to create config dir: {}", e))?;
+            let content = serde_json::json!({ "license_key": license_key }).to_string();
+            fs::write(&file, content).map_err(|e| format!("Failed to save license: {}", e))
+        }
+        Store::Remove if file.exists() => {
+            fs::remove_file(&file).map_err(|e| format!("Failed to remove license: {}", e))
+        }
+        Store::Remove => Ok(()),
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn machine_id() -> Option<String> {
+    fs::read_to_string("/etc/machine-id")
+        .or_else(|_| fs::read_to_string("/var/lib/dbus/machine-id"))
+        .ok()
+}
+
+#[cfg(target_os = "macos")]
+fn machine_id() -> Option<String> {
+    let output = crate::create_windowless_command_str("ioreg")
+        .args(["-rd1", "-c", "IOPlatformExpertDevice"])
+        .output()
+        .ok()?;
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find(|line| line.contains("IOPlatformUUID"))
+        .and_then(|line| line.split('"').nth(3))
+        .map(String::from)
+}
+
+#[cfg(target_os = "windows")]
+fn machine_id() -> Option<String> {
+    let output = crate::create_windowless_command_str("reg")
+        .args([
+            "query",
+            r"HKLM\SOFTWARE\Microsoft\Cryptography",
+            "/v",
+            "MachineGuid",
+        ])
+        .output()
+        .ok()?;
+    String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .last()
+        .map(String::from)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn machine_id() -> Option<String> {
+    None
+}
+
+/// Stable id for this machine, hashed so the raw machine id never leaves it.
+/// Falls back to a random id kept in the config dir.
+fn device_id(app: &AppHandle) -> Result<String, String> {
+    static DEVICE_ID: OnceLock<String> = OnceLock::new();
+    if let Some(id) = DEVICE_ID.get() {
+        return Ok(id.clone());
+    }
+
+    let seed = match machine_id().map(|id| id.trim().to_string()) {
+        Some(id) if !id.is_empty() => id,
+        _ => {
+            let file = config_dir(app)?.join(DEVICE_FILE);
+            match fs::read_to_string(&file) {
+                Ok(id) if !id.trim().is_empty() => id.trim().to_string(),
+                _ => {
+                    let random = hex::encode(Sha256::digest(format!(
+                        "{:?}-{}-{:p}",
+                        SystemTime::now(),
+                        std::process::id(),
+                        &file
+                    )));
+                    fs::create_dir_all(file.parent().unwrap_or(&file))
+                        .map_err(|e| format!("Failed to create config dir: {}", e))?;
+                    fs::write(&file, &random)
+                        .map_err(|e| format!("Failed to save device id: {}", e))?;
+                    random
+                }
+            }
+        }
+    };
+    let id = hex::encode(Sha256::digest(format!("clipme-device:{}", seed)));
+    Ok(DEVICE_ID.get_or_init(|| id).clone())
+}
+
+/// Whether a stored token grants Pro right now, without going online.
+/// A legacy license counts once `get_license_status` has exchanged it for a token.
+fn grants_pro(
+    stored: Option<&StoredLicense>,
+    key: &VerifyingKey,
+    device_id: &str,
+    confirmed_at: impl Fn(&str) -> Option<u64>,
+    now: u64,
+) -> bool {
+    let recently_confirmed = |license_key: &str| {
+        confirmed_at(license_key).is_some_and(|at| now < at.saturating_add(REVALIDATE_AFTER))
+    };
+    match stored {
+        Some(StoredLicense::Token(token)) => verify_token(token, key, device_id, now)
+            .map(|claims| {
+                standing(&claims, now) != Standing::Expired
+                    || recently_confirmed(&claims.license_key)
+            })
+            .unwrap_or(false),
+        Some(StoredLicense::Legacy(license_key)) => recently_confirmed(license_key),
+        None => false,
+    }
+}
+
+/// The verified license state used to gate commands
+pub fn is_pro(app: &AppHandle) -> bool {
+    let (Ok(stored), Ok(key), Ok(device_id)) = (read_stored(app), public_key(), device_id(app))
+    else {
+        return false;
+    };
+    grants_pro(stored.as_ref(), &key, &device_id, confirmed_at, now_secs())
+}
+
+fn verifier(app: &AppHandle) -> Result<Verifier, String> {
+    Ok(Verifier {
+        url: VERIFY_URL.to_string(),
+        key: public_key()?,
+        device_id: device_id(app)?,
+    })
+}
+
+#[tauri::command]
+pub async fn verify_license(app: AppHandle, license_key: String) -> Result<LicenseInfo, String> {
+    log::info!("Verifying license");
+    let (info, store) = activate(license_key.trim(), &verifier(&app)?, now_secs()).await?;
+    apply_store(&app, store)?;
+    Ok(info)
+}
+
+/// The stored license, checked against the signature every time and
+/// revalidated online when due
+#[tauri::command]
+pub async fn get_license_status(app: AppHandle) -> Result<LicenseInfo, String> {
+    let stored = read_stored(&app)?;
+    if stored.is_none() {
+        return Ok(LicenseInfo::default());
+    }
+    let (info, store) = evaluate(stored, &verifier(&app)?, now_secs()).await;
+    apply_store(&app, store)?;
+    Ok(info)
+}
+
+#[tauri::command]
+pub async fn clear_license(app: AppHandle) -> Result<(), String> {
+    apply_store(&app, Store::Remove)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+    use std::collections::HashMap;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+
+    const NOW: u64 = 1_750_000_000;
+    const DEVICE: &str = "device-a";
+
+    /// The license server's side: sign claims into a token
+    fn issue(key: &SigningKey, claims: &LicenseClaims) -> String {
+        let payload = URL_SAFE_NO_PAD.encode(serde_json::to_vec(claims).unwrap());
+        let signature = key.sign(payload.as_bytes());
+        format!(
+            "{}.{}",
+            payload,
+            URL_SAFE_NO_PAD.encode(signature.to_bytes())
+        )
+    }
+
+    fn claims(license_key: &str, issued_at: u64) -> LicenseClaims {
+        LicenseClaims {
+            product_id: GUMROAD_PRODUCT_ID.to_string(),
+            license_key: license_key.to_string(),
+            email: Some("buyer@example.com".to_string()),
+            device_id: DEVICE.to_string(),
+            issued_at,
+            expires_at: issued_at + 30 * DAY,
+        }
+    }
+
+    /// How the mock answers each key: purchase state, or `None` for an unknown key
+    type Purchases = Arc<Mutex<HashMap<String, Option<serde_json::Value>>>>;
+
+    /// Local stand-in for the verify endpoint. Answers like Gumroad, plus a
+    /// token signed with `key` for good purchases not marked `"tokenless"`.
+    fn serve(key: SigningKey, purchases: Purchases, requests: Arc<Mutex<Vec<String>>>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { continue };
+                let mut request = Vec::new();
+                let mut buffer = [0u8; 4096];
+                // Read headers, then as much body as Content-Length says
+                loop {
+                    let read = stream.read(&mut buffer).unwrap_or(0);
+                    request.extend_from_slice(&buffer[..read]);
+                    let text = String::from_utf8_lossy(&request).to_string();
+                    if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                        let length = head
+                            .lines()
+                            .find_map(|l| {
+                                l.to_lowercase()
+                                    .strip_prefix("content-length:")
+                                    .map(|v| v.trim().parse::<usize>().unwrap_or(0))
+                            })
+                            .unwrap_or(0);
+                        if body.len() >= length || read == 0 {
+                            break;
+                        }
+                    } else if read == 0 {
+                        break;
+                    }
+                }
+                let text = String::from_utf8_lossy(&request).to_string();
+                let body = text.split_once("\r\n\r\n").map(|(_, b)| b).unwrap_or("");
+                requests.lock().unwrap().push(body.to_string());
+                let form: HashMap<String, String> = body
+                    .split('&')
+                    .filter_map(|pair| pair.split_once('='))
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect();
+                let license_key = form.get("license_key").cloned().unwrap_or_default();
+                let device = form.get("device_id").cloned().unwrap_or_default();
+
+                let (status, json) = match purchases.lock().unwrap().get(&license_key).cloned() {
+                    Some(Some(purchase)) => {
+                        let mut issued = claims(&license_key, NOW);
+                        issued.device_id = device;
+                        let mut json = serde_json::json!({ "success": true, "purchase": purchase });
+                        if !purchase["tokenless"].as_bool().unwrap_or(false) {
+                            json["token"] = issue(&key, &issued).into();
+                        }
+                        ("200 OK", json)
+                    }
+                    _ => (
+                        "404 Not Found",
+                        serde_json::json!({
+                            "success": false,
+                            "message": "That license does not exist for the provided product."
+                        }),
+                    ),
+                };
+                let json = json.to_string();
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    json.len(),
+                    json
+                );
+                let _ = stream.write_all(response.as_bytes());
+            }
+        });
+        format!("http://{}/v2/licenses/verify", addr)
+    }
+
+    struct Mock {
+        signing: SigningKey,
+        purchases: Purchases,
+        requests: Arc<Mutex<Vec<String>>>,
+        verifier: Verifier,
+    }
+
+    fn mock() -> Mock {
+        let signing = SigningKey::from_bytes(&[7u8; 32]);
+        let purchases = Purchases::default();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let url = serve(signing.clone(), purchases.clone(), requests.clone());
+        Mock {
+            verifier: Verifier {
+                url,
+                key: signing.verifying_key(),
+                device_id: DEVICE.to_string(),
+            },
+            signing,
+            purchases,
+            requests,
+        }
+    }
+
+    fn run<T>(future: impl std::future::Future<Output = T>) -> T {
+        tauri::async_runtime::block_on(future)
+    }
+
+    #[test]
+    fn rejects_tampered_and_foreign_tokens() {
+        let signing = SigningKey::from_bytes(&[7u8; 32]);
+        let key = signing.verifying_key();
+        let token = issue(&signing, &claims("KEY", NOW));
+        assert_eq!(
+            verify_token(&token, &key, DEVICE, NOW).unwrap().license_key,
+            "KEY"
+        );
+
+        // Claims edited after signing
+        let mut forged = claims("KEY", NOW);
+        forged.expires_at += 365 * DAY;
+        let forged_payload = URL_SAFE_NO_PAD.encode(serde_json::to_vec(&forged).unwrap());
+        let signature = token.split_once('.').unwrap().1;
+        let tampered = format!("{}.{}", forged_payload, signature);
+        assert!(verify_token(&tampered, &key, DEVICE, NOW).is_err());
+
+        // Signed by someone else
+        let other = issue(&SigningKey::from_bytes(&[9u8; 32]), &claims("KEY", NOW));
+        assert!(verify_token(&other, &key, DEVICE, NOW).is_err());
+
+        // Copied to another machine
+        assert!(verify_token(&token, &key, "device-b", NOW).is_err());
+        // Issued in the future
+        assert!(verify_token(&token, &key, DEVICE, NOW - DAY).is_err());
+        // The old plain JSON format
+        assert!(verify_token(r#"{"is_pro":true}"#, &key, DEVICE, NOW).is_err());
+    }
+
+    #[test]
+    fn embedded_public_key_parses() {
+        assert!(public_key().is_ok());
+    }
+
+    #[test]
+    fn classifies_token_age() {
+        let claims = claims("KEY", NOW);
+        assert_eq!(standing(&claims, NOW + DAY), Standing::Current);
+        assert_eq!(standing(&claims, NOW + 8 * DAY), Standing::Due);
+        assert_eq!(standing(&claims, NOW + 31 * DAY), Standing::Grace);
+        assert_eq!(standing(&claims, NOW + 45 * DAY), Standing::Expired);
+    }
+
+    #[test]
+    fn grants_pro_only_for_verified_unexpired_tokens() {
+        let signing = SigningKey::from_bytes(&[7u8; 32]);
+        let key = signing.verifying_key();
+        let token = StoredLicense::Token(issue(&signing, &claims("KEY", NOW)));
+        let never = |_: &str| None;
+        assert!(grants_pro(Some(&token), &key, DEVICE, never, NOW));
+        // Still honoured in the grace period, not after it
+        assert!(grants_pro(Some(&token), &key, DEVICE, never, NOW + 40 * DAY));
+        assert!(!grants_pro(Some(&token), &key, DEVICE, never, NOW + 50 * DAY));
+        assert!(!grants_pro(Some(&token), &key, "device-b", never, NOW));
+        assert!(!grants_pro(None, &key, DEVICE, never, NOW));
+
+        // Legacy keys only while a recent online confirmation backs them
+        let legacy = StoredLicense::Legacy("KEY".to_string());
+        let confirmed = |key: &str| (key == "KEY").then_some(NOW);
+        assert!(!grants_pro(Some(&legacy), &key, DEVICE, never, NOW));
+        assert!(grants_pro(Some(&legacy), &key, DEVICE, confirmed, NOW + DAY));
+        assert!(!grants_pro(Some(&legacy), &key, DEVICE, confirmed, NOW + 8 * DAY));
+    }
+
+    #[test]
+    fn activates_against_the_verify_endpoint() {
+        let mock = mock();
+        mock.purchases.lock().unwrap().insert(
+            "GOOD".to_string(),
+            Some(serde_json::json!({ "email": "buyer@example.com", "refunded": false })),
+        );
+        mock.purchases.lock().unwrap().insert(
+            "REFUNDED".to_string(),
+            Some(serde_json::json!({ "refunded": true })),
+        );
+
+        let (info, store) = run(activate("GOOD", &mock.verifier, NOW)).unwrap();
+        assert!(info.is_pro);
+        assert_eq!(info.email.as_deref(), Some("buyer@example.com"));
+        assert!(matches!(store, Store::Replace(_)));
+        assert!(mock.requests.lock().unwrap()[0].contains("increment_uses_count=true"));
+
+        let (info, store) = run(activate("REFUNDED", &mock.verifier, NOW)).unwrap();
+        assert!(!info.is_pro);
+        assert_eq!(store, Store::Keep);
+
+        let error = run(activate("UNKNOWN", &mock.verifier, NOW)).unwrap_err();
+        assert!(error.contains("does not exist"), "{}", error);
+    }
+
+    #[test]
+    fn revalidates_when_due_and_honours_the_grace_period() {
+        let mock = mock();
+        let purchase = serde_json::json!({ "refunded": false });
+        mock.purchases
+            .lock()
+            .unwrap()
+            .insert("KEY".to_string(), Some(purchase));
+        let stored = StoredLicense::Token(issue(&mock.signing, &claims("KEY", NOW - 10 * DAY)));
+
+        // Fresh enough: no request at all
+        let fresh = StoredLicense::Token(issue(&mock.signing, &claims("KEY", NOW - DAY)));
+        let (info, store) = run(evaluate(Some(fresh), &mock.verifier, NOW));
+        assert!(info.is_pro);
+        assert_eq!(store, Store::Keep);
+        assert!(mock.requests.lock().unwrap().is_empty());
+
+        // Due: a new token replaces the old one
+        let (info, store) = run(evaluate(Some(stored.clone()), &mock.verifier, NOW));
+        assert!(info.is_pro && !info.offline);
+        assert!(matches!(store, Store::Replace(_)));
+        assert!(mock.requests.lock().unwrap()[0].contains("increment_uses_count=false"));
+
+        // Refunded since: the license is removed
+        mock.purchases.lock().unwrap().insert(
+            "KEY".to_string(),
+            Some(serde_json::json!({ "refunded": true })),
+        );
+        let (info, store) = run(evaluate(Some(stored), &mock.verifier, NOW));
+        assert!(!info.is_pro);
+        assert_eq!(store, Store::Remove);
+
+        // Server unreachable: valid until expiry, then the grace period, then off
+        let offline = Verifier {
+            url: "http://127.0.0.1:9/verify".to_string(),
+            key: mock.signing.verifying_key(),
+            device_id: DEVICE.to_string(),
+        };
+        let token = StoredLicense::Token(issue(&mock.signing, &claims("KEY", NOW)));
+        for (days, pro, in_grace) in [(10, true, false), (35, true, true), (50, false, false)] {
+            let (info, store) = run(evaluate(Some(token.clone()), &offline, NOW + days * DAY));
+            assert_eq!((info.is_pro, info.offline), (pro, in_grace), "day {}", days);
+            assert_eq!(store, Store::Keep);
+        }
+    }
+
+    #[test]
+    fn upgrades_legacy_licenses_and_drops_forged_ones() {
+        let mock = mock();
+        mock.purchases
+            .lock()
+            .unwrap()
+            .insert("OLD".to_string(), Some(serde_json::json!({})));
+
+        let (info, store) = run(evaluate(
+            Some(StoredLicense::Legacy("OLD".to_string())),
+            &mock.verifier,
+            NOW,
+        ));
+        assert!(info.is_pro);
+        assert!(matches!(store, Store::Replace(_)));
+
+        let forged = StoredLicense::Token("eyJpc19wcm8iOnRydWV9.AAAA".to_string());
+        let (info, store) = run(evaluate(Some(forged), &mock.verifier, NOW));
+        assert!(!info.is_pro);
+        assert_eq!(store, Store::Remove);
+    }
+
+    #[test]
+    fn honours_keys_gumroad_confirms_without_a_token() {
+        let mock = mock();
+        mock.purchases.lock().unwrap().insert(
+            "GUMROAD".to_string(),
+            Some(serde_json::json!({ "tokenless": true })),
+        );
+
+        let (info, store) = run(activate("GUMROAD", &mock.verifier, NOW)).unwrap();
+        assert!(info.is_pro);
+        assert_eq!(store, Store::Legacy("GUMROAD".to_string()));
+        assert_eq!(confirmed_at("GUMROAD"), Some(NOW));
+
+        let legacy = StoredLicense::Legacy("GUMROAD".to_string());
+        let (info, store) = run(evaluate(Some(legacy), &mock.verifier, NOW + DAY));
+        assert!(info.is_pro);
+        assert_eq!(store, Store::Keep);
+        assert_eq!(confirmed_at("GUMROAD"), Some(NOW + DAY));
+    }
+}
@@ -0,0 +1,379 @@
+use serde::Serialize;
+use std::sync::{Mutex, OnceLock};
+use tauri::{AppHandle, Emitter, Url};
+use tauri_plugin_deep_link::DeepLinkExt;
+
+use crate::{metadata_cache, read_app_settings, timecode, ClipJob, QUALITY_PRESETS};
+
+/// Query parameters that only identify where a link was shared from
+const TRACKING_PARAMS: &[&str] = &[
+    "si",
+    "feature",
+    "pp",
+    "fbclid",
+    "gclid",
+    "igshid",
+    "igsh",
+    "mc_cid",
+    "mc_eid",
+    "ref",
+    "ref_src",
+    "ref_url",
+    "share_id",
+    "is_from_webapp",
+    "sender_device",
+];
+const START_PARAMS: &[&str] = &["t", "start", "time_continue"];
+const END_PARAMS: &[&str] = &["end"];
+
+/// A pasted URL split into the URL to fetch and any timing it carried
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct NormalizedLink {
+    pub url: String,
+    pub start: Option<f64>,
+    pub end: Option<f64>,
+}
+
+/// A `clipme://clip?...` request
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct DeepLinkClip {
+    url: String,
+    title: Option<String>,
+    start: f64,
+    end: f64,
+    quality: Option<String>,
+    format: Option<String>,
+}
+
+fn is_tracking_param(key: &str) -> bool {
+    key.starts_with("utm_") || TRACKING_PARAMS.contains(&key)
+}
+
+fn is_time_param(key: &str) -> bool {
+    START_PARAMS.contains(&key) || END_PARAMS.contains(&key)
+}
+
+/// First parseable time among `keys` (`#t=` fragments count too)
+fn find_time(pairs: &[(String, String)], keys: &[&str]) -> Option<f64> {
+    pairs
+        .iter()
+        .filter(|(key, _)| keys.contains(&key.as_str()))
+        .find_map(|(_, value)| timecode::parse_timecode(value, None).ok())
+}
+
+/// Strip tracking and timing parameters and pull out start/end times.
+/// YouTube links collapse to `watch?v=<id>` so shorts, embeds and youtu.be
+/// links share one cache entry. Anything that isn't an http(s) URL (local
+/// paths) is returned unchanged.
+pub fn normalize_url(input: &str) -> NormalizedLink {
+    let trimmed = input.trim();
+    let unchanged = NormalizedLink {
+        url: trimmed.to_string(),
+        start: None,
+        end: None,
+    };
+    let Ok(mut parsed) = Url::parse(trimmed) else {
+        return unchanged;
+    };
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return unchanged;
+    }
+
+    let mut pairs: Vec<(String, String)> = parsed
+        .query_pairs()
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect();
+    // Media fragments (`#t=12`) carry time the same way
+    let fragment_pairs: Vec<(String, String)> = parsed
+        .fragment()
+        .filter(|f| f.contains('='))
+        .map(|f| {
+            f.split('&')
+                .filter_map(|pair| pair.split_once('='))
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect()
+        })
+        .unwrap_or_default();
+    let timing: Vec<(String, String)> = pairs.iter().chain(&fragment_pairs).cloned().collect();
+
+    let start = find_time(&timing, START_PARAMS);
+    let end = find_time(&timing, END_PARAMS).filter(|end| start.is_none_or(|start| *end > start));
+
+    let url = if let Some(id) = metadata_cache::youtube_video_id(trimmed) {
+        format!("https://www.youtube.com/watch?v={}", id)
+    } else {
+        pairs.retain(|(key, _)| !is_tracking_param(key) && !is_time_param(key));
+        if pairs.is_empty() {
+            parsed.set_query(None);
+        } else {
+            parsed.query_pairs_mut().clear().extend_pairs(&pairs);
+        }
+        if !fragment_pairs.is_empty() {
+            parsed.set_fragment(None);
+        }
+        if let Some(host) = parsed.host_str() {
+            if host == "twitch.tv" || host == "m.twitch.tv" {
+                let _ = parsed.set_host(Some("www.twitch.tv"));
+            }
+        }
+        parsed.to_string()
+    };
+
+    NormalizedLink { url, start, end }
+}
+
+/// `clipme://clip?...` (some platforms deliver it as `clipme:clip?...`)
+fn is_clip_link(link: &Url) -> bool {
+    let action = link
+        .host_str()
+        .unwrap_or_else(|| link.path().trim_start_matches('/'));
+    link.scheme() == "clipme" && action == "clip"
+}
+
+/// The format ends up in ffmpeg arguments and the output file name
+fn is_plain_token(value: &str) -> bool {
+    !value.is_empty() && value.len() <= 16 && value.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+/// Parse `clipme://clip?url=...&start=...&end=...&quality=...`. Times may be any
+/// timecode; when missing they fall back to timing carried by the inner URL.
+pub fn parse_clip_link(link: &Url) -> Result<DeepLinkClip, String> {
+    if !is_clip_link(link) {
+        return Err(format!("Not a clip link: {}", link));
+    }
+
+    let param = |name: &str| {
+        link.query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+            .filter(|value| !value.is_empty())
+    };
+    let time_param = |name: &str| -> Result<Option<f64>, String> {
+        param(name)
+            .map(|value| timecode::parse_timecode(&value, None))
+            .transpose()
+    };
+
+    let source = param("url").ok_or("Clip link is missing a url")?;
+    // Links can come from any web page, so never let them point at local files
+    if !(source.starts_with("https://") || source.starts_with("http://")) {
+        return Err("Clip links only accept http(s) URLs".to_string());
+    }
+    let normalized = normalize_url(&source);
+
+    let start = time_param("start")?.or(normalized.start).unwrap_or(0.0);
+    let end = time_param("end")?
+        .or(normalized.end)
+        .ok_or("Clip link is missing an end time")?;
+    if end <= start {
+        return Err(format!("Invalid clip range {}-{}", start, end));
+    }
+    let quality = param("quality");
+    if quality
+        .as_deref()
+        .is_some_and(|q| !QUALITY_PRESETS.contains(&q))
+    {
+        return Err("Invalid quality in clip link".to_string());
+    }
+    let format = param("format");
+    if format.as_deref().is_some_and(|f| !is_plain_token(f)) {
+        return Err("Invalid format in clip link".to_string());
+    }
+
+    Ok(DeepLinkClip {
+        url: normalized.url,
+        title: param("title"),
+        start,
+        end,
+        quality,
+        format,
+    })
+}
+
+/// Clips asked for by links and not yet taken by the frontend
+fn pending_clips() -> &'static Mutex<Vec<ClipJob>> {
+    static CLIPS: OnceLock<Mutex<Vec<ClipJob>>> = OnceLock::new();
+    CLIPS.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+/// Queue the clip a `clipme://clip` link asks for and emit `deep-link-clip`.
+/// Any web page can open such a link, so nothing runs here: the frontend asks
+/// the user and downloads confirmed clips through `download_clip`, one at a
+/// time with its own jobs. Other links (auth callbacks) are left to the frontend.
+pub fn handle_deep_link(app: &AppHandle, link: &Url) {
+    if !is_clip_link(link) {
+        return;
+    }
+    let clip = match parse_clip_link(link) {
+        Ok(clip) => clip,
+        Err(e) => {
+            log::warn!("Ignoring deep link: {}", e);
+            let _ = app.emit("deep-link-error", e);
+            return;
+        }
+    };
+
+    let title = clip
+        .title
+        .clone()
+        .or_else(|| metadata_cache::lookup(app, &clip.url, u64::MAX).map(|m| m.title))
+        .or_else(|| metadata_cache::youtube_video_id(&clip.url))
+        .unwrap_or_else(|| "clip".to_string());
+    let quality = clip
+        .quality
+        .clone()
+        .or_else(|| {
+            read_app_settings(app)
+                .ok()
+                .and_then(|s| s.preferred_quality)
+        })
+        .unwrap_or_else(|| "1080p".to_string());
+    let id = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default();
+
+    let job = ClipJob {
+        url: clip.url.clone(),
+        title,
+        start: clip.start,
+        end: clip.end,
+        quality,
+        format: clip.format.clone().unwrap_or_else(|| "mp4".to_string()),
+        id,
+        format_id: None,
+        video_format_id: None,
+        audio_tracks: Vec::new(),
+    };
+    log::info!(
+        "Clip requested by deep link: {} ({}-{})",
+        job.url, job.start, job.end
+    );
+    if let Ok(mut pending) = pending_clips().lock() {
+        pending.push(job.clone());
+    }
+    let _ = app.emit("deep-link-clip", &job);
+}
+
+/// Clips requested by links since the last call, including any that arrived
+/// before the frontend was listening
+#[tauri::command]
+pub async fn take_deep_link_clips() -> Result<Vec<ClipJob>, String> {
+    let mut pending = pending_clips()
+        .lock()
+        .map_err(|_| "Failed to lock pending clips")?;
+    Ok(std::mem::take(&mut *pending))
+}
+
+/// Handle links that launched the app and any that arrive while it runs
+pub fn register(app: &AppHandle) {
+    let handle = app.clone();
+    app.deep_link().on_open_url(move |event| {
+        for link in event.urls() {
+            handle_deep_link(&handle, &link);
+        }
+    });
+    if let Ok(Some(links)) = app.deep_link().get_current() {
+        for link in links {
+            handle_deep_link(app, &link);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link(url: &str, start: Option<f64>, end: Option<f64>) -> NormalizedLink {
+        NormalizedLink {
+            url: url.to_string(),
+            start,
+            end,
+        }
+    }
+
+    #[test]
+    fn normalizes_youtube_links() {
+        assert_eq!(
+            normalize_url("https://youtu.be/dQw4w9WgXcQ?si=abc&t=93"),
+            link(
+                "https://www.youtube.com/watch?v=dQw4w9WgXcQ",
+                Some(93.0),
+                None
+            )
+        );
+        assert_eq!(
+            normalize_url("https://www.youtube.com/embed/dQw4w9WgXcQ?start=10&end=25"),
+            link(
+                "https://www.youtube.com/watch?v=dQw4w9WgXcQ",
+                Some(10.0),
+                Some(25.0)
+            )
+        );
+        assert_eq!(
+            normalize_url("https://www.youtube.com/watch?v=dQw4w9WgXcQ&feature=share&t=1m3s"),
+            link(
+                "https://www.youtube.com/watch?v=dQw4w9WgXcQ",
+                Some(63.0),
+                None
+            )
+        );
+    }
+
+    #[test]
+    fn normalizes_twitch_and_generic_links() {
+        assert_eq!(
+            normalize_url("https://twitch.tv/videos/123456?t=1h2m3s"),
+            link("https://www.twitch.tv/videos/123456", Some(3723.0), None)
+        );
+        assert_eq!(
+            normalize_url("https://vimeo.com/76979871?utm_source=x&fbclid=y#t=12"),
+            link("https://vimeo.com/76979871", Some(12.0), None)
+        );
+        assert_eq!(
+            normalize_url("https://example.com/v?id=5&utm_medium=social"),
+            link("https://example.com/v?id=5", None, None)
+        );
+    }
+
+    #[test]
+    fn leaves_local_paths_alone() {
+        assert_eq!(
+            normalize_url("/home/me/video.mp4"),
+            link("/home/me/video.mp4", None, None)
+        );
+    }
+
+    #[test]
+    fn parses_clip_links() {
+        let url = Url::parse(
+            "clipme://clip?url=https%3A%2F%2Fyoutu.be%2FdQw4w9WgXcQ%3Ft%3D30&end=1:00&quality=720p",
+        )
+        .unwrap();
+        let clip = parse_clip_link(&url).unwrap();
+        assert_eq!(clip.url, "https://www.youtube.com/watch?v=dQw4w9WgXcQ");
+        assert_eq!((clip.start, clip.end), (30.0, 60.0));
+        assert_eq!(clip.quality.as_deref(), Some("720p"));
+        let audio =
+            Url::parse("clipme://clip?url=https%3A%2F%2Fexample.com%2Fv&end=5&quality=Audio+Only")
+                .unwrap();
+        assert_eq!(
+            parse_clip_link(&audio).unwrap().quality.as_deref(),
+            Some("Audio Only")
+        );
+        let unknown =
+            Url::parse("clipme://clip?url=https%3A%2F%2Fexample.com%2Fv&end=5&quality=best%5B")
+                .unwrap();
+        assert!(parse_clip_link(&unknown).is_err());
+
+        let local = Url::parse("clipme://clip?url=%2Fetc%2Fpasswd&start=0&end=5").unwrap();
+        assert!(parse_clip_link(&local).is_err());
+        let traversal =
+            Url::parse("clipme://clip?url=https%3A%2F%2Fexample.com%2Fv&end=5&format=..%2Fx")
+                .unwrap();
+        assert!(parse_clip_link(&traversal).is_err());
+        let backwards =
+            Url::parse("clipme://clip?url=https%3A%2F%2Fexample.com%2Fv&start=9&end=5").unwrap();
+        assert!(parse_clip_link(&backwards).is_err());
+    }
+}
@@ -0,0 +1,522 @@
+//! Application log: levelled records in a rotating file in the app log dir.
+//! Everything is redacted before it's written, so license keys, tokens,
+//! emails, URL queries and the user's home directory never reach disk.
+
+use crate::{entitlements, history, read_app_settings, toolchain};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::SystemTime;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_dialog::DialogExt;
+
+pub const LOG_FILE: &str = "clipme.log";
+/// The current file is rotated once it grows past this
+const MAX_FILE_BYTES: u64 = 2 * 1024 * 1024;
+/// Rotated files kept next to the current one (`clipme.log.1` is the newest)
+const KEEP_ROTATED: usize = 3;
+const DEFAULT_LINES: usize = 500;
+/// Log lines included in a diagnostics bundle
+const DIAGNOSTICS_LINES: usize = 2000;
+/// `key=value` pairs whose value is always redacted
+const SECRET_NAMES: &[&str] = &[
+    "license_key",
+    "licensekey",
+    "key",
+    "token",
+    "access_token",
+    "password",
+    "signature",
+    "sig",
+];
+
+struct OpenLog {
+    file: File,
+    len: u64,
+}
+
+struct FileLogger {
+    level: LevelFilter,
+    dir: PathBuf,
+    home: Option<String>,
+    file: Mutex<Option<OpenLog>>,
+}
+
+impl FileLogger {
+    fn write_line(&self, line: &str) -> std::io::Result<()> {
+        let mut guard = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        if guard
+            .as_ref()
+            .is_some_and(|open| open.len + line.len() as u64 > MAX_FILE_BYTES)
+        {
+            *guard = None;
+            rotate(&self.dir, KEEP_ROTATED)?;
+        }
+        if guard.is_none() {
+            fs::create_dir_all(&self.dir)?;
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(self.dir.join(LOG_FILE))?;
+            let len = file.metadata().map(|m| m.len()).unwrap_or(0);
+            *guard = Some(OpenLog { file, len });
+        }
+        if let Some(open) = guard.as_mut() {
+            open.file.write_all(line.as_bytes())?;
+            open.len += line.len() as u64;
+        }
+        Ok(())
+    }
+}
+
+impl Log for FileLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let line = format_line(
+            &history::iso8601(SystemTime::now()),
+            record.level(),
+            record.target(),
+            &redact(&record.args().to_string(), self.home.as_deref()),
+        );
+        if cfg!(debug_assertions) {
+            print!("{}", line);
+        }
+        if let Err(e) = self.write_line(&line) {
+            eprintln!("Failed to write log: {}", e);
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(open) = self.file.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+            let _ = open.file.flush();
+        }
+    }
+}
+
+/// Install the logger. The level comes from `CLIPME_LOG` (e.g. `debug`),
+/// defaulting to debug in development builds and info in release builds.
+pub fn init(app: &AppHandle) {
+    let level = std::env::var("CLIPME_LOG")
+        .ok()
+        .and_then(|level| LevelFilter::from_str(&level).ok())
+        .unwrap_or(if cfg!(debug_assertions) {
+            LevelFilter::Debug
+        } else {
+            LevelFilter::Info
+        });
+    let Ok(dir) = app.path().app_log_dir() else {
+        eprintln!("No log directory available; logging disabled");
+        return;
+    };
+    let logger = FileLogger {
+        level,
+        dir,
+        home: home_dir(app),
+        file: Mutex::new(None),
+    };
+    if log::set_boxed_logger(Box::new(logger)).is_ok() {
+        log::set_max_level(level);
+    }
+}
+
+fn home_dir(app: &AppHandle) -> Option<String> {
+    app.path()
+        .home_dir()
+        .ok()
+        .map(|home| home.to_string_lossy().to_string())
+        .filter(|home| home.len() > 1)
+}
+
+fn log_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_log_dir()
+        .map_err(|e| format!("Failed to get log dir: {}", e))
+}
+
+/// Shift `clipme.log` to `.1`, `.1` to `.2` and so on, dropping the oldest
+fn rotate(dir: &Path, keep: usize) -> std::io::Result<()> {
+    let rotated = |n: usize| dir.join(format!("{}.{}", LOG_FILE, n));
+    let _ = fs::remove_file(rotated(keep));
+    for n in (1..keep).rev() {
+        if rotated(n).exists() {
+            fs::rename(rotated(n), rotated(n + 1))?;
+        }
+    }
+    fs::rename(dir.join(LOG_FILE), rotated(1))
+}
+
+/// Log files from oldest to newest
+fn log_files(dir: &Path) -> Vec<PathBuf> {
+    (1..=KEEP_ROTATED)
+        .rev()
+        .map(|n| dir.join(format!("{}.{}", LOG_FILE, n)))
+        .chain(std::iter::once(dir.join(LOG_FILE)))
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+/// One line per record; newlines in the message are escaped
+fn format_line(time: &str, level: Level, target: &str, message: &str) -> String {
+    format!(
+        "{} [{}] {}: {}\n",
+        time,
+        level,
+        target,
+        message.replace('\r', "").replace('\n', "\\n")
+    )
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct LogEntry {
+    pub time: String,
+    pub level: String,
+    pub target: String,
+    pub message: String,
+}
+
+fn parse_line(line: &str) -> Option<LogEntry> {
+    let (time, rest) = line.split_once(" [")?;
+    let (level, rest) = rest.split_once("] ")?;
+    let (target, message) = rest.split_once(": ")?;
+    Level::from_str(level).ok()?;
+    Some(LogEntry {
+        time: time.to_string(),
+        level: level.to_string(),
+        target: target.to_string(),
+        message: message.to_string(),
+    })
+}
+
+/// The newest `limit` entries at `level` or more severe, oldest first
+fn read_entries(dir: &Path, level: LevelFilter, limit: usize) -> Vec<LogEntry> {
+    let mut entries: Vec<LogEntry> = log_files(dir)
+        .iter()
+        .filter_map(|path| fs::read_to_string(path).ok())
+        .flat_map(|content| content.lines().filter_map(parse_line).collect::<Vec<_>>())
+        .filter(|entry| Level::from_str(&entry.level).is_ok_and(|l| l <= level))
+        .collect();
+    let skip = entries.len().saturating_sub(limit);
+    entries.drain(..skip);
+    entries
+}
+
+/// Replace the home directory with `~` and mask anything that looks secret
+/// or personal: emails, license keys, tokens and URL query strings
+pub fn redact(text: &str, home: Option<&str>) -> String {
+    let text = match home {
+        // `{:?}` doubles the backslashes of Windows paths, so match that form too
+        Some(home) if !home.is_empty() => text
+            .replace(&home.replace('\\', "\\\\"), "~")
+            .replace(home, "~"),
+        _ => text.to_string(),
+    };
+    let is_delimiter = |c: char| c.is_whitespace() || "\"'`,;()[]{}<>|".contains(c);
+
+    let mut out = String::with_capacity(text.len());
+    let mut word = String::new();
+    for c in text.chars() {
+        if is_delimiter(c) {
+            out.push_str(&redact_word(&word));
+            word.clear();
+            out.push(c);
+        } else {
+            word.push(c);
+        }
+    }
+    out.push_str(&redact_word(&word));
+    out
+}
+
+fn redact_word(word: &str) -> String {
+    // Sentence punctuation stays outside the check
+    let trimmed = word.trim_end_matches(['.', ':', '!', '?']);
+    let (word, tail) = word.split_at(trimmed.len());
+    if word.is_empty() {
+        return tail.to_string();
+    }
+
+    let redacted = if let Some(scheme_end) = word.find("://") {
+        let mut url = word.to_string();
+        // Credentials before the host
+        if let Some(at) = url[scheme_end + 3..].find('@') {
+            let host_start = scheme_end + 3 + at + 1;
+            if !url[scheme_end + 3..host_start].contains('/') {
+                url.replace_range(scheme_end + 3..host_start, "<redacted>@");
+            }
+        }
+        match url.find('?') {
+            Some(query) => format!("{}?<redacted>", &url[..query]),
+            None => url,
+        }
+    } else if is_token(word) {
+        "<secret>".to_string()
+    } else if let Some((name, value)) = word.split_once('=') {
+        if SECRET_NAMES.contains(&name.to_lowercase().as_str()) && !value.is_empty() {
+            format!("{}=<redacted>", name)
+        } else {
+            format!("{}={}", name, redact_word(value))
+        }
+    } else if is_email(word) {
+        "<email>".to_string()
+    } else if is_license_key(word) {
+        "<license-key>".to_string()
+    } else {
+        word.to_string()
+    };
+    redacted + tail
+}
+
+fn is_email(word: &str) -> bool {
+    let Some((local, domain)) = word.split_once('@') else {
+        return false;
+    };
+    !local.is_empty()
+        && local
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "._%+-".contains(c))
+        && domain.contains('.')
+        && !domain.starts_with('.')
+        && domain
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || ".-".contains(c))
+}
+
+/// Gumroad keys: four or more dash-separated groups of eight characters
+fn is_license_key(word: &str) -> bool {
+    let groups: Vec<&str> = word.split('-').collect();
+    groups.len() >= 4
+        && groups
+            .iter()
+            .all(|g| g.len() == 8 && g.chars().all(|c| c.is_ascii_alphanumeric()))
+}
+
+/// Long base64-ish runs with mixed case and digits: signed tokens, API keys.
+/// Lowercase hex like checksums is left alone.
+fn is_token(word: &str) -> bool {
+    word.len() >= 32
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_.+=/".contains(c))
+        && word.chars().any(|c| c.is_ascii_uppercase())
+        && word.chars().any(|c| c.is_ascii_lowercase())
+        && word.chars().any(|c| c.is_ascii_digit())
+}
+
+/// Redact every string in a JSON value
+fn scrub(value: serde_json::Value, home: Option<&str>) -> serde_json::Value {
+    use serde_json::Value;
+    match value {
+        Value::String(s) => Value::String(redact(&s, home)),
+        Value::Array(items) => Value::Array(items.into_iter().map(|v| scrub(v, home)).collect()),
+        Value::Object(map) => {
+            Value::Object(map.into_iter().map(|(k, v)| (k, scrub(v, home))).collect())
+        }
+        other => other,
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct Diagnostics {
+    generated_at: String,
+    app_version: String,
+    os: String,
+    arch: String,
+    license_tier: entitlements::Tier,
+    tools: BTreeMap<String, String>,
+    settings: serde_json::Value,
+    logs: Vec<String>,
+}
+
+/// Recent log entries, newest last. `level` is the least severe level to
+/// include (default `info`).
+#[tauri::command]
+pub async fn get_logs(
+    app: AppHandle,
+    level: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<LogEntry>, String> {
+    let level = match level {
+        Some(level) => {
+            LevelFilter::from_str(&level).map_err(|_| format!("Unknown log level: {}", level))?
+        }
+        None => LevelFilter::Info,
+    };
+    Ok(read_entries(
+        &log_dir(&app)?,
+        level,
+        limit.unwrap_or(DEFAULT_LINES),
+    ))
+}
+
+/// Save recent logs, tool versions and settings with personal data scrubbed,
+/// for attaching to a bug report. Returns the saved path, or `None` if the
+/// dialog was cancelled.
+#[tauri::command]
+pub async fn export_diagnostics(app: AppHandle) -> Result<Option<String>, String> {
+    let home = home_dir(&app);
+    let settings = serde_json::to_value(read_app_settings(&app)?)
+        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+    let logs = read_entries(&log_dir(&app)?, LevelFilter::Trace, DIAGNOSTICS_LINES)
+        .into_iter()
+        .map(|entry| {
+            format_line(
+                &entry.time,
+                Level::from_str(&entry.level).unwrap_or(Level::Info),
+                &entry.target,
+                &redact(&entry.message, home.as_deref()),
+            )
+            .trim_end()
+            .to_string()
+        })
+        .collect();
+    let diagnostics = Diagnostics {
+        generated_at: history::iso8601(SystemTime::now()),
+        app_version: app.package_info().version.to_string(),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        license_tier: entitlements::tier(&app),
+        tools: toolchain::tool_versions(&app)
+            .into_iter()
+            .map(|(tool, version)| (tool, redact(&version, home.as_deref())))
+            .collect(),
+        settings: scrub(settings, home.as_deref()),
+        logs,
+    };
+
+    let picked = app
+        .dialog()
+        .file()
+        .add_filter("Diagnostics", &["json"])
+        .set_file_name("clipme-diagnostics.json")
+        .blocking_save_file();
+    let Some(path) = picked.and_then(|p| p.into_path().ok()) else {
+        return Ok(None);
+    };
+    let content = serde_json::to_string_pretty(&diagnostics)
+        .map_err(|e| format!("Failed to serialize diagnostics: {}", e))?;
+    fs::write(&path, content).map_err(|e| format!("Failed to write diagnostics: {}", e))?;
+    log::info!("Exported diagnostics to {}", path.display());
+    Ok(Some(path.to_string_lossy().to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redacts_secrets_and_personal_data() {
+        let home = Some("/home/alice");
+        assert_eq!(
+            redact("Saved project to /home/alice/Videos/talk.clipme", home),
+            "Saved project to ~/Videos/talk.clipme"
+        );
+        assert_eq!(
+            redact(
+                r#"Running FFmpeg: ["-i", "C:\\Users\\alice\\in.mp4", "C:\\Users\\alice\\out.mp4"]"#,
+                Some(r"C:\Users\alice")
+            ),
+            r#"Running FFmpeg: ["-i", "~\\in.mp4", "~\\out.mp4"]"#
+        );
+        assert_eq!(
+            redact("License for alice.smith+pro@example.co.uk.", home),
+            "License for <email>."
+        );
+        assert_eq!(
+            redact("key 38A2D1F4-6E7B4C21-9F3E8A57-1B2C3D4E rejected", None),
+            "key <license-key> rejected"
+        );
+        assert_eq!(
+            redact(
+                "Fetching https://user:pw@example.com/watch?v=abc&token=x",
+                None
+            ),
+            "Fetching https://<redacted>@example.com/watch?<redacted>"
+        );
+        assert_eq!(
+            redact("form license_key=ABC123 product_id=Vk", None),
+            "form license_key=<redacted> product_id=Vk"
+        );
+        assert_eq!(
+            redact(
+                r#"{"token":"eyJwcm9kdWN0X2lkIjoiVmtNdk5yVzZRTXFiSWd2bHQ0TDZ4dz09In0.AbC123"}"#,
+                None
+            ),
+            r#"{"token":"<secret>"}"#
+        );
+        // Checksums, ids and plain text stay readable
+        let plain = "sha256 9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08 for dQw4w9WgXcQ at 12:30";
+        assert_eq!(redact(plain, None), plain);
+    }
+
+    #[test]
+    fn round_trips_lines() {
+        let line = format_line(
+            "2025-01-01T00:00:00.000Z",
+            Level::Warn,
+            "clipme_lib::history",
+            "first: line\nsecond",
+        );
+        assert_eq!(line.matches('\n').count(), 1);
+        assert_eq!(
+            parse_line(line.trim_end()),
+            Some(LogEntry {
+                time: "2025-01-01T00:00:00.000Z".to_string(),
+                level: "WARN".to_string(),
+                target: "clipme_lib::history".to_string(),
+                message: "first: line\\nsecond".to_string(),
+            })
+        );
+        assert_eq!(parse_line("not a log line"), None);
+    }
+
+    #[test]
+    fn rotates_and_reads_across_files() {
+        let dir = std::env::temp_dir().join(format!("clipme-logs-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let logger = FileLogger {
+            level: LevelFilter::Debug,
+            dir: dir.clone(),
+            home: None,
+            file: Mutex::new(None),
+        };
+
+        for n in 0..6 {
+            let line = format_line("t", Level::Info, "test", &format!("entry {}", n));
+            logger.write_line(&line).unwrap();
+            if n % 2 == 1 {
+                *logger.file.lock().unwrap() = None;
+                rotate(&dir, 2).unwrap();
+            }
+        }
+        logger
+            .write_line(&format_line("t", Level::Debug, "test", "detail"))
+            .unwrap();
+
+        // Only two rotated files survive, so entries 0 and 1 are gone
+        assert_eq!(log_files(&dir).len(), 3);
+        let messages = |level, limit| -> Vec<String> {
+            read_entries(&dir, level, limit)
+                .into_iter()
+                .map(|e| e.message)
+                .collect()
+        };
+        assert_eq!(
+            messages(LevelFilter::Info, 10),
+            ["entry 2", "entry 3", "entry 4", "entry 5"]
+        );
+        assert_eq!(messages(LevelFilter::Debug, 2), ["entry 5", "detail"]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
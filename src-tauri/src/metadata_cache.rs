@@ -0,0 +1,321 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+
+use crate::VideoMetadata;
+
+/// Default time-to-live for cached lookups
+pub const DEFAULT_TTL_SECS: u64 = 60 * 60;
+/// Default cap for the whole metadata cache directory
+pub const DEFAULT_MAX_BYTES: u64 = 20 * 1024 * 1024;
+
+const ALIASES_FILE: &str = "aliases.json";
+
+#[derive(Serialize, Deserialize, Debug)]
+struct CacheEntry {
+    key: String,
+    fetched_at: u64,
+    metadata: VideoMetadata,
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn cache_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .app_cache_dir()
+        .map_err(|e| format!("Failed to get cache dir: {}", e))?
+        .join("metadata");
+    if !dir.exists() {
+        fs::create_dir_all(&dir).map_err(|e| format!("Failed to create cache dir: {}", e))?;
+    }
+    Ok(dir)
+}
+
+fn entry_path(dir: &std::path::Path, key: &str) -> PathBuf {
+    let file_stem: String = key
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    dir.join(format!("{}.json", file_stem))
+}
+
+fn is_youtube_id(id: &str) -> bool {
+    id.len() == 11
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Extract the video id from the common YouTube URL shapes
+/// (`youtu.be/<id>`, `watch?v=<id>`, `/shorts/<id>`, `/embed/<id>`, `/live/<id>`).
+pub fn youtube_video_id(url: &str) -> Option<String> {
+    let without_scheme = url
+        .trim()
+        .trim_start_matches("https://")
+        .trim_start_matches("http://");
+    let (host, rest) = without_scheme.split_once('/')?;
+    let host = host.trim_start_matches("www.").trim_start_matches("m.");
+    let (path, query) = rest.split_once('?').unwrap_or((rest, ""));
+    let query = query.split('#').next().unwrap_or_default();
+
+    let candidate = if host == "youtu.be" {
+        path.split('/').next().map(String::from)
+    } else if host == "youtube.com" || host == "music.youtube.com" {
+        if path == "watch" || path.is_empty() {
+            query
+                .split('&')
+                .find_map(|pair| pair.strip_prefix("v="))
+                .map(String::from)
+        } else {
+            ["shorts/", "embed/", "live/", "v/"]
+                .iter()
+                .find_map(|prefix| path.strip_prefix(prefix))
+                .and_then(|id| id.split('/').next())
+                .map(String::from)
+        }
+    } else {
+        None
+    };
+
+    candidate.filter(|id| is_youtube_id(id))
+}
+
+/// Cache key for a URL that can be derived without asking yt-dlp
+fn key_from_url(url: &str) -> Option<String> {
+    youtube_video_id(url).map(|id| format!("youtube:{}", id))
+}
+
+/// Cache key reported by yt-dlp for a fetched video (`<extractor>:<id>`)
+fn key_from_json(json_val: &serde_json::Value) -> Option<String> {
+    let extractor = json_val["extractor_key"]
+        .as_str()
+        .or(json_val["extractor"].as_str())?;
+    let id = json_val["id"].as_str()?;
+    Some(format!("{}:{}", extractor.to_lowercase(), id))
+}
+
+/// URLs are aliased to keys with the fragment and surrounding whitespace removed
+fn alias_for(url: &str) -> String {
+    url.trim().split('#').next().unwrap_or_default().to_string()
+}
+
+fn load_aliases(dir: &std::path::Path) -> serde_json::Map<String, serde_json::Value> {
+    fs::read_to_string(dir.join(ALIASES_FILE))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Resolve the cache key for a URL, either directly or via a previous lookup
+fn resolve_key(dir: &std::path::Path, url: &str) -> Option<String> {
+    key_from_url(url).or_else(|| {
+        load_aliases(dir)
+            .get(&alias_for(url))
+            .and_then(|v| v.as_str())
+            .map(String::from)
+    })
+}
+
+/// Return cached metadata for `url` if present and younger than `ttl_secs`.
+/// Entries whose downloaded preview file has disappeared are treated as misses.
+pub fn lookup(app: &AppHandle, url: &str, ttl_secs: u64) -> Option<VideoMetadata> {
+    let dir = cache_dir(app).ok()?;
+    let key = resolve_key(&dir, url)?;
+    let content = fs::read_to_string(entry_path(&dir, &key)).ok()?;
+    let entry: CacheEntry = serde_json::from_str(&content).ok()?;
+
+    if now_secs().saturating_sub(entry.fetched_at) > ttl_secs {
+        return None;
+    }
+    if let Some(ref preview) = entry.metadata.preview_url {
+        if !preview.starts_with("http") && !std::path::Path::new(preview).exists() {
+            return None;
+        }
+    }
+
+    log::debug!("Metadata cache hit: {}", key);
+    Some(entry.metadata)
+}
+
+/// Swap in a re-resolved preview URL without touching the rest of the entry
+pub fn update_preview(app: &AppHandle, url: &str, preview_url: &str, expires_at: Option<u64>) {
+    let Ok(dir) = cache_dir(app) else {
+        return;
+    };
+    let Some(key) = resolve_key(&dir, url) else {
+        return;
+    };
+    let path = entry_path(&dir, &key);
+    let Some(mut entry) = fs::read_to_string(&path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<CacheEntry>(&content).ok())
+    else {
+        return;
+    };
+
+    entry.metadata.preview_url = Some(preview_url.to_string());
+    entry.metadata.preview_expires_at = expires_at;
+    if let Ok(json_str) = serde_json::to_string(&entry) {
+        let _ = fs::write(&path, json_str);
+    }
+}
+
+/// Store freshly fetched metadata and trim the cache to `max_bytes`
+pub fn store(
+    app: &AppHandle,
+    url: &str,
+    json_val: &serde_json::Value,
+    metadata: &VideoMetadata,
+    max_bytes: u64,
+) -> Result<(), String> {
+    let Some(key) = key_from_json(json_val).or_else(|| key_from_url(url)) else {
+        return Ok(());
+    };
+    let dir = cache_dir(app)?;
+
+    let entry = CacheEntry {
+        key: key.clone(),
+        fetched_at: now_secs(),
+        metadata: metadata.clone(),
+    };
+    let json_str = serde_json::to_string(&entry)
+        .map_err(|e| format!("Failed to serialize cache entry: {}", e))?;
+    fs::write(entry_path(&dir, &key), json_str)
+        .map_err(|e| format!("Failed to write cache entry: {}", e))?;
+
+    enforce_size_cap(&dir, max_bytes);
+
+    // Aliases of evicted entries go too, so the file doesn't only grow
+    let mut aliases = load_aliases(&dir);
+    if key_from_url(url).is_none() {
+        aliases.insert(alias_for(url), serde_json::Value::String(key));
+    }
+    let aliases = prune_aliases(&dir, aliases);
+    let json_str = serde_json::to_string(&aliases)
+        .map_err(|e| format!("Failed to serialize cache aliases: {}", e))?;
+    fs::write(dir.join(ALIASES_FILE), json_str)
+        .map_err(|e| format!("Failed to write cache aliases: {}", e))?;
+    Ok(())
+}
+
+/// Keep only aliases whose entry is still on disk
+fn prune_aliases(
+    dir: &std::path::Path,
+    aliases: serde_json::Map<String, serde_json::Value>,
+) -> serde_json::Map<String, serde_json::Value> {
+    aliases
+        .into_iter()
+        .filter(|(_, key)| {
+            key.as_str()
+                .is_some_and(|key| entry_path(dir, key).is_file())
+        })
+        .collect()
+}
+
+/// Delete the oldest entries until the cache fits in `max_bytes`
+fn enforce_size_cap(dir: &std::path::Path, max_bytes: u64) {
+    let Ok(read_dir) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut entries: Vec<(std::time::SystemTime, u64, PathBuf)> = read_dir
+        .filter_map(Result::ok)
+        .filter(|e| e.file_name() != ALIASES_FILE)
+        .filter_map(|e| {
+            let meta = e.metadata().ok()?;
+            Some((meta.modified().ok()?, meta.len(), e.path()))
+        })
+        .collect();
+
+    let mut total: u64 = entries.iter().map(|(_, len, _)| len).sum();
+    entries.sort_by_key(|(modified, _, _)| *modified);
+    for (_, len, path) in entries {
+        if total <= max_bytes {
+            break;
+        }
+        if fs::remove_file(&path).is_ok() {
+            total = total.saturating_sub(len);
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn purge_metadata_cache(app: AppHandle) -> Result<(), String> {
+    let dir = cache_dir(&app)?;
+    fs::remove_dir_all(&dir).map_err(|e| format!("Failed to purge metadata cache: {}", e))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_youtube_ids() {
+        for url in [
+            "https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=43",
+            "https://youtu.be/dQw4w9WgXcQ?si=abc",
+            "https://m.youtube.com/shorts/dQw4w9WgXcQ",
+            "http://youtube.com/embed/dQw4w9WgXcQ",
+            "https://music.youtube.com/watch?list=x&v=dQw4w9WgXcQ#frag",
+        ] {
+            assert_eq!(
+                youtube_video_id(url).as_deref(),
+                Some("dQw4w9WgXcQ"),
+                "{}",
+                url
+            );
+        }
+        assert_eq!(
+            youtube_video_id("https://www.youtube.com/watch?v=short"),
+            None
+        );
+        assert_eq!(youtube_video_id("https://vimeo.com/123456"), None);
+        assert_eq!(
+            key_from_url("https://youtu.be/dQw4w9WgXcQ").as_deref(),
+            Some("youtube:dQw4w9WgXcQ")
+        );
+    }
+
+    #[test]
+    fn keys_fetched_videos_by_extractor_and_id() {
+        let json =
+            serde_json::json!({ "extractor_key": "TikTok", "extractor": "tiktok", "id": "7300" });
+        assert_eq!(key_from_json(&json).as_deref(), Some("tiktok:7300"));
+        let json = serde_json::json!({ "extractor": "Vimeo", "id": "42" });
+        assert_eq!(key_from_json(&json).as_deref(), Some("vimeo:42"));
+        assert_eq!(key_from_json(&serde_json::json!({ "id": "42" })), None);
+
+        assert_eq!(
+            alias_for("  https://vimeo.com/42#t=10 "),
+            "https://vimeo.com/42"
+        );
+    }
+
+    #[test]
+    fn prunes_aliases_of_evicted_entries() {
+        let dir = std::env::temp_dir().join(format!("clipme-metadata-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(entry_path(&dir, "vimeo:42"), "{}").unwrap();
+
+        let mut aliases = serde_json::Map::new();
+        aliases.insert("https://vimeo.com/42".into(), "vimeo:42".into());
+        aliases.insert("https://vimeo.com/gone".into(), "vimeo:7".into());
+        let kept = prune_aliases(&dir, aliases);
+        assert_eq!(kept.keys().collect::<Vec<_>>(), ["https://vimeo.com/42"]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,516 @@
+//! Output file names from a template such as `{uploader}/{title}_{start}`.
+//! A `/` in the template starts a subfolder. Each part is made safe for the
+//! current OS and shortened to fit filesystem limits, keeping the extension.
+
+use crate::{history, metadata_cache, read_app_settings, resolve_output_dir, AppState};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::AppHandle;
+
+/// Reproduces the original names: `{n}` is empty for single clips
+pub const DEFAULT_TEMPLATE: &str = "{title}_clip{n}_{timestamp}";
+const VARIABLES: &[&str] = &[
+    "title",
+    "uploader",
+    "id",
+    "start",
+    "end",
+    "quality",
+    "date",
+    "timestamp",
+    "segment_label",
+    "n",
+];
+/// Longest file or folder name written, in bytes. Filesystems allow 255; the
+/// rest is left for the `.temp`, `.part` and fragment suffixes of downloads.
+const MAX_NAME_BYTES: usize = 200;
+const MAX_SUFFIX: u32 = 9999;
+const WINDOWS_RESERVED: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// What to do when the output file already exists
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CollisionPolicy {
+    /// Add `_2`, `_3`, ... before the extension
+    #[default]
+    Suffix,
+    Overwrite,
+    /// Keep the existing file and don't render the clip
+    Skip,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Os {
+    Windows,
+    Mac,
+    Linux,
+}
+
+impl Os {
+    pub fn current() -> Os {
+        if cfg!(windows) {
+            Os::Windows
+        } else if cfg!(target_os = "macos") {
+            Os::Mac
+        } else {
+            Os::Linux
+        }
+    }
+}
+
+/// Values the template variables expand to
+#[derive(Debug, Clone, Default)]
+pub struct NameFields {
+    pub title: String,
+    pub uploader: String,
+    pub id: String,
+    pub start: f64,
+    pub end: f64,
+    pub quality: String,
+    pub segment_label: Option<String>,
+    /// Segment number in a multi-clip export
+    pub n: Option<usize>,
+    pub timestamp: u64,
+    /// UTC date as `YYYY-MM-DD`
+    pub date: String,
+}
+
+impl NameFields {
+    fn value(&self, name: &str) -> String {
+        match name {
+            "title" => self.title.clone(),
+            "uploader" => self.uploader.clone(),
+            "id" => self.id.clone(),
+            "start" => clock(self.start),
+            "end" => clock(self.end),
+            "quality" => self.quality.clone(),
+            "date" => self.date.clone(),
+            "timestamp" => self.timestamp.to_string(),
+            "segment_label" => self.segment_label.clone().unwrap_or_default(),
+            "n" => self.n.map(|n| n.to_string()).unwrap_or_default(),
+            _ => String::new(),
+        }
+    }
+
+    /// Sample values for previewing a template in settings
+    fn sample() -> NameFields {
+        NameFields {
+            title: "Never Gonna Give You Up".to_string(),
+            uploader: "Rick Astley".to_string(),
+            id: "dQw4w9WgXcQ".to_string(),
+            start: 43.0,
+            end: 61.5,
+            quality: "1080p".to_string(),
+            segment_label: Some("Chorus".to_string()),
+            n: Some(2),
+            timestamp: 1_700_000_000,
+            date: "2023-11-14".to_string(),
+        }
+    }
+}
+
+/// `HH-MM-SS`, with milliseconds when there are any; `:` isn't allowed everywhere
+fn clock(seconds: f64) -> String {
+    let millis = (seconds.max(0.0) * 1000.0).round() as u64;
+    let (secs, millis) = (millis / 1000, millis % 1000);
+    let base = format!("{:02}-{:02}-{:02}", secs / 3600, secs / 60 % 60, secs % 60);
+    if millis == 0 {
+        base
+    } else {
+        format!("{}.{:03}", base, millis)
+    }
+}
+
+enum Piece<'a> {
+    Text(&'a str),
+    Variable(&'a str),
+    Separator,
+}
+
+fn parse(template: &str) -> Result<Vec<Piece<'_>>, String> {
+    let mut pieces = Vec::new();
+    let mut rest = template;
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('{') {
+            let close = after.find('}').ok_or("Unclosed { in filename template")?;
+            let name = &after[..close];
+            if !VARIABLES.contains(&name) {
+                return Err(format!(
+                    "Unknown variable {{{}}} in filename template; use one of {}",
+                    name,
+                    VARIABLES
+                        .iter()
+                        .map(|v| format!("{{{}}}", v))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ));
+            }
+            pieces.push(Piece::Variable(name));
+            rest = &after[close + 1..];
+        } else if let Some(after) = rest.strip_prefix(['/', '\\']) {
+            pieces.push(Piece::Separator);
+            rest = after;
+        } else {
+            let end = rest.find(['{', '/', '\\']).unwrap_or(rest.len());
+            pieces.push(Piece::Text(&rest[..end]));
+            rest = &rest[end..];
+        }
+    }
+    Ok(pieces)
+}
+
+/// Expand a template into folder names followed by the file stem. Variable
+/// values can't add folders, and empty folders are dropped.
+pub fn render(template: &str, fields: &NameFields, os: Os) -> Result<Vec<String>, String> {
+    let mut parts = vec![String::new()];
+    for piece in parse(template)? {
+        match piece {
+            Piece::Text(text) => parts.last_mut().unwrap().push_str(text),
+            Piece::Variable(name) => parts
+                .last_mut()
+                .unwrap()
+                .push_str(&fields.value(name).replace(['/', '\\'], "_")),
+            Piece::Separator => parts.push(String::new()),
+        }
+    }
+
+    let stem = sanitize(&parts.pop().unwrap_or_default(), os);
+    let mut parts: Vec<String> = parts
+        .iter()
+        .map(|part| truncate(&sanitize(part, os), MAX_NAME_BYTES))
+        .filter(|part| !part.is_empty())
+        .collect();
+    parts.push(if stem.is_empty() {
+        "clip".to_string()
+    } else {
+        stem
+    });
+    Ok(parts)
+}
+
+pub fn validate(template: &str) -> Result<(), String> {
+    if template.trim().is_empty() {
+        return Err("Filename template is empty".to_string());
+    }
+    render(template, &NameFields::sample(), Os::current()).map(|_| ())
+}
+
+/// Make one path component safe to create on `os`
+pub fn sanitize(name: &str, os: Os) -> String {
+    let forbidden: &[char] = match os {
+        Os::Windows => &['<', '>', ':', '"', '/', '\\', '|', '?', '*'],
+        Os::Mac => &[':', '/'],
+        Os::Linux => &['/'],
+    };
+    let replaced: String = name
+        .chars()
+        .map(|c| {
+            if c.is_whitespace() {
+                ' '
+            } else if c.is_control() || forbidden.contains(&c) {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect();
+    let collapsed = replaced.split_whitespace().collect::<Vec<_>>().join(" ");
+    // No hidden files or `.`/`..`; Windows also drops trailing dots
+    let mut safe = collapsed.trim_start_matches('.').to_string();
+    if os == Os::Windows {
+        safe = safe.trim_end_matches(['.', ' ']).to_string();
+        let base = safe.split('.').next().unwrap_or("").trim_end();
+        if WINDOWS_RESERVED.contains(&base.to_uppercase().as_str()) {
+            safe.insert(0, '_');
+        }
+    }
+    safe
+}
+
+/// Cut to at most `max` bytes on a character boundary
+fn truncate(name: &str, max: usize) -> String {
+    if name.len() <= max {
+        return name.to_string();
+    }
+    let mut end = max;
+    while !name.is_char_boundary(end) {
+        end -= 1;
+    }
+    name[..end].trim_end_matches(['.', ' ']).to_string()
+}
+
+/// `stem + suffix + "." + ext`, shortening the stem so the whole name fits
+fn file_name(stem: &str, suffix: &str, ext: &str) -> String {
+    let room = MAX_NAME_BYTES.saturating_sub(suffix.len() + ext.len() + 1);
+    format!("{}{}.{}", truncate(stem, room), suffix, ext)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Target {
+    Write(PathBuf),
+    /// The file exists and the policy is to keep it
+    Skip(PathBuf),
+}
+
+/// Pick the path to write under `dir`, applying the collision policy.
+/// `earlier` are the paths this job already used; a clash with one of those
+/// is always suffixed, so a template without `{n}` can't make one clip of a
+/// job overwrite or stand in for another.
+pub fn resolve(
+    dir: &Path,
+    parts: &[String],
+    ext: &str,
+    policy: CollisionPolicy,
+    earlier: &[PathBuf],
+    exists: impl Fn(&Path) -> bool,
+) -> Result<Target, String> {
+    let (stem, folders) = parts.split_last().ok_or("Empty file name")?;
+    let folder = folders
+        .iter()
+        .fold(dir.to_path_buf(), |path, part| path.join(part));
+    let path = folder.join(file_name(stem, "", ext));
+    let this_job = |path: &Path| earlier.iter().any(|p| p == path);
+    let taken = |path: &Path| this_job(path) || exists(path);
+    if !taken(&path) {
+        return Ok(Target::Write(path));
+    }
+    match policy {
+        CollisionPolicy::Overwrite if !this_job(&path) => Ok(Target::Write(path)),
+        CollisionPolicy::Skip if !this_job(&path) => Ok(Target::Skip(path)),
+        _ => (2..=MAX_SUFFIX)
+            .map(|n| folder.join(file_name(stem, &format!("_{}", n), ext)))
+            .find(|path| !taken(path))
+            .map(Target::Write)
+            .ok_or_else(|| format!("Too many files named {}", path.display())),
+    }
+}
+
+/// Fields for a clip of `url`. Uploader and id come from cached metadata;
+/// local files use their file name as the id.
+pub fn fields_for(app: &AppHandle, url: &str, title: &str, quality: &str) -> NameFields {
+    let now = SystemTime::now();
+    let metadata = metadata_cache::lookup(app, url, u64::MAX);
+    let id = metadata
+        .as_ref()
+        .and_then(|m| m.video_id.clone())
+        .or_else(|| metadata_cache::youtube_video_id(url))
+        .or_else(|| {
+            Path::new(url)
+                .file_stem()
+                .filter(|_| !url.contains("://"))
+                .map(|stem| stem.to_string_lossy().to_string())
+        })
+        .unwrap_or_default();
+    NameFields {
+        title: title.to_string(),
+        uploader: metadata.and_then(|m| m.uploader).unwrap_or_default(),
+        id,
+        quality: quality.to_string(),
+        timestamp: now
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default(),
+        date: history::iso8601(now)[..10].to_string(),
+        ..NameFields::default()
+    }
+}
+
+/// Where to write a clip, following the template and collision policy from
+/// settings. Creates any subfolders. `earlier` are the job's previous outputs.
+pub fn output_target(
+    app: &AppHandle,
+    state: &AppState,
+    fields: &NameFields,
+    ext: &str,
+    earlier: &[PathBuf],
+) -> Result<Target, String> {
+    let settings = read_app_settings(app).unwrap_or_default();
+    let template = settings
+        .filename_template
+        .as_deref()
+        .filter(|t| !t.trim().is_empty())
+        .unwrap_or(DEFAULT_TEMPLATE);
+    let parts = render(template, fields, Os::current())?;
+    let target = resolve(
+        &resolve_output_dir(app, state)?,
+        &parts,
+        ext,
+        settings.collision_policy.unwrap_or_default(),
+        earlier,
+        |path| path.exists(),
+    )?;
+    if let Target::Write(path) = &target {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create output folder: {}", e))?;
+        }
+    }
+    Ok(target)
+}
+
+/// The file name a template gives for a sample video, for the settings page
+#[tauri::command]
+pub async fn preview_filename(template: String) -> Result<String, String> {
+    validate(&template)?;
+    let parts = render(&template, &NameFields::sample(), Os::current())?;
+    let (stem, folders) = parts.split_last().ok_or("Empty file name")?;
+    let mut path: Vec<String> = folders.to_vec();
+    path.push(file_name(stem, "", "mp4"));
+    Ok(path.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn fields() -> NameFields {
+        NameFields {
+            title: "Talk: Rust/Tauri?".to_string(),
+            timestamp: 1_700_000_000,
+            start: 65.0,
+            end: 90.25,
+            ..NameFields::default()
+        }
+    }
+
+    #[test]
+    fn keeps_the_original_names_by_default() {
+        let single = render(DEFAULT_TEMPLATE, &fields(), Os::Linux).unwrap();
+        assert_eq!(single, ["Talk: Rust_Tauri?_clip_1700000000"]);
+        let third = NameFields {
+            n: Some(3),
+            ..fields()
+        };
+        assert_eq!(
+            render(DEFAULT_TEMPLATE, &third, Os::Linux).unwrap(),
+            ["Talk: Rust_Tauri?_clip3_1700000000"]
+        );
+    }
+
+    #[test]
+    fn expands_variables_and_folders() {
+        let fields = NameFields {
+            uploader: "Some Channel".to_string(),
+            segment_label: Some("Intro".to_string()),
+            ..fields()
+        };
+        assert_eq!(
+            render(
+                "{uploader}/{date}/{segment_label} {start}-{end}",
+                &fields,
+                Os::Linux
+            )
+            .unwrap(),
+            ["Some Channel", "Intro 00-01-05-00-01-30.250"]
+        );
+        // Empty folders vanish and `..` can't climb out of the output dir
+        let anonymous = NameFields {
+            title: "..".to_string(),
+            ..NameFields::default()
+        };
+        assert_eq!(
+            render("{uploader}/../{title}", &anonymous, Os::Linux).unwrap(),
+            ["clip"]
+        );
+        assert!(render("{title", &fields, Os::Linux).is_err());
+        assert!(render("{name}", &fields, Os::Linux)
+            .unwrap_err()
+            .contains("{title}"));
+    }
+
+    #[test]
+    fn sanitizes_per_os() {
+        let name = "a<b>:c\"d|e?f*g\t. ";
+        assert_eq!(sanitize(name, Os::Windows), "a_b__c_d_e_f_g");
+        assert_eq!(sanitize(name, Os::Mac), "a<b>_c\"d|e?f*g .");
+        assert_eq!(sanitize(name, Os::Linux), "a<b>:c\"d|e?f*g .");
+        assert_eq!(sanitize("con.txt", Os::Windows), "_con.txt");
+        assert_eq!(sanitize("Trailing...", Os::Windows), "Trailing");
+        assert_eq!(sanitize(".hidden", Os::Linux), "hidden");
+    }
+
+    #[test]
+    fn truncates_long_names_but_keeps_the_extension() {
+        let long = "é".repeat(300);
+        let name = file_name(&long, "_12", "webm");
+        assert!(name.len() <= MAX_NAME_BYTES);
+        assert!(name.ends_with("é_12.webm"));
+    }
+
+    #[test]
+    fn applies_the_collision_policy() {
+        let dir = Path::new("/clips");
+        let taken: HashSet<PathBuf> = ["/clips/a.mp4", "/clips/a_2.mp4"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+        let exists = |path: &Path| taken.contains(path);
+        let parts = vec!["a".to_string()];
+
+        assert_eq!(
+            resolve(dir, &parts, "mp4", CollisionPolicy::Suffix, &[], exists).unwrap(),
+            Target::Write(PathBuf::from("/clips/a_3.mp4"))
+        );
+        assert_eq!(
+            resolve(dir, &parts, "mp4", CollisionPolicy::Overwrite, &[], exists).unwrap(),
+            Target::Write(PathBuf::from("/clips/a.mp4"))
+        );
+        assert_eq!(
+            resolve(dir, &parts, "mp4", CollisionPolicy::Skip, &[], exists).unwrap(),
+            Target::Skip(PathBuf::from("/clips/a.mp4"))
+        );
+        assert_eq!(
+            resolve(
+                dir,
+                &["sub".to_string(), "b".to_string()],
+                "mkv",
+                CollisionPolicy::Skip,
+                &[],
+                exists
+            )
+            .unwrap(),
+            Target::Write(PathBuf::from("/clips/sub/b.mkv"))
+        );
+    }
+
+    #[test]
+    fn never_reuses_a_name_within_one_job() {
+        let dir = Path::new("/clips");
+        let parts = vec!["a".to_string()];
+        // The first clip of the job kept or replaced a.mp4, which now exists
+        let earlier = [PathBuf::from("/clips/a.mp4")];
+        let exists = |path: &Path| path == Path::new("/clips/a.mp4");
+
+        for policy in [
+            CollisionPolicy::Suffix,
+            CollisionPolicy::Overwrite,
+            CollisionPolicy::Skip,
+        ] {
+            assert_eq!(
+                resolve(dir, &parts, "mp4", policy, &earlier, exists).unwrap(),
+                Target::Write(PathBuf::from("/clips/a_2.mp4"))
+            );
+        }
+        // Not yet on disk still counts as taken
+        assert_eq!(
+            resolve(
+                dir,
+                &parts,
+                "mp4",
+                CollisionPolicy::Overwrite,
+                &[
+                    PathBuf::from("/clips/a.mp4"),
+                    PathBuf::from("/clips/a_2.mp4")
+                ],
+                |_| false
+            )
+            .unwrap(),
+            Target::Write(PathBuf::from("/clips/a_3.mp4"))
+        );
+    }
+}
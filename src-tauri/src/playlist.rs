@@ -0,0 +1,509 @@
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Read};
+use std::process::Stdio;
+use tauri::{AppHandle, Emitter, State};
+
+use crate::entitlements::{self, CommandError, Feature};
+use crate::{get_ytdlp, run_clip_job, timecode, validation, AppState, ClipJob};
+
+const DEFAULT_PAGE_SIZE: u32 = 50;
+const MAX_PAGE_SIZE: u32 = 500;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PlaylistEntry {
+    index: u64,
+    id: String,
+    title: String,
+    duration: Option<f64>,
+    thumbnail: Option<String>,
+    url: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PlaylistPage {
+    playlist_id: Option<String>,
+    title: Option<String>,
+    total_count: Option<u64>,
+    page: u32,
+    page_size: u32,
+    has_more: bool,
+    entries: Vec<PlaylistEntry>,
+}
+
+#[derive(Clone, Serialize, Debug)]
+struct PlaylistEntryEvent {
+    request_id: u64,
+    entry: PlaylistEntry,
+}
+
+/// Channel URLs without a tab expand into their tabs ("Videos", "Shorts", ...)
+/// rather than videos, so point them at the uploads tab.
+fn expand_channel_url(url: &str) -> String {
+    let is_channel = url.contains("youtube.com/@")
+        || url.contains("youtube.com/channel/")
+        || url.contains("youtube.com/c/");
+    if !is_channel {
+        return url.to_string();
+    }
+
+    let trimmed = url.trim_end_matches('/');
+    let tabs = ["/videos", "/shorts", "/streams", "/playlists", "/featured"];
+    if tabs.iter().any(|tab| trimmed.ends_with(tab)) {
+        trimmed.to_string()
+    } else {
+        format!("{}/videos", trimmed)
+    }
+}
+
+/// Turn one `--flat-playlist --dump-json` line into an entry
+fn parse_flat_entry(json_val: &serde_json::Value, fallback_index: u64) -> Option<PlaylistEntry> {
+    let id = json_val["id"].as_str()?.to_string();
+    let url = json_val["url"]
+        .as_str()
+        .or(json_val["webpage_url"].as_str())
+        .map(String::from)
+        .unwrap_or_else(|| format!("https://www.youtube.com/watch?v={}", id));
+    let thumbnail = json_val["thumbnails"]
+        .as_array()
+        .and_then(|thumbs| thumbs.last())
+        .and_then(|thumb| thumb["url"].as_str())
+        .or(json_val["thumbnail"].as_str())
+        .map(String::from);
+
+    Some(PlaylistEntry {
+        index: json_val["playlist_index"].as_u64().unwrap_or(fallback_index),
+        title: json_val["title"].as_str().unwrap_or("Untitled").to_string(),
+        duration: json_val["duration"].as_f64(),
+        id,
+        thumbnail,
+        url,
+    })
+}
+
+/// List one page of a playlist or channel. Entries are also emitted one by one
+/// as `playlist-entry` events (tagged with `request_id`) while yt-dlp walks the list.
+#[tauri::command]
+pub async fn get_playlist(
+    app: AppHandle,
+    url: String,
+    page: Option<u32>,
+    page_size: Option<u32>,
+    request_id: u64,
+) -> Result<PlaylistPage, String> {
+    let page = page.unwrap_or(0);
+    let page_size = page_size.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let first = page as u64 * page_size as u64 + 1;
+    // Ask for one extra entry so we know whether another page exists
+    let last = first + page_size as u64;
+
+    let url = expand_channel_url(&validation::validate_remote(&url)?);
+    log::info!("Listing playlist: {} (items {}-{})", url, first, last);
+
+    let ytdlp = get_ytdlp(&app)?;
+    let mut child = ytdlp.command()
+        .args(validation::ytdlp_args(
+            [
+                "--flat-playlist",
+                "--dump-json",
+                "--no-warnings",
+                "--playlist-items",
+                &format!("{}:{}", first, last),
+            ],
+            &url,
+        ))
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to execute yt-dlp: {}", e))?;
+
+    let mut stderr = child.stderr.take();
+    let stderr_thread = std::thread::spawn(move || {
+        let mut buf = String::new();
+        if let Some(ref mut err) = stderr {
+            let _ = err.read_to_string(&mut buf);
+        }
+        buf
+    });
+
+    let mut entries: Vec<PlaylistEntry> = Vec::new();
+    let mut playlist_id = None;
+    let mut title = None;
+    let mut total_count = None;
+
+    if let Some(stdout) = child.stdout.take() {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            let Ok(json_val) = serde_json::from_str::<serde_json::Value>(&line) else {
+                continue;
+            };
+            if playlist_id.is_none() {
+                playlist_id = json_val["playlist_id"].as_str().map(String::from);
+                title = json_val["playlist_title"].as_str().map(String::from);
+                total_count = json_val["playlist_count"].as_u64();
+            }
+            let fallback_index = first + entries.len() as u64;
+            let Some(entry) = parse_flat_entry(&json_val, fallback_index) else {
+                continue;
+            };
+            if entries.len() < page_size as usize {
+                let _ = app.emit(
+                    "playlist-entry",
+                    PlaylistEntryEvent {
+                        request_id,
+                        entry: entry.clone(),
+                    },
+                );
+            }
+            entries.push(entry);
+        }
+    }
+
+    let status = child
+        .wait()
+        .map_err(|e| format!("Failed to wait on yt-dlp: {}", e))?;
+    let stderr = stderr_thread.join().unwrap_or_default();
+
+    if !status.success() && entries.is_empty() {
+        return Err(format!("yt-dlp error: {}", stderr));
+    }
+
+    let has_more = entries.len() > page_size as usize;
+    entries.truncate(page_size as usize);
+
+    Ok(PlaylistPage {
+        playlist_id,
+        title,
+        total_count,
+        page,
+        page_size,
+        has_more,
+        entries,
+    })
+}
+
+/// How each batch entry is turned into a clip
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum ClipRule {
+    /// The first `seconds` of every video
+    FirstSeconds {
+        #[serde(deserialize_with = "timecode::deserialize_seconds")]
+        seconds: f64,
+    },
+    /// The whole video
+    FullVideo,
+    /// The same range for every video
+    Range {
+        #[serde(deserialize_with = "timecode::deserialize_seconds")]
+        start: f64,
+        #[serde(deserialize_with = "timecode::deserialize_seconds")]
+        end: f64,
+    },
+    /// Each entry's own `start`/`end`
+    PerItem,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct BatchEntry {
+    url: String,
+    title: String,
+    duration: Option<f64>,
+    #[serde(default, deserialize_with = "timecode::deserialize_optional_seconds")]
+    start: Option<f64>,
+    #[serde(default, deserialize_with = "timecode::deserialize_optional_seconds")]
+    end: Option<f64>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct BatchItemResult {
+    url: String,
+    title: String,
+    output_path: Option<String>,
+    error: Option<String>,
+}
+
+#[derive(Clone, Serialize, Debug)]
+struct BatchProgress {
+    batch_id: u64,
+    current_item: usize,
+    total_items: usize,
+    overall_percent: f64,
+    title: String,
+    status: String,
+}
+
+impl ClipRule {
+    /// Resolve the clip range for an entry. A `None` end means "until the end"
+    /// of a video whose duration the listing didn't include.
+    fn range_for(&self, entry: &BatchEntry) -> Result<(f64, Option<f64>), String> {
+        let clamp = |end: f64| entry.duration.map_or(end, |duration| end.min(duration));
+        let (start, end) = match self {
+            ClipRule::FirstSeconds { seconds } => (0.0, Some(clamp(*seconds))),
+            ClipRule::FullVideo => (0.0, entry.duration),
+            ClipRule::Range { start, end } => (*start, Some(clamp(*end))),
+            ClipRule::PerItem => (
+                entry.start.unwrap_or(0.0),
+                entry.end.map(clamp).or(entry.duration),
+            ),
+        };
+        check_range(start, end, entry.duration)?;
+        Ok((start, end))
+    }
+}
+
+fn check_range(start: f64, end: Option<f64>, duration: Option<f64>) -> Result<(), String> {
+    let past_end = duration.is_some_and(|duration| start >= duration);
+    if start < 0.0 || past_end || end.is_some_and(|end| end <= start) {
+        return Err(match end {
+            Some(end) => format!("Invalid range {}-{} for this video", start, end),
+            None => format!("Invalid start {} for this video", start),
+        });
+    }
+    Ok(())
+}
+
+/// Look up the duration of a video the playlist listing didn't time, so an
+/// open-ended range gets a real end. Local files must come with their duration.
+fn resolve_end(app: &AppHandle, url: &str, start: f64) -> Result<f64, String> {
+    let url = match validation::validate_source(app, url)? {
+        validation::MediaSource::Remote(url) => url,
+        validation::MediaSource::Local(_) => {
+            return Err("Set an end time for local files of unknown length".to_string())
+        }
+    };
+    let output = get_ytdlp(app)?
+        .command()
+        .args(validation::ytdlp_args(
+            ["--print", "duration", "--no-playlist", "--no-warnings"],
+            &url,
+        ))
+        .output()
+        .map_err(|e| format!("Failed to execute yt-dlp: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "yt-dlp error: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    let duration = String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse::<f64>()
+        .map_err(|_| "Couldn't find out how long this video is".to_string())?;
+    check_range(start, Some(duration), Some(duration))?;
+    Ok(duration)
+}
+
+/// Clip a list of playlist entries one after another with a shared rule.
+/// A failing entry is recorded and the batch moves on; cancelling stops the batch.
+/// The app has no playlist view yet, so this and `get_playlist` are backend-only.
+#[tauri::command]
+pub async fn download_batch(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    entries: Vec<BatchEntry>,
+    rule: ClipRule,
+    quality: String,
+    format: String,
+    batch_id: u64,
+) -> Result<Vec<BatchItemResult>, CommandError> {
+    if entries.is_empty() {
+        return Err("No entries selected".into());
+    }
+    let mut features = vec![Feature::Batch];
+    features.extend(Feature::for_quality(&quality));
+    entitlements::require(&app, &features)?;
+
+    {
+        let mut cancel_lock = state
+            .cancel_requested
+            .lock()
+            .map_err(|_| "Failed to lock state")?;
+        *cancel_lock = false;
+    }
+
+    let total_items = entries.len();
+    let mut results = Vec::with_capacity(total_items);
+
+    for (index, entry) in entries.into_iter().enumerate() {
+        let cancelled = *state
+            .cancel_requested
+            .lock()
+            .map_err(|_| "Failed to lock state")?;
+        if cancelled {
+            results.push(BatchItemResult {
+                url: entry.url,
+                title: entry.title,
+                output_path: None,
+                error: Some("Download cancelled".to_string()),
+            });
+            continue;
+        }
+
+        let _ = app.emit(
+            "batch-progress",
+            BatchProgress {
+                batch_id,
+                current_item: index + 1,
+                total_items,
+                overall_percent: (index as f64 / total_items as f64) * 100.0,
+                title: entry.title.clone(),
+                status: "Downloading".to_string(),
+            },
+        );
+
+        let outcome = rule.range_for(&entry).and_then(|(start, end)| {
+            let end = match end {
+                Some(end) => end,
+                None => resolve_end(&app, &entry.url, start)?,
+            };
+            run_clip_job(
+                &app,
+                &state,
+                ClipJob {
+                    url: entry.url.clone(),
+                    title: entry.title.clone(),
+                    start,
+                    end,
+                    quality: quality.clone(),
+                    format: format.clone(),
+                    id: batch_id,
+                    format_id: None,
+                    video_format_id: None,
+                    audio_tracks: Vec::new(),
+                },
+            )
+            .map_err(|e| e.to_string())
+        });
+
+        let (output_path, error) = match outcome {
+            Ok(path) => (Some(path), None),
+            Err(e) => {
+                log::warn!("Batch item {} failed: {}", index + 1, e);
+                (None, Some(e))
+            }
+        };
+        results.push(BatchItemResult {
+            url: entry.url,
+            title: entry.title,
+            output_path,
+            error,
+        });
+    }
+
+    let _ = app.emit(
+        "batch-progress",
+        BatchProgress {
+            batch_id,
+            current_item: total_items,
+            total_items,
+            overall_percent: 100.0,
+            title: String::new(),
+            status: "Done".to_string(),
+        },
+    );
+
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn entry(duration: Option<f64>, start: Option<f64>, end: Option<f64>) -> BatchEntry {
+        BatchEntry {
+            url: "https://www.youtube.com/watch?v=abc".to_string(),
+            title: "Video".to_string(),
+            duration,
+            start,
+            end,
+        }
+    }
+
+    #[test]
+    fn expands_channels_to_their_uploads() {
+        assert_eq!(
+            expand_channel_url("https://www.youtube.com/@creator"),
+            "https://www.youtube.com/@creator/videos"
+        );
+        assert_eq!(
+            expand_channel_url("https://www.youtube.com/channel/UC123/"),
+            "https://www.youtube.com/channel/UC123/videos"
+        );
+        assert_eq!(
+            expand_channel_url("https://www.youtube.com/@creator/shorts/"),
+            "https://www.youtube.com/@creator/shorts"
+        );
+        let playlist = "https://www.youtube.com/playlist?list=PL123";
+        assert_eq!(expand_channel_url(playlist), playlist);
+    }
+
+    #[test]
+    fn parses_flat_entries() {
+        let entry = parse_flat_entry(
+            &json!({
+                "id": "abc",
+                "title": "First",
+                "duration": 61.5,
+                "playlist_index": 3,
+                "thumbnails": [{"url": "https://i.ytimg.com/small.jpg"}, {"url": "https://i.ytimg.com/big.jpg"}],
+            }),
+            7,
+        )
+        .unwrap();
+        assert_eq!(entry.index, 3);
+        assert_eq!(entry.url, "https://www.youtube.com/watch?v=abc");
+        assert_eq!(entry.duration, Some(61.5));
+        assert_eq!(entry.thumbnail.as_deref(), Some("https://i.ytimg.com/big.jpg"));
+
+        let entry = parse_flat_entry(
+            &json!({"id": "xyz", "url": "https://vimeo.com/1", "thumbnail": "https://t/1.jpg"}),
+            7,
+        )
+        .unwrap();
+        assert_eq!(entry.index, 7);
+        assert_eq!(entry.title, "Untitled");
+        assert_eq!(entry.url, "https://vimeo.com/1");
+        assert_eq!(entry.duration, None);
+        assert_eq!(entry.thumbnail.as_deref(), Some("https://t/1.jpg"));
+
+        assert!(parse_flat_entry(&json!({"title": "No id"}), 1).is_none());
+    }
+
+    #[test]
+    fn resolves_ranges_against_the_duration() {
+        let timed = entry(Some(100.0), None, None);
+        let first = ClipRule::FirstSeconds { seconds: 30.0 };
+        assert_eq!(first.range_for(&timed), Ok((0.0, Some(30.0))));
+        let first = ClipRule::FirstSeconds { seconds: 300.0 };
+        assert_eq!(first.range_for(&timed), Ok((0.0, Some(100.0))));
+        assert_eq!(ClipRule::FullVideo.range_for(&timed), Ok((0.0, Some(100.0))));
+
+        let range = |start, end| ClipRule::Range { start, end };
+        assert_eq!(range(90.0, 120.0).range_for(&timed), Ok((90.0, Some(100.0))));
+        assert!(range(150.0, 160.0).range_for(&timed).is_err());
+        assert!(range(20.0, 10.0).range_for(&timed).is_err());
+
+        let own = entry(Some(100.0), Some(10.0), Some(20.0));
+        assert_eq!(ClipRule::PerItem.range_for(&own), Ok((10.0, Some(20.0))));
+        let open = entry(Some(100.0), Some(10.0), None);
+        assert_eq!(ClipRule::PerItem.range_for(&open), Ok((10.0, Some(100.0))));
+        assert!(ClipRule::PerItem
+            .range_for(&entry(Some(100.0), Some(-1.0), None))
+            .is_err());
+    }
+
+    #[test]
+    fn leaves_the_end_open_without_a_duration() {
+        let untimed = entry(None, None, None);
+        assert_eq!(ClipRule::FullVideo.range_for(&untimed), Ok((0.0, None)));
+        assert_eq!(
+            ClipRule::FirstSeconds { seconds: 30.0 }.range_for(&untimed),
+            Ok((0.0, Some(30.0)))
+        );
+        assert_eq!(
+            ClipRule::PerItem.range_for(&entry(None, Some(5.0), None)),
+            Ok((5.0, None))
+        );
+        assert!(check_range(5.0, None, Some(5.0)).is_err());
+    }
+}
@@ -0,0 +1,462 @@
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::{
+    codec_field, get_ffmpeg, get_ytdlp, metadata_cache, validation, AppState, VideoMetadata,
+};
+
+/// Signed stream URLs are re-resolved this long before they expire
+pub const REFRESH_MARGIN_SECS: u64 = 10 * 60;
+
+/// Default cap for the downloaded preview cache
+pub const DEFAULT_CACHE_MAX_BYTES: u64 = 512 * 1024 * 1024;
+
+/// Lowest rendition that is still useful for scrubbing: a muxed stream up to 480p,
+/// otherwise the smallest muxed one, otherwise whatever exists
+pub const PREVIEW_DOWNLOAD_SELECTOR: &str = "best[height<=480][vcodec!=none][acodec!=none]/worst[vcodec!=none][acodec!=none]/best[height<=480]/worst";
+
+/// Previews above this height are re-encoded into a proxy
+const PROXY_HEIGHT: u64 = 480;
+
+/// yt-dlp selector equivalent to `select_preview_format`, used when re-resolving
+const PREVIEW_FORMAT_SELECTOR: &str = "best[height<=720][vcodec!=none][acodec!=none][protocol^=http][ext=mp4]/best[vcodec!=none][acodec!=none][protocol^=http]";
+
+#[derive(Clone, Serialize, Debug)]
+pub struct PreviewUrl {
+    source_url: String,
+    preview_url: String,
+    expires_at: Option<u64>,
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Pick a stream the webview can play on its own: muxed audio+video over plain
+/// HTTP(S), preferring MP4 at or below 720p. Returns `None` when the source only
+/// offers split (DASH) or manifest-based streams.
+pub fn select_preview_format(json_val: &serde_json::Value) -> Option<String> {
+    let formats_arr = json_val["formats"].as_array()?;
+
+    let playable = formats_arr.iter().filter(|fmt| {
+        let protocol = fmt["protocol"].as_str().unwrap_or("https");
+        codec_field(&fmt["vcodec"]).is_some()
+            && codec_field(&fmt["acodec"]).is_some()
+            && (protocol == "https" || protocol == "http")
+            && fmt["url"].as_str().is_some_and(|u| u.starts_with("http"))
+    });
+
+    let score = |fmt: &serde_json::Value| {
+        let height = fmt["height"].as_u64().unwrap_or(0);
+        let is_mp4 = fmt["ext"].as_str() == Some("mp4");
+        // Anything up to 720p beats larger streams, then MP4 beats other containers
+        (
+            height <= 720,
+            is_mp4,
+            if height <= 720 {
+                height
+            } else {
+                u64::MAX - height
+            },
+        )
+    };
+
+    playable
+        .max_by_key(|fmt| score(fmt))
+        .and_then(|fmt| fmt["url"].as_str())
+        .map(String::from)
+}
+
+/// Read the expiry of a signed stream URL. googlevideo URLs carry it as an
+/// `expire=<unix ts>` query parameter, manifest URLs as an `/expire/<ts>/` path segment.
+pub fn stream_url_expiry(url: &str) -> Option<u64> {
+    let without_fragment = url.split('#').next()?;
+    if let Some((_, query)) = without_fragment.split_once('?') {
+        let from_query = query
+            .split('&')
+            .find_map(|pair| pair.strip_prefix("expire="))
+            .and_then(|v| v.parse::<u64>().ok());
+        if from_query.is_some() {
+            return from_query;
+        }
+    }
+
+    let mut segments = without_fragment.split('/');
+    while let Some(segment) = segments.next() {
+        if segment == "expire" {
+            return segments.next().and_then(|v| v.parse::<u64>().ok());
+        }
+    }
+    None
+}
+
+/// Whether a preview with this expiry should be re-resolved now
+pub fn needs_refresh(expires_at: Option<u64>) -> bool {
+    expires_at.is_some_and(|exp| exp <= now_secs() + REFRESH_MARGIN_SECS)
+}
+
+/// Ask yt-dlp for a fresh playable stream URL
+fn resolve_preview_url(app: &AppHandle, source_url: &str) -> Result<PreviewUrl, String> {
+    let ytdlp = get_ytdlp(app)?;
+    let output = ytdlp
+        .command()
+        .args(validation::ytdlp_args(
+            [
+                "--get-url",
+                "--no-playlist",
+                "--no-warnings",
+                "-f",
+                PREVIEW_FORMAT_SELECTOR,
+            ],
+            source_url,
+        ))
+        .output()
+        .map_err(|e| format!("Failed to execute yt-dlp: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("yt-dlp error: {}", stderr));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let preview_url = stdout
+        .lines()
+        .map(str::trim)
+        .find(|line| line.starts_with("http"))
+        .ok_or("yt-dlp returned no playable stream")?
+        .to_string();
+
+    Ok(PreviewUrl {
+        source_url: source_url.to_string(),
+        expires_at: stream_url_expiry(&preview_url),
+        preview_url,
+    })
+}
+
+/// Re-resolve the preview stream for `url` if it is about to expire (or when `force` is set).
+/// The metadata cache is updated so later lookups get the fresh URL.
+#[tauri::command]
+pub async fn refresh_preview_url(
+    app: AppHandle,
+    url: String,
+    force: Option<bool>,
+) -> Result<PreviewUrl, String> {
+    let url = validation::validate_remote(&url)?;
+    if !force.unwrap_or(false) {
+        if let Some(cached) = metadata_cache::lookup(&app, &url, u64::MAX) {
+            if let Some(preview_url) = cached.preview_url {
+                if !needs_refresh(cached.preview_expires_at) {
+                    return Ok(PreviewUrl {
+                        source_url: url,
+                        preview_url,
+                        expires_at: cached.preview_expires_at,
+                    });
+                }
+            }
+        }
+    }
+
+    let fresh = resolve_preview_url(&app, &url)?;
+    metadata_cache::update_preview(&app, &url, &fresh.preview_url, fresh.expires_at);
+    Ok(fresh)
+}
+
+/// Keep the preview of the most recently opened source alive: shortly before
+/// its signed URL expires it is re-resolved and a `preview-url-refreshed` event
+/// is emitted. Opening another source stops the previous watcher.
+pub fn watch_expiry(app: &AppHandle, source_url: &str, metadata: &VideoMetadata) {
+    let generation = {
+        let state = app.state::<AppState>();
+        let Ok(mut generation_lock) = state.preview_generation.lock() else {
+            return;
+        };
+        *generation_lock += 1;
+        *generation_lock
+    };
+
+    let Some(mut expires_at) = metadata.preview_expires_at else {
+        return;
+    };
+
+    let app = app.clone();
+    let source_url = source_url.to_string();
+    std::thread::spawn(move || loop {
+        let refresh_at = expires_at.saturating_sub(REFRESH_MARGIN_SECS);
+        let wait = refresh_at.saturating_sub(now_secs());
+        // Sleep in short steps so a newer source can retire this watcher
+        let mut waited = 0;
+        while waited < wait {
+            std::thread::sleep(std::time::Duration::from_secs(30.min(wait - waited)));
+            waited += 30;
+            if !is_current(&app, generation) {
+                return;
+            }
+        }
+        if !is_current(&app, generation) {
+            return;
+        }
+
+        match resolve_preview_url(&app, &source_url) {
+            Ok(fresh) => {
+                log::info!("Refreshed preview URL for {}", source_url);
+                metadata_cache::update_preview(
+                    &app,
+                    &source_url,
+                    &fresh.preview_url,
+                    fresh.expires_at,
+                );
+                let _ = app.emit("preview-url-refreshed", fresh.clone());
+                match fresh.expires_at {
+                    Some(next) if next > expires_at => expires_at = next,
+                    _ => return,
+                }
+            }
+            Err(e) => {
+                log::warn!("Failed to refresh preview URL: {}", e);
+                // Retry once the margin has shrunk a bit, but stop after expiry
+                if now_secs() >= expires_at {
+                    return;
+                }
+                std::thread::sleep(std::time::Duration::from_secs(60));
+            }
+        }
+    });
+}
+
+fn is_current(app: &AppHandle, generation: u64) -> bool {
+    let state = app.state::<AppState>();
+    let current = state.preview_generation.lock().map(|g| *g).unwrap_or(0);
+    current == generation
+}
+
+/// Directory holding downloaded previews and proxies for this session
+pub fn preview_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .app_cache_dir()
+        .map_err(|e| format!("Failed to get cache dir: {}", e))?
+        .join("previews");
+    if !dir.exists() {
+        fs::create_dir_all(&dir).map_err(|e| format!("Failed to create preview dir: {}", e))?;
+    }
+    Ok(dir)
+}
+
+/// Whether the webview can play this download as-is
+fn is_webview_playable(path: &Path, json_val: &serde_json::Value) -> bool {
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let container_ok = matches!(ext.as_str(), "mp4" | "m4v" | "webm" | "mov");
+    let codec_ok = match codec_field(&json_val["vcodec"]) {
+        Some(vcodec) => ["avc1", "h264", "vp8", "vp9", "vp09", "av01"]
+            .iter()
+            .any(|prefix| vcodec.starts_with(prefix)),
+        // Unknown codec: trust the container
+        None => true,
+    };
+    let height_ok = json_val["height"].as_u64().unwrap_or(0) <= PROXY_HEIGHT * 3 / 2;
+    container_ok && codec_ok && height_ok
+}
+
+/// Re-encode a preview into a small H.264/AAC proxy next to it
+fn generate_proxy(app: &AppHandle, source: &Path) -> Result<PathBuf, String> {
+    let stem = source
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "preview".to_string());
+    let proxy_path = source.with_file_name(format!("{}.proxy.mp4", stem));
+
+    log::debug!("Generating preview proxy: {:?}", proxy_path);
+
+    let status = get_ffmpeg(app)?
+        .command()
+        .arg("-y")
+        .arg("-i")
+        .arg(source)
+        .args([
+            "-vf",
+            &format!("scale=-2:'min({},ih)'", PROXY_HEIGHT),
+            "-c:v",
+            "libx264",
+            "-preset",
+            "veryfast",
+            "-crf",
+            "30",
+            "-c:a",
+            "aac",
+            "-b:a",
+            "96k",
+            "-movflags",
+            "+faststart",
+        ])
+        .arg(&proxy_path)
+        .status()
+        .map_err(|e| format!("Failed to start ffmpeg: {}", e))?;
+
+    if !status.success() {
+        let _ = fs::remove_file(&proxy_path);
+        return Err("Preview proxy encoding failed".to_string());
+    }
+
+    let _ = fs::remove_file(source);
+    Ok(proxy_path)
+}
+
+/// Turn a freshly downloaded preview into the file the player should open:
+/// unplayable or oversized downloads are replaced by a proxy, the result is
+/// marked as most recently used, and the cache is trimmed to `max_bytes`.
+pub fn finalize_preview(
+    app: &AppHandle,
+    downloaded: &Path,
+    json_val: &serde_json::Value,
+    max_bytes: u64,
+) -> PathBuf {
+    let playable = if is_webview_playable(downloaded, json_val) {
+        downloaded.to_path_buf()
+    } else {
+        generate_proxy(app, downloaded).unwrap_or_else(|e| {
+            log::warn!("Falling back to original preview: {}", e);
+            downloaded.to_path_buf()
+        })
+    };
+
+    touch(&playable);
+    if let Some(dir) = playable.parent() {
+        enforce_cache_limit(dir, max_bytes, &playable);
+    }
+    playable
+}
+
+/// Bump the modification time, which the cache uses as "last used"
+pub fn touch(path: &Path) {
+    if let Ok(file) = fs::File::options().append(true).open(path) {
+        let _ = file.set_modified(std::time::SystemTime::now());
+    }
+}
+
+/// Evict least recently used files until the directory fits in `max_bytes`.
+/// `keep` (the file just opened) is never evicted.
+pub fn enforce_cache_limit(dir: &Path, max_bytes: u64, keep: &Path) {
+    let Ok(read_dir) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut entries: Vec<(std::time::SystemTime, u64, PathBuf)> = read_dir
+        .filter_map(Result::ok)
+        .filter_map(|e| {
+            let meta = e.metadata().ok()?;
+            if !meta.is_file() {
+                return None;
+            }
+            Some((meta.modified().ok()?, meta.len(), e.path()))
+        })
+        .collect();
+
+    let mut total: u64 = entries.iter().map(|(_, len, _)| len).sum();
+    entries.sort_by_key(|(modified, _, _)| *modified);
+    for (_, len, path) in entries {
+        if total <= max_bytes {
+            break;
+        }
+        if path == keep {
+            continue;
+        }
+        log::debug!("Evicting preview: {:?}", path);
+        if fs::remove_file(&path).is_ok() {
+            total = total.saturating_sub(len);
+        }
+    }
+}
+
+/// Remove every cached preview, plus previews left in the temp dir by older versions
+pub fn clear_previews(app: &AppHandle) {
+    if let Ok(dir) = preview_dir(app) {
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    if let Ok(read_dir) = fs::read_dir(std::env::temp_dir()) {
+        for entry in read_dir.filter_map(Result::ok) {
+            if entry
+                .file_name()
+                .to_string_lossy()
+                .starts_with("clipme_preview_")
+            {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn reads_expiry_from_query_and_path() {
+        assert_eq!(
+            stream_url_expiry(
+                "https://rr1.googlevideo.com/videoplayback?id=1&expire=1700000000&ip=x"
+            ),
+            Some(1700000000)
+        );
+        assert_eq!(
+            stream_url_expiry("https://manifest.googlevideo.com/api/manifest/hls/expire/1700000123/ei/abc/file/index.m3u8"),
+            Some(1700000123)
+        );
+        assert_eq!(
+            stream_url_expiry("https://cdn.example.com/v.mp4#expire=5"),
+            None
+        );
+        assert_eq!(
+            stream_url_expiry("https://cdn.example.com/v.mp4?expire=soon"),
+            None
+        );
+    }
+
+    #[test]
+    fn refreshes_within_the_margin() {
+        let now = now_secs();
+        assert!(!needs_refresh(None));
+        assert!(needs_refresh(Some(now)));
+        assert!(needs_refresh(Some(now + REFRESH_MARGIN_SECS - 5)));
+        assert!(!needs_refresh(Some(now + REFRESH_MARGIN_SECS + 60)));
+    }
+
+    #[test]
+    fn prefers_muxed_http_mp4_up_to_720p() {
+        let json = json!({ "formats": [
+            { "url": "https://a/360.mp4", "ext": "mp4", "height": 360, "vcodec": "avc1", "acodec": "mp4a", "protocol": "https" },
+            { "url": "https://a/720.webm", "ext": "webm", "height": 720, "vcodec": "vp9", "acodec": "opus", "protocol": "https" },
+            { "url": "https://a/720.mp4", "ext": "mp4", "height": 720, "vcodec": "avc1", "acodec": "mp4a", "protocol": "https" },
+            { "url": "https://a/1080.mp4", "ext": "mp4", "height": 1080, "vcodec": "avc1", "acodec": "mp4a", "protocol": "https" },
+            { "url": "https://a/720-video.mp4", "ext": "mp4", "height": 720, "vcodec": "avc1", "acodec": "none", "protocol": "https" },
+            { "url": "https://a/720.m3u8", "ext": "mp4", "height": 720, "vcodec": "avc1", "acodec": "mp4a", "protocol": "m3u8_native" }
+        ]});
+        assert_eq!(
+            select_preview_format(&json).as_deref(),
+            Some("https://a/720.mp4")
+        );
+
+        let only_large = json!({ "formats": [
+            { "url": "https://a/2160.mp4", "ext": "mp4", "height": 2160, "vcodec": "avc1", "acodec": "mp4a" },
+            { "url": "https://a/1080.mp4", "ext": "mp4", "height": 1080, "vcodec": "avc1", "acodec": "mp4a" }
+        ]});
+        assert_eq!(
+            select_preview_format(&only_large).as_deref(),
+            Some("https://a/1080.mp4")
+        );
+
+        let split_only = json!({ "formats": [
+            { "url": "https://a/v.mp4", "height": 720, "vcodec": "avc1", "acodec": "none" },
+            { "url": "https://a/a.m4a", "vcodec": "none", "acodec": "mp4a" }
+        ]});
+        assert_eq!(select_preview_format(&split_only), None);
+    }
+}
@@ -0,0 +1,587 @@
+//! `.clipme` project files: a multi-segment session that can be saved, reopened
+//! and autosaved. Local sources carry a fingerprint so a moved file can be
+//! found again, or relinked by hand when it can't.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_dialog::DialogExt;
+
+use crate::{history, toolchain, validation, ClipSegment};
+
+pub const PROJECT_EXTENSION: &str = "clipme";
+const AUTOSAVE_FILE: &str = "autosave.clipme";
+const RECENT_FILE: &str = "recent_projects.json";
+const MAX_RECENT: usize = 10;
+
+/// Upgrades from each older format to the next; the current format is one past the last
+const UPGRADES: &[fn(&mut Value)] = &[];
+pub const PROJECT_VERSION: u32 = UPGRADES.len() as u32 + 1;
+
+/// Where the clips come from
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ProjectSource {
+    /// Web URL or absolute path
+    pub url: String,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub duration: Option<f64>,
+    /// Fingerprint of a local source, filled in on save
+    #[serde(default)]
+    pub file_size: Option<u64>,
+    #[serde(default)]
+    pub sha256: Option<String>,
+    /// Path from the project's folder, so a project moved together with its
+    /// media still opens
+    #[serde(default)]
+    pub relative_path: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Project {
+    #[serde(default)]
+    pub version: u32,
+    pub source: ProjectSource,
+    pub segments: Vec<ClipSegment>,
+    pub quality: String,
+    pub format: String,
+    #[serde(default)]
+    pub format_id: Option<String>,
+    #[serde(default)]
+    pub video_format_id: Option<String>,
+    #[serde(default)]
+    pub audio_tracks: Vec<String>,
+    /// Editor settings per segment id, stored as the frontend sent them
+    #[serde(default)]
+    pub segment_options: BTreeMap<String, Value>,
+    #[serde(default)]
+    pub saved_at: Option<String>,
+}
+
+/// How the source of an opened project was found
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum SourceStatus {
+    Remote,
+    Found,
+    /// The file moved and was found at `url`
+    Relinked {
+        from: String,
+    },
+    /// Not found; the user needs to locate it
+    Missing,
+}
+
+#[derive(Serialize, Debug)]
+pub struct OpenedProject {
+    /// `None` for a restored autosave
+    path: Option<String>,
+    project: Project,
+    source: SourceStatus,
+}
+
+/// A file picked to replace a missing source
+#[derive(Serialize, Debug)]
+pub struct RelinkedSource {
+    source: ProjectSource,
+    /// Whether size and hash match the original; a different file is allowed
+    /// (e.g. a re-encode) but worth a warning
+    matches: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RecentProject {
+    pub path: String,
+    pub title: String,
+    pub opened_at: String,
+    #[serde(default)]
+    pub exists: bool,
+}
+
+fn is_local(url: &str) -> bool {
+    Path::new(url).is_absolute()
+}
+
+/// Read a project file, upgrading older formats
+pub fn parse_project(text: &str) -> Result<Project, String> {
+    let mut value: Value =
+        serde_json::from_str(text).map_err(|e| format!("Not a Clipme project: {}", e))?;
+    let version = value
+        .get("version")
+        .and_then(Value::as_u64)
+        .filter(|v| *v >= 1)
+        .ok_or("Not a Clipme project")?;
+    if version > PROJECT_VERSION as u64 {
+        return Err(format!(
+            "This project was saved by a newer version of Clipme (format {})",
+            version
+        ));
+    }
+    for upgrade in &UPGRADES[version as usize - 1..] {
+        upgrade(&mut value);
+    }
+    let mut project: Project =
+        serde_json::from_value(value).map_err(|e| format!("Invalid project file: {}", e))?;
+    project.version = PROJECT_VERSION;
+    Ok(project)
+}
+
+/// Write through a temp file so a crash never leaves half a project
+fn write_project(path: &Path, project: &Project) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(project)
+        .map_err(|e| format!("Failed to serialize project: {}", e))?;
+    let temp = path.with_extension(format!("{}.tmp", PROJECT_EXTENSION));
+    fs::write(&temp, content).map_err(|e| format!("Failed to write project: {}", e))?;
+    fs::rename(&temp, path).map_err(|e| format!("Failed to write project: {}", e))
+}
+
+/// Record size, hash and project-relative path of a local source. Autosaves
+/// skip the hash so they never wait on a large file.
+fn fingerprint(source: &mut ProjectSource, project_path: Option<&Path>, hash: bool) {
+    if !is_local(&source.url) {
+        source.file_size = None;
+        source.sha256 = None;
+        source.relative_path = None;
+        return;
+    }
+    let path = Path::new(&source.url);
+    let Ok(meta) = fs::metadata(path) else {
+        // Keep the old fingerprint; it's all we have to find the file with
+        return;
+    };
+    if source.file_size != Some(meta.len()) {
+        source.sha256 = None;
+    }
+    source.file_size = Some(meta.len());
+    if hash {
+        source.sha256 = toolchain::sha256_file(path).ok();
+    }
+    source.relative_path = project_path
+        .and_then(Path::parent)
+        .and_then(|dir| path.strip_prefix(dir).ok())
+        .map(|relative| relative.to_string_lossy().to_string());
+}
+
+/// Whether `path` looks like the file the fingerprint was taken from
+fn matches_fingerprint(path: &Path, source: &ProjectSource) -> bool {
+    let Ok(meta) = fs::metadata(path) else {
+        return false;
+    };
+    if !meta.is_file() || source.file_size.is_some_and(|size| size != meta.len()) {
+        return false;
+    }
+    match &source.sha256 {
+        Some(hash) => toolchain::sha256_file(path).is_ok_and(|actual| &actual == hash),
+        None => true,
+    }
+}
+
+/// A project file can come from anyone, so the path it names only becomes an
+/// allowed source when a full fingerprint vouches for the file there
+fn vouches_for(source: &ProjectSource, path: &Path) -> bool {
+    source.file_size.is_some() && source.sha256.is_some() && matches_fingerprint(path, source)
+}
+
+/// Find a local source: where it was, next to the project (by relative path,
+/// then by name), then anywhere below the project's folder with the same size
+/// and hash
+pub fn locate_source(source: &ProjectSource, project_dir: Option<&Path>) -> Option<PathBuf> {
+    let original = PathBuf::from(&source.url);
+    if matches_fingerprint(&original, source) {
+        return Some(original);
+    }
+    let dir = project_dir?;
+    let mut candidates = Vec::new();
+    if let Some(relative) = &source.relative_path {
+        candidates.push(dir.join(relative));
+    }
+    if let Some(name) = original.file_name() {
+        candidates.push(dir.join(name));
+    }
+    if let Some(found) = candidates
+        .into_iter()
+        .find(|candidate| matches_fingerprint(candidate, source))
+    {
+        return Some(found);
+    }
+
+    // A name match is good enough above; a search needs the full fingerprint
+    let (Some(size), Some(_)) = (source.file_size, &source.sha256) else {
+        return None;
+    };
+    history::index_files(dir)
+        .remove(&size)?
+        .into_iter()
+        .find(|candidate| matches_fingerprint(candidate, source))
+}
+
+/// Point `project` at wherever its source is now. `own_file` is for the
+/// autosave, which the app wrote itself from an already allowed source.
+fn resolve_source(
+    app: &AppHandle,
+    project: &mut Project,
+    project_path: Option<&Path>,
+    own_file: bool,
+) -> Result<SourceStatus, String> {
+    if !is_local(&project.source.url) {
+        project.source.url = validation::validate_remote(&project.source.url)?;
+        return Ok(SourceStatus::Remote);
+    }
+    let Some(path) = locate_source(&project.source, project_path.and_then(Path::parent)) else {
+        return Ok(SourceStatus::Missing);
+    };
+    if own_file || vouches_for(&project.source, &path) {
+        validation::allow_local_path(app, &path);
+    } else if validation::validate_source(app, &path.to_string_lossy()).is_err() {
+        // Not fingerprinted and not opened before: the user relinks it by hand
+        return Ok(SourceStatus::Missing);
+    }
+    let path = path.to_string_lossy().to_string();
+    if path == project.source.url {
+        Ok(SourceStatus::Found)
+    } else {
+        let from = std::mem::replace(&mut project.source.url, path);
+        log::info!("Project source moved: {} -> {}", from, project.source.url);
+        Ok(SourceStatus::Relinked { from })
+    }
+}
+
+/// Move `entry` to the front of the list
+fn remember(mut recent: Vec<RecentProject>, entry: RecentProject) -> Vec<RecentProject> {
+    recent.retain(|r| r.path != entry.path);
+    recent.insert(0, entry);
+    recent.truncate(MAX_RECENT);
+    recent
+}
+
+fn config_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .app_config_dir()
+        .map_err(|e| format!("Failed to get config dir: {}", e))?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create config dir: {}", e))?;
+    Ok(dir)
+}
+
+fn read_recent(app: &AppHandle) -> Result<Vec<RecentProject>, String> {
+    let file = config_dir(app)?.join(RECENT_FILE);
+    let Ok(content) = fs::read_to_string(&file) else {
+        return Ok(Vec::new());
+    };
+    Ok(serde_json::from_str(&content).unwrap_or_default())
+}
+
+fn add_recent(app: &AppHandle, path: &Path, project: &Project) -> Result<(), String> {
+    let entry = RecentProject {
+        path: path.to_string_lossy().to_string(),
+        title: project.source.title.clone(),
+        opened_at: history::iso8601(SystemTime::now()),
+        exists: true,
+    };
+    let recent = remember(read_recent(app)?, entry);
+    let content = serde_json::to_string_pretty(&recent)
+        .map_err(|e| format!("Failed to serialize recent projects: {}", e))?;
+    fs::write(config_dir(app)?.join(RECENT_FILE), content)
+        .map_err(|e| format!("Failed to write recent projects: {}", e))
+}
+
+/// Paths from the frontend are only trusted if the user opened or saved them
+/// before; anything else goes through a dialog
+fn known_project(app: &AppHandle, path: &str) -> Result<PathBuf, String> {
+    if read_recent(app)?.iter().any(|r| r.path == path) {
+        Ok(PathBuf::from(path))
+    } else {
+        Err("Open the project from the file menu first".to_string())
+    }
+}
+
+fn with_extension(path: PathBuf) -> PathBuf {
+    let has_extension = path
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case(PROJECT_EXTENSION));
+    if has_extension {
+        path
+    } else {
+        let mut name = path.into_os_string();
+        name.push(format!(".{}", PROJECT_EXTENSION));
+        PathBuf::from(name)
+    }
+}
+
+/// Save the session. Without a `path` (or for "Save As") the user picks one.
+/// Returns where it was saved, or `None` if the dialog was cancelled.
+#[tauri::command]
+pub async fn save_project(
+    app: AppHandle,
+    mut project: Project,
+    path: Option<String>,
+) -> Result<Option<String>, String> {
+    // Only sources the user already opened get fingerprinted
+    validation::validate_source(&app, &project.source.url)?;
+
+    let path = match path {
+        Some(path) => known_project(&app, &path)?,
+        None => {
+            let picked = app
+                .dialog()
+                .file()
+                .add_filter("Clipme project", &[PROJECT_EXTENSION])
+                .set_file_name(format!("{}.{}", project.source.title, PROJECT_EXTENSION))
+                .blocking_save_file();
+            let Some(picked) = picked else {
+                return Ok(None);
+            };
+            with_extension(
+                picked
+                    .into_path()
+                    .map_err(|e| format!("Failed to read selected file: {}", e))?,
+            )
+        }
+    };
+
+    project.version = PROJECT_VERSION;
+    project.saved_at = Some(history::iso8601(SystemTime::now()));
+    fingerprint(&mut project.source, Some(&path), true);
+    write_project(&path, &project)?;
+    add_recent(&app, &path, &project)?;
+    log::info!("Saved project to {}", path.display());
+    Ok(Some(path.to_string_lossy().to_string()))
+}
+
+/// Open a project, from the recent list when `path` is given or else through a
+/// file picker. A local source that moved is looked for before reporting it missing.
+#[tauri::command]
+pub async fn open_project(
+    app: AppHandle,
+    path: Option<String>,
+) -> Result<Option<OpenedProject>, String> {
+    let path = match path {
+        Some(path) => known_project(&app, &path)?,
+        None => {
+            let picked = app
+                .dialog()
+                .file()
+                .add_filter("Clipme project", &[PROJECT_EXTENSION])
+                .blocking_pick_file();
+            let Some(picked) = picked else {
+                return Ok(None);
+            };
+            picked
+                .into_path()
+                .map_err(|e| format!("Failed to read selected file: {}", e))?
+        }
+    };
+
+    let content = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let mut project = parse_project(&content)?;
+    let source = resolve_source(&app, &mut project, Some(&path), false)?;
+    add_recent(&app, &path, &project)?;
+    Ok(Some(OpenedProject {
+        path: Some(path.to_string_lossy().to_string()),
+        project,
+        source,
+    }))
+}
+
+/// Let the user locate a missing local source
+#[tauri::command]
+pub async fn relink_project_source(
+    app: AppHandle,
+    mut source: ProjectSource,
+) -> Result<Option<RelinkedSource>, String> {
+    let name = Path::new(&source.url)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| source.title.clone());
+    let Some(path) = validation::pick_video(&app, Some(&format!("Locate {}", name)))? else {
+        return Ok(None);
+    };
+    let matches = matches_fingerprint(&path, &source);
+    source.url = path.to_string_lossy().to_string();
+    Ok(Some(RelinkedSource { source, matches }))
+}
+
+/// Keep the current session in the config dir so it survives a crash or quit
+#[tauri::command]
+pub async fn autosave_project(app: AppHandle, mut project: Project) -> Result<(), String> {
+    validation::validate_source(&app, &project.source.url)?;
+    project.version = PROJECT_VERSION;
+    project.saved_at = Some(history::iso8601(SystemTime::now()));
+    fingerprint(&mut project.source, None, false);
+    write_project(&config_dir(&app)?.join(AUTOSAVE_FILE), &project)
+}
+
+/// The autosaved session, if there is one
+#[tauri::command]
+pub async fn get_autosave(app: AppHandle) -> Result<Option<OpenedProject>, String> {
+    let file = config_dir(&app)?.join(AUTOSAVE_FILE);
+    let Ok(content) = fs::read_to_string(&file) else {
+        return Ok(None);
+    };
+    let mut project = match parse_project(&content) {
+        Ok(project) => project,
+        Err(e) => {
+            log::warn!("Ignoring unreadable autosave: {}", e);
+            return Ok(None);
+        }
+    };
+    let source = resolve_source(&app, &mut project, None, true)?;
+    Ok(Some(OpenedProject {
+        path: None,
+        project,
+        source,
+    }))
+}
+
+#[tauri::command]
+pub async fn discard_autosave(app: AppHandle) -> Result<(), String> {
+    let file = config_dir(&app)?.join(AUTOSAVE_FILE);
+    if file.exists() {
+        fs::remove_file(&file).map_err(|e| format!("Failed to remove autosave: {}", e))?;
+    }
+    Ok(())
+}
+
+/// Recently saved or opened projects, newest first
+#[tauri::command]
+pub async fn get_recent_projects(app: AppHandle) -> Result<Vec<RecentProject>, String> {
+    let mut recent = read_recent(&app)?;
+    for entry in &mut recent {
+        entry.exists = Path::new(&entry.path).is_file();
+    }
+    Ok(recent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("clipme-project-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn project(url: &str) -> Project {
+        serde_json::from_value(serde_json::json!({
+            "version": 1,
+            "source": { "url": url, "title": "Talk", "duration": 600 },
+            "segments": [
+                { "id": "a", "start": "0:10", "end": 20 },
+                { "id": "b", "start": 30, "end": 45.5, "label": "Q&A" }
+            ],
+            "quality": "1080p",
+            "format": "mp4",
+            "segment_options": { "b": { "fade": true } }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn round_trips_and_checks_the_version() {
+        let dir = temp_dir("version");
+        let path = dir.join("talk.clipme");
+        write_project(&path, &project("https://youtu.be/abc")).unwrap();
+        let opened = parse_project(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(opened.segments.len(), 2);
+        assert_eq!(opened.segments[0].start, 10.0);
+        assert_eq!(opened.segments[1].label.as_deref(), Some("Q&A"));
+        assert_eq!(opened.segment_options["b"]["fade"], true);
+        assert!(!dir.join("talk.clipme.tmp").exists());
+
+        let newer = r#"{"version": 99, "source": {"url": "x"}, "segments": []}"#;
+        assert!(parse_project(newer).unwrap_err().contains("newer version"));
+        assert!(parse_project(r#"{"segments": []}"#).is_err());
+        assert!(parse_project("not json").is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn finds_moved_sources() {
+        let dir = temp_dir("relink");
+        let media = dir.join("media");
+        fs::create_dir_all(&media).unwrap();
+        let original = media.join("talk.mp4");
+        fs::write(&original, b"video bytes").unwrap();
+        let project_path = dir.join("talk.clipme");
+
+        let mut source = project(&original.to_string_lossy()).source;
+        fingerprint(&mut source, Some(&project_path), true);
+        assert_eq!(source.file_size, Some(11));
+        assert_eq!(source.relative_path.as_deref(), Some("media/talk.mp4"));
+        assert_eq!(locate_source(&source, Some(&dir)), Some(original.clone()));
+        assert!(vouches_for(&source, &original));
+        // Without a hash the file is still found but not trusted on the project's word
+        let unhashed = ProjectSource {
+            sha256: None,
+            ..source.clone()
+        };
+        assert_eq!(locate_source(&unhashed, Some(&dir)), Some(original.clone()));
+        assert!(!vouches_for(&unhashed, &original));
+
+        // Renamed somewhere below the project: found by size and hash
+        let renamed = dir.join("archive").join("renamed.mp4");
+        fs::create_dir_all(renamed.parent().unwrap()).unwrap();
+        fs::rename(&original, &renamed).unwrap();
+        assert_eq!(locate_source(&source, Some(&dir)), Some(renamed.clone()));
+
+        // Same size but different content is not the same file
+        fs::write(&renamed, b"other bytes").unwrap();
+        assert_eq!(locate_source(&source, Some(&dir)), None);
+        assert!(!matches_fingerprint(&renamed, &source));
+
+        // Project and media moved together: the relative path still works
+        let moved = temp_dir("relink-moved");
+        fs::create_dir_all(moved.join("media")).unwrap();
+        fs::write(moved.join("media/talk.mp4"), b"video bytes").unwrap();
+        assert_eq!(
+            locate_source(&source, Some(&moved)),
+            Some(moved.join("media/talk.mp4"))
+        );
+        let _ = fs::remove_dir_all(&dir);
+        let _ = fs::remove_dir_all(&moved);
+    }
+
+    #[test]
+    fn keeps_recent_projects_unique_and_bounded() {
+        let entry = |path: &str| RecentProject {
+            path: path.to_string(),
+            title: String::new(),
+            opened_at: String::new(),
+            exists: true,
+        };
+        let mut recent = Vec::new();
+        for i in 0..12 {
+            recent = remember(recent, entry(&format!("/p/{}.clipme", i)));
+        }
+        recent = remember(recent, entry("/p/5.clipme"));
+        assert_eq!(recent.len(), MAX_RECENT);
+        assert_eq!(recent[0].path, "/p/5.clipme");
+        assert_eq!(recent.iter().filter(|r| r.path == "/p/5.clipme").count(), 1);
+        assert_eq!(recent[1].path, "/p/11.clipme");
+    }
+
+    #[test]
+    fn adds_the_project_extension() {
+        assert_eq!(
+            with_extension(PathBuf::from("/a/talk")),
+            PathBuf::from("/a/talk.clipme")
+        );
+        assert_eq!(
+            with_extension(PathBuf::from("/a/t.v2")),
+            PathBuf::from("/a/t.v2.clipme")
+        );
+        assert_eq!(
+            with_extension(PathBuf::from("/a/talk.CLIPME")),
+            PathBuf::from("/a/talk.CLIPME")
+        );
+    }
+}
@@ -0,0 +1,420 @@
+//! Timecode parsing and formatting shared by commands, deep links and imports.
+//!
+//! Accepted inputs:
+//! - plain seconds: `62.5`
+//! - clock time: `1:02:03`, `02:03.5`
+//! - unit time (YouTube/Twitch style): `1h2m3s`, `93s`, `2m`
+//! - SMPTE: `00:01:02:15` (non-drop) and `00:01:02;15` (drop-frame)
+//! - URL parameters: `?t=93`, `&start=62`, `#t=1m3s`
+
+use serde::{Deserialize, Deserializer};
+use std::fmt;
+
+/// Frame rate assumed for SMPTE input when the source's rate isn't known
+pub const DEFAULT_FPS: f64 = 30.0;
+/// NTSC rate drop-frame timecode is defined against
+const NTSC_FPS: f64 = 30000.0 / 1001.0;
+
+/// Parse any supported timecode into seconds. `fps` is only used for the frame
+/// field of SMPTE timecodes.
+pub fn parse_timecode(input: &str, fps: Option<f64>) -> Result<f64, String> {
+    let trimmed = input.trim();
+    let invalid = || format!("Invalid timecode: {}", input);
+    if trimmed.is_empty() {
+        return Err(invalid());
+    }
+
+    // A query string or a whole URL carrying `t=`/`start=`
+    if trimmed.contains('=') {
+        let value = time_param(trimmed).ok_or_else(invalid)?;
+        return parse_timecode(&value, fps);
+    }
+
+    let seconds = if trimmed.contains(';') || trimmed.matches(':').count() == 3 {
+        parse_smpte(trimmed, fps)
+    } else if trimmed.contains(':') {
+        parse_clock(trimmed)
+    } else if trimmed.ends_with(|c: char| c.is_ascii_alphabetic()) || trimmed.contains(['h', 'm']) {
+        parse_units(trimmed)
+    } else {
+        trimmed.parse::<f64>().ok()
+    };
+
+    seconds
+        .filter(|s| s.is_finite() && *s >= 0.0)
+        .ok_or_else(invalid)
+}
+
+/// Value of the first timing parameter in a `key=value&...` string
+fn time_param(params: &str) -> Option<String> {
+    params
+        .trim_start_matches(['?', '#', '&'])
+        .split(['&', '#', '?'])
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| matches!(*key, "t" | "start" | "time_continue"))
+        .map(|(_, value)| value.replace("%3A", ":").replace("%3a", ":"))
+        .filter(|value| !value.is_empty())
+}
+
+/// `H:MM:SS(.ss)` or `M:SS(.ss)`
+fn parse_clock(input: &str) -> Option<f64> {
+    let parts: Vec<&str> = input.split(':').collect();
+    let (hours, minutes, seconds) = match parts.as_slice() {
+        [m, s] => ("0", *m, *s),
+        [h, m, s] => (*h, *m, *s),
+        _ => return None,
+    };
+    let hours: u64 = hours.parse().ok()?;
+    let minutes: u64 = minutes.parse().ok()?;
+    let seconds: f64 = seconds.parse().ok()?;
+    // Only the leading field may overflow its unit (`90:00` is fine, `1:90:00` isn't)
+    if (parts.len() == 3 && minutes >= 60) || seconds >= 60.0 {
+        return None;
+    }
+    Some(hours as f64 * 3600.0 + minutes as f64 * 60.0 + seconds)
+}
+
+/// `1h2m3s`, `2m30s`, `93s`, `1m30` (trailing bare number is seconds)
+fn parse_units(input: &str) -> Option<f64> {
+    let mut total = 0.0;
+    let mut number = String::new();
+    let mut seen_unit = false;
+    for c in input.chars() {
+        if c.is_ascii_digit() || c == '.' {
+            number.push(c);
+            continue;
+        }
+        let multiplier = match c.to_ascii_lowercase() {
+            'h' => 3600.0,
+            'm' => 60.0,
+            's' => 1.0,
+            _ => return None,
+        };
+        total += number.parse::<f64>().ok()? * multiplier;
+        number.clear();
+        seen_unit = true;
+    }
+    if !number.is_empty() {
+        total += number.parse::<f64>().ok()?;
+    }
+    seen_unit.then_some(total)
+}
+
+/// Number of frames dropped per minute for a drop-frame rate (2 at 29.97, 4 at 59.94)
+fn dropped_frames(nominal: u64) -> u64 {
+    nominal / 15
+}
+
+fn parse_smpte(input: &str, fps: Option<f64>) -> Option<f64> {
+    let drop_frame = input.contains(';');
+    let parts: Vec<u64> = input
+        .split([':', ';'])
+        .map(|p| p.parse().ok())
+        .collect::<Option<_>>()?;
+    let [hours, minutes, seconds, frames] = parts.as_slice() else {
+        return None;
+    };
+    let fps = fps.unwrap_or(if drop_frame { NTSC_FPS } else { DEFAULT_FPS });
+    let nominal = fps.round() as u64;
+    if *minutes >= 60 || *seconds >= 60 || *frames >= nominal {
+        return None;
+    }
+
+    let mut frame_number = ((hours * 60 + minutes) * 60 + seconds) * nominal + frames;
+    if drop_frame {
+        let drop = dropped_frames(nominal);
+        // Frame numbers 0..drop don't exist at the start of minutes not divisible by 10
+        if *seconds == 0 && *frames < drop && minutes % 10 != 0 {
+            return None;
+        }
+        let total_minutes = hours * 60 + minutes;
+        frame_number -= drop * (total_minutes - total_minutes / 10);
+    }
+    Some(frames_to_seconds(frame_number, fps))
+}
+
+/// Index of the frame shown at `seconds`
+pub fn seconds_to_frames(seconds: f64, fps: f64) -> u64 {
+    (seconds * fps + 1e-6).floor().max(0.0) as u64
+}
+
+/// Start time of frame `frames`
+pub fn frames_to_seconds(frames: u64, fps: f64) -> f64 {
+    frames as f64 / fps
+}
+
+/// `1:02:03.5` or `2:03`, millisecond precision with trailing zeros dropped
+pub fn format_clock(seconds: f64) -> String {
+    let total_ms = (seconds.max(0.0) * 1000.0).round() as u64;
+    let (hours, rem) = (total_ms / 3_600_000, total_ms % 3_600_000);
+    let (minutes, rem) = (rem / 60_000, rem % 60_000);
+    let (secs, ms) = (rem / 1000, rem % 1000);
+
+    let fraction = if ms > 0 {
+        format!(".{:03}", ms).trim_end_matches('0').to_string()
+    } else {
+        String::new()
+    };
+    if hours > 0 {
+        format!("{}:{:02}:{:02}{}", hours, minutes, secs, fraction)
+    } else {
+        format!("{}:{:02}{}", minutes, secs, fraction)
+    }
+}
+
+/// `1h2m3s` style used by YouTube and Twitch links (whole seconds)
+pub fn format_units(seconds: f64) -> String {
+    let total = seconds.max(0.0).round() as u64;
+    let (hours, minutes, secs) = (total / 3600, total / 60 % 60, total % 60);
+    let mut out = String::new();
+    if hours > 0 {
+        out.push_str(&format!("{}h", hours));
+    }
+    if minutes > 0 {
+        out.push_str(&format!("{}m", minutes));
+    }
+    if secs > 0 || out.is_empty() {
+        out.push_str(&format!("{}s", secs));
+    }
+    out
+}
+
+/// `HH:MM:SS:FF`, or `HH:MM:SS;FF` when `drop_frame` is set (only meaningful
+/// for 29.97 and 59.94 fps)
+pub fn format_smpte(seconds: f64, fps: f64, drop_frame: bool) -> String {
+    let nominal = fps.round().max(1.0) as u64;
+    let mut frame_number = (seconds.max(0.0) * fps).round() as u64;
+
+    if drop_frame {
+        let drop = dropped_frames(nominal);
+        let per_minute = nominal * 60 - drop;
+        let per_ten_minutes = nominal * 600 - drop * 9;
+        let tens = frame_number / per_ten_minutes;
+        let rem = frame_number % per_ten_minutes;
+        frame_number += drop * 9 * tens;
+        if rem > drop {
+            frame_number += drop * ((rem - drop) / per_minute);
+        }
+    }
+
+    let frames = frame_number % nominal;
+    let total_secs = frame_number / nominal;
+    format!(
+        "{:02}:{:02}:{:02}{}{:02}",
+        total_secs / 3600,
+        total_secs / 60 % 60,
+        total_secs % 60,
+        if drop_frame { ';' } else { ':' },
+        frames
+    )
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TimecodeStyle {
+    #[default]
+    Clock,
+    Units,
+    Smpte,
+    SmpteDropFrame,
+    Frames,
+}
+
+/// Parse user-entered time so the frontend validates input the same way the backend does
+#[tauri::command]
+pub fn parse_time(input: String, fps: Option<f64>) -> Result<f64, String> {
+    parse_timecode(&input, fps)
+}
+
+#[tauri::command]
+pub fn format_time(seconds: f64, style: Option<TimecodeStyle>, fps: Option<f64>) -> String {
+    let fps = fps.filter(|f| *f > 0.0);
+    match style.unwrap_or_default() {
+        TimecodeStyle::Clock => format_clock(seconds),
+        TimecodeStyle::Units => format_units(seconds),
+        TimecodeStyle::Smpte => format_smpte(seconds, fps.unwrap_or(DEFAULT_FPS), false),
+        TimecodeStyle::SmpteDropFrame => format_smpte(seconds, fps.unwrap_or(NTSC_FPS), true),
+        TimecodeStyle::Frames => seconds_to_frames(seconds, fps.unwrap_or(DEFAULT_FPS)).to_string(),
+    }
+}
+
+/// A time that deserializes from either seconds (`62.5`) or any timecode string
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeValue(pub f64);
+
+impl<'de> Deserialize<'de> for TimeValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct TimeVisitor;
+
+        impl serde::de::Visitor<'_> for TimeVisitor {
+            type Value = TimeValue;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("seconds or a timecode string")
+            }
+
+            fn visit_f64<E: serde::de::Error>(self, v: f64) -> Result<TimeValue, E> {
+                Ok(TimeValue(v))
+            }
+
+            fn visit_i64<E: serde::de::Error>(self, v: i64) -> Result<TimeValue, E> {
+                Ok(TimeValue(v as f64))
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, v: u64) -> Result<TimeValue, E> {
+                Ok(TimeValue(v as f64))
+            }
+
+            fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<TimeValue, E> {
+                parse_timecode(v, None).map(TimeValue).map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_any(TimeVisitor)
+    }
+}
+
+/// `deserialize_with` helper for `f64` second fields
+pub fn deserialize_seconds<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    TimeValue::deserialize(deserializer).map(|t| t.0)
+}
+
+/// `deserialize_with` helper for `Option<f64>` second fields
+pub fn deserialize_optional_seconds<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<f64>, D::Error> {
+    Option::<TimeValue>::deserialize(deserializer).map(|t| t.map(|t| t.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> f64 {
+        parse_timecode(input, None).unwrap()
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-6
+    }
+
+    #[test]
+    fn parses_plain_seconds() {
+        assert_eq!(parse("62.5"), 62.5);
+        assert_eq!(parse(" 93 "), 93.0);
+        assert!(parse_timecode("-3", None).is_err());
+        assert!(parse_timecode("", None).is_err());
+    }
+
+    #[test]
+    fn parses_clock_time() {
+        assert_eq!(parse("1:02:03"), 3723.0);
+        assert_eq!(parse("02:03.5"), 123.5);
+        assert_eq!(parse("00:00:05.25"), 5.25);
+        assert_eq!(parse("90:00"), 5400.0);
+        assert!(parse_timecode("1:75:00", None).is_err());
+        assert!(parse_timecode("1:2:3:4:5", None).is_err());
+    }
+
+    #[test]
+    fn parses_unit_time() {
+        assert_eq!(parse("1h2m3s"), 3723.0);
+        assert_eq!(parse("93s"), 93.0);
+        assert_eq!(parse("2m"), 120.0);
+        assert_eq!(parse("1m30"), 90.0);
+        assert_eq!(parse("1.5s"), 1.5);
+        assert!(parse_timecode("1x", None).is_err());
+    }
+
+    #[test]
+    fn parses_url_parameters() {
+        assert_eq!(parse("?t=93"), 93.0);
+        assert_eq!(parse("t=1h2m3s"), 3723.0);
+        assert_eq!(parse("https://youtu.be/abc?t=93"), 93.0);
+        assert_eq!(parse("https://www.youtube.com/watch?v=abc&t=1m3s"), 63.0);
+        assert_eq!(parse("https://www.twitch.tv/videos/123?t=1h2m3s"), 3723.0);
+        assert_eq!(parse("https://example.com/v.mp4#t=12"), 12.0);
+        assert!(parse_timecode("https://www.youtube.com/watch?v=abc", None).is_err());
+    }
+
+    #[test]
+    fn parses_smpte_non_drop() {
+        assert!(close(
+            parse_timecode("00:01:02:15", Some(30.0)).unwrap(),
+            62.5
+        ));
+        assert!(close(
+            parse_timecode("00:00:01:12", Some(24.0)).unwrap(),
+            1.5
+        ));
+        assert!(parse_timecode("00:00:01:30", Some(30.0)).is_err());
+    }
+
+    #[test]
+    fn parses_smpte_drop_frame() {
+        // 00:01:00;02 is the first frame of minute one: 1800 frames minus 2 dropped
+        assert!(close(
+            parse("00:01:00;02"),
+            frames_to_seconds(1800, NTSC_FPS)
+        ));
+        // Ten-minute marks don't drop, so an hour is exactly 107892 frames
+        assert!(close(
+            parse("01:00:00;00"),
+            frames_to_seconds(107_892, NTSC_FPS)
+        ));
+        assert!(close(
+            parse("00:01:02;15"),
+            frames_to_seconds(1800 + 2 * 30 + 15 - 2, NTSC_FPS)
+        ));
+        // Dropped frame numbers don't exist
+        assert!(parse_timecode("00:01:00;00", None).is_err());
+    }
+
+    #[test]
+    fn drop_frame_round_trips() {
+        for frame in [0u64, 1, 1799, 1800, 17_981, 17_982, 107_891, 123_456] {
+            let seconds = frames_to_seconds(frame, NTSC_FPS);
+            let timecode = format_smpte(seconds, NTSC_FPS, true);
+            assert!(
+                close(parse(&timecode), seconds),
+                "{} -> {}",
+                frame,
+                timecode
+            );
+        }
+        assert_eq!(
+            format_smpte(frames_to_seconds(1800, NTSC_FPS), NTSC_FPS, true),
+            "00:01:00;02"
+        );
+    }
+
+    #[test]
+    fn formats_times() {
+        assert_eq!(format_clock(3723.0), "1:02:03");
+        assert_eq!(format_clock(123.5), "2:03.5");
+        assert_eq!(format_clock(59.9999), "1:00");
+        assert_eq!(format_units(3723.0), "1h2m3s");
+        assert_eq!(format_units(120.0), "2m");
+        assert_eq!(format_units(0.0), "0s");
+        assert_eq!(format_smpte(62.5, 30.0, false), "00:01:02:15");
+    }
+
+    #[test]
+    fn counts_frames() {
+        assert_eq!(seconds_to_frames(1.0, 29.97), 29);
+        assert_eq!(
+            seconds_to_frames(frames_to_seconds(1798, NTSC_FPS), NTSC_FPS),
+            1798
+        );
+        assert_eq!(seconds_to_frames(2.5, 24.0), 60);
+    }
+
+    #[test]
+    fn deserializes_numbers_and_strings() {
+        let values: Vec<TimeValue> =
+            serde_json::from_str(r#"[62.5, 93, "1:02:03", "1h2m3s"]"#).unwrap();
+        let seconds: Vec<f64> = values.into_iter().map(|t| t.0).collect();
+        assert_eq!(seconds, vec![62.5, 93.0, 3723.0, 3723.0]);
+        assert!(serde_json::from_str::<TimeValue>(r#""soon""#).is_err());
+    }
+}
@@ -0,0 +1,500 @@
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
+use tauri::{AppHandle, Manager};
+
+use crate::{
+    create_windowless_command, get_exe_extension, get_target_triple, read_app_settings,
+    ytdlp_update, AppSettings,
+};
+
+pub const TOOLS: &[&str] = &["yt-dlp", "ffmpeg", "ffprobe"];
+/// Written next to the sidecars by `scripts/write-binary-checksums.sh`
+const MANIFEST_NAME: &str = "checksums.sha256";
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BinarySource {
+    /// Path set by the user in settings
+    Custom,
+    /// Updated copy in the app data dir (yt-dlp only)
+    Managed,
+    Bundled,
+    System,
+}
+
+#[derive(Debug, Clone)]
+pub struct ResolvedBinary {
+    pub path: PathBuf,
+    pub source: BinarySource,
+    /// Hash matched the manifest or the recorded update
+    pub verified: bool,
+}
+
+/// A resolved tool plus the user's extra arguments for it
+#[derive(Debug, Clone)]
+pub struct Sidecar {
+    pub path: PathBuf,
+    pub extra_args: Vec<String>,
+}
+
+impl Sidecar {
+    /// Command with the extra arguments already applied, ahead of any the
+    /// caller adds (so they stay before yt-dlp's `--` separator)
+    pub fn command(&self) -> Command {
+        let mut cmd = create_windowless_command(&self.path);
+        cmd.args(&self.extra_args);
+        cmd
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct ToolInfo {
+    name: String,
+    path: Option<String>,
+    source: Option<BinarySource>,
+    version: Option<String>,
+    extra_args: Vec<String>,
+    sha256: Option<String>,
+    verified: bool,
+    error: Option<String>,
+}
+
+type HashKey = (PathBuf, u64, Option<SystemTime>);
+
+/// Hashes keyed by path, size and mtime so each binary is read once per session
+fn hash_cache() -> &'static Mutex<HashMap<HashKey, String>> {
+    static CACHE: OnceLock<Mutex<HashMap<HashKey, String>>> = OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+pub fn sha256_file(path: &Path) -> Result<String, String> {
+    let meta =
+        fs::metadata(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let key = (path.to_path_buf(), meta.len(), meta.modified().ok());
+    if let Some(hash) = hash_cache().lock().ok().and_then(|c| c.get(&key).cloned()) {
+        return Ok(hash);
+    }
+
+    let mut file =
+        fs::File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 1 << 16];
+    loop {
+        let read = file
+            .read(&mut buffer)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    let hash = hex::encode(hasher.finalize());
+
+    if let Ok(mut cache) = hash_cache().lock() {
+        cache.insert(key, hash.clone());
+    }
+    Ok(hash)
+}
+
+/// Parse `sha256sum` output into tool name -> hash. File names carry the target
+/// triple and extension (`ffmpeg-x86_64-pc-windows-msvc.exe`); both are stripped.
+fn parse_manifest(content: &str, triple: &str) -> HashMap<String, String> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let (hash, file) = line.trim().split_once(char::is_whitespace)?;
+            // `sha256sum -b` marks binary mode with a leading `*`
+            let file = file.trim_start().trim_start_matches('*');
+            let stem = file.strip_suffix(".exe").unwrap_or(file);
+            let name = stem.strip_suffix(&format!("-{}", triple))?;
+            Some((name.to_string(), hash.to_lowercase()))
+        })
+        .collect()
+}
+
+fn load_manifest(app: &AppHandle) -> Option<HashMap<String, String>> {
+    let path = app
+        .path()
+        .resolve(
+            format!("binaries/{}", MANIFEST_NAME),
+            tauri::path::BaseDirectory::Resource,
+        )
+        .ok()?;
+    let content = fs::read_to_string(path).ok()?;
+    Some(parse_manifest(&content, get_target_triple()))
+}
+
+/// Where a bundled sidecar can live: the resource dir during development, next
+/// to the executable in installed bundles
+fn bundled_candidates(app: &AppHandle, name: &str) -> Vec<PathBuf> {
+    let exe_ext = get_exe_extension();
+    let mut candidates = Vec::new();
+    if let Ok(path) = app.path().resolve(
+        format!("binaries/{}-{}{}", name, get_target_triple(), exe_ext),
+        tauri::path::BaseDirectory::Resource,
+    ) {
+        candidates.push(path);
+    }
+    if let Some(dir) = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf))
+    {
+        candidates.push(dir.join(format!("{}{}", name, exe_ext)));
+    }
+    candidates
+}
+
+fn find_in_path(file_name: &str) -> Option<PathBuf> {
+    let paths = std::env::var_os("PATH")?;
+    std::env::split_paths(&paths)
+        .map(|dir| dir.join(file_name))
+        .find(|candidate| candidate.is_file())
+}
+
+/// macOS bundling re-signs the sidecars after the manifest is written, which
+/// changes their bytes. A sidecar with an intact signature from the same team
+/// as the app itself is the signed artifact of the one the manifest lists.
+#[cfg(target_os = "macos")]
+fn signed_like_app(path: &Path) -> bool {
+    fn team_id(path: &Path) -> Option<String> {
+        let output = Command::new("codesign")
+            .args(["-dv", "--verbose=2"])
+            .arg(path)
+            .output()
+            .ok()?;
+        // codesign prints signature details on stderr
+        String::from_utf8_lossy(&output.stderr)
+            .lines()
+            .find_map(|line| line.strip_prefix("TeamIdentifier="))
+            .map(str::trim)
+            .filter(|team| *team != "not set")
+            .map(String::from)
+    }
+
+    let intact = Command::new("codesign")
+        .args(["--verify", "--strict"])
+        .arg(path)
+        .status()
+        .is_ok_and(|status| status.success());
+    let Some(app_team) = std::env::current_exe().ok().and_then(|exe| team_id(&exe)) else {
+        return false;
+    };
+    intact && team_id(path).is_some_and(|team| team == app_team)
+}
+
+#[cfg(not(target_os = "macos"))]
+fn signed_like_app(_path: &Path) -> bool {
+    false
+}
+
+/// Check a bundled binary against the manifest. Builds without a manifest
+/// (plain `cargo run`) are let through unverified.
+fn verify_bundled(app: &AppHandle, name: &str, path: &Path) -> Result<bool, String> {
+    let Some(manifest) = load_manifest(app) else {
+        log::warn!("No checksum manifest found, {} is unverified", name);
+        return Ok(false);
+    };
+    let expected = manifest
+        .get(name)
+        .ok_or_else(|| format!("Bundled {} is not listed in the checksum manifest", name))?;
+    let actual = sha256_file(path)?;
+    if &actual != expected {
+        // Keyed by hash so codesign runs once per binary rather than per command
+        static SIGNED: OnceLock<Mutex<HashMap<String, bool>>> = OnceLock::new();
+        let cache = SIGNED.get_or_init(|| Mutex::new(HashMap::new()));
+        let cached = cache.lock().ok().and_then(|c| c.get(&actual).copied());
+        let signed = cached.unwrap_or_else(|| {
+            let signed = signed_like_app(path);
+            if let Ok(mut cache) = cache.lock() {
+                cache.insert(actual.clone(), signed);
+            }
+            signed
+        });
+        if signed {
+            log::debug!("Bundled {} was re-signed, verified by its code signature", name);
+            return Ok(true);
+        }
+        return Err(format!(
+            "Bundled {} at {} failed verification (expected SHA-256 {}, found {}). Reinstall Clipme to restore it.",
+            name,
+            path.display(),
+            expected,
+            actual
+        ));
+    }
+    Ok(true)
+}
+
+fn custom_path(settings: &AppSettings, name: &str) -> Option<PathBuf> {
+    settings
+        .tool_paths
+        .get(name)
+        .map(|path| path.trim())
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Extra arguments configured for `name`
+pub fn extra_args(app: &AppHandle, name: &str) -> Vec<String> {
+    read_app_settings(app)
+        .ok()
+        .and_then(|mut settings| settings.tool_args.remove(name))
+        .unwrap_or_default()
+}
+
+/// Find a tool: the path set in settings, then an updated yt-dlp from the app
+/// data dir, then the bundled sidecar if present (and intact), otherwise the
+/// first match on `PATH`
+pub fn resolve_binary(app: &AppHandle, name: &str) -> Result<ResolvedBinary, String> {
+    if let Some(path) = read_app_settings(app)
+        .ok()
+        .and_then(|settings| custom_path(&settings, name))
+    {
+        if !path.is_file() {
+            return Err(format!(
+                "Custom {} not found at {}. Fix or clear it in settings.",
+                name,
+                path.display()
+            ));
+        }
+        log::debug!("Using custom binary: {:?}", path);
+        return Ok(ResolvedBinary {
+            path,
+            source: BinarySource::Custom,
+            verified: false,
+        });
+    }
+    resolve_default(app, name)
+}
+
+fn resolve_default(app: &AppHandle, name: &str) -> Result<ResolvedBinary, String> {
+    if name == "yt-dlp" {
+        if let Some(path) = ytdlp_update::managed_binary(app)? {
+            log::debug!("Using updated binary: {:?}", path);
+            return Ok(ResolvedBinary {
+                path,
+                source: BinarySource::Managed,
+                verified: true,
+            });
+        }
+    }
+
+    if let Some(path) = bundled_candidates(app, name)
+        .into_iter()
+        .find(|p| p.is_file())
+    {
+        let verified = verify_bundled(app, name, &path)?;
+        log::debug!("Using bundled binary: {:?}", path);
+        return Ok(ResolvedBinary {
+            path,
+            source: BinarySource::Bundled,
+            verified,
+        });
+    }
+
+    let system_name = format!("{}{}", name, get_exe_extension());
+    let path = find_in_path(&system_name).ok_or_else(|| {
+        format!(
+            "{} not found: the bundled copy is missing and it is not on PATH",
+            name
+        )
+    })?;
+    log::warn!(
+        "Bundled binary not found, falling back to system: {:?}",
+        path
+    );
+    Ok(ResolvedBinary {
+        path,
+        source: BinarySource::System,
+        verified: false,
+    })
+}
+
+/// First line of the tool's version output
+pub fn binary_version(name: &str, path: &Path) -> Result<String, String> {
+    version_with_args(name, path, &[])
+}
+
+fn version_with_args(name: &str, path: &Path, extra_args: &[String]) -> Result<String, String> {
+    let flag = if name == "yt-dlp" {
+        "--version"
+    } else {
+        "-version"
+    };
+    let output = create_windowless_command(path)
+        .args(extra_args)
+        .arg(flag)
+        .output()
+        .map_err(|e| format!("Failed to run {}: {}", name, e))?;
+    if !output.status.success() {
+        return Err(format!("{} {} exited with {}", name, flag, output.status));
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .next()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .ok_or_else(|| format!("{} printed no version", name))
+}
+
+/// Syntax checks for extra arguments; running them is left to the version check
+fn check_extra_args(name: &str, args: &[String]) -> Result<(), String> {
+    for arg in args {
+        if arg.trim().is_empty() || arg.chars().any(char::is_control) {
+            return Err(format!("Invalid extra argument for {}: {:?}", name, arg));
+        }
+        // A bare `--` would turn everything after it, our options included,
+        // into URLs for yt-dlp
+        if arg == "--" {
+            return Err(format!("Extra arguments for {} can't contain '--'", name));
+        }
+    }
+    Ok(())
+}
+
+/// Check tool overrides before settings are saved: every override must name a
+/// known tool, and the binary must answer its version command with the extra
+/// arguments applied
+pub fn validate_tool_settings(app: &AppHandle, settings: &AppSettings) -> Result<(), String> {
+    for name in settings.tool_paths.keys().chain(settings.tool_args.keys()) {
+        if !TOOLS.contains(&name.as_str()) {
+            return Err(format!("Unknown tool: {}", name));
+        }
+    }
+
+    for name in TOOLS {
+        let custom = custom_path(settings, name);
+        let args = settings.tool_args.get(*name).cloned().unwrap_or_default();
+        if custom.is_none() && args.is_empty() {
+            continue;
+        }
+        check_extra_args(name, &args)?;
+        let path = match custom {
+            Some(path) => {
+                if !path.is_absolute() {
+                    return Err(format!("Custom {} path must be absolute", name));
+                }
+                if !path.is_file() {
+                    return Err(format!("Custom {} not found at {}", name, path.display()));
+                }
+                path
+            }
+            None => resolve_default(app, name)?.path,
+        };
+        let version = version_with_args(name, &path, &args).map_err(|e| {
+            format!(
+                "{} at {} failed its version check: {}",
+                name,
+                path.display(),
+                e
+            )
+        })?;
+        log::debug!("Validated {} override: {}", name, version);
+    }
+    Ok(())
+}
+
+/// Version output by binary, so recording a job doesn't spawn three processes
+fn version_cache() -> &'static Mutex<HashMap<HashKey, String>> {
+    static CACHE: OnceLock<Mutex<HashMap<HashKey, String>>> = OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Version of each tool that would run now; tools that can't be found or
+/// started are left out
+pub fn tool_versions(app: &AppHandle) -> BTreeMap<String, String> {
+    TOOLS
+        .iter()
+        .filter_map(|name| {
+            let path = resolve_binary(app, name).ok()?.path;
+            let meta = fs::metadata(&path).ok()?;
+            let key = (path.clone(), meta.len(), meta.modified().ok());
+            let cached = version_cache()
+                .lock()
+                .ok()
+                .and_then(|cache| cache.get(&key).cloned());
+            let version = match cached {
+                Some(version) => version,
+                None => {
+                    let version = binary_version(name, &path).ok()?;
+                    if let Ok(mut cache) = version_cache().lock() {
+                        cache.insert(key, version.clone());
+                    }
+                    version
+                }
+            };
+            Some((name.to_string(), version))
+        })
+        .collect()
+}
+
+fn tool_info(app: &AppHandle, name: &str) -> ToolInfo {
+    let mut info = ToolInfo {
+        name: name.to_string(),
+        path: None,
+        source: None,
+        version: None,
+        extra_args: extra_args(app, name),
+        sha256: None,
+        verified: false,
+        error: None,
+    };
+    let resolved = match resolve_binary(app, name) {
+        Ok(resolved) => resolved,
+        Err(e) => {
+            info.error = Some(e);
+            return info;
+        }
+    };
+    info.path = Some(resolved.path.to_string_lossy().to_string());
+    info.source = Some(resolved.source);
+    info.verified = resolved.verified;
+    info.sha256 = sha256_file(&resolved.path).ok();
+    match version_with_args(name, &resolved.path, &info.extra_args) {
+        Ok(version) => info.version = Some(version),
+        Err(e) => info.error = Some(e),
+    }
+    info
+}
+
+/// Path, origin, version and hash of each sidecar tool
+#[tauri::command]
+pub async fn get_toolchain_info(app: AppHandle) -> Vec<ToolInfo> {
+    TOOLS.iter().map(|name| tool_info(&app, name)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_sha256sum_manifest() {
+        let manifest = parse_manifest(
+            "ABC123  yt-dlp-x86_64-pc-windows-msvc.exe\n\
+             def456 *ffmpeg-x86_64-pc-windows-msvc.exe\n\
+             0000  ffprobe-aarch64-apple-darwin\n\
+             \n",
+            "x86_64-pc-windows-msvc",
+        );
+        assert_eq!(manifest.get("yt-dlp").map(String::as_str), Some("abc123"));
+        assert_eq!(manifest.get("ffmpeg").map(String::as_str), Some("def456"));
+        // Other targets' binaries are ignored
+        assert!(!manifest.contains_key("ffprobe"));
+    }
+
+    #[test]
+    fn rejects_unsafe_extra_args() {
+        let args = |list: &[&str]| list.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+        assert!(check_extra_args("ffmpeg", &args(&["-hwaccel", "cuda"])).is_ok());
+        assert!(check_extra_args("yt-dlp", &args(&["--", "x"])).is_err());
+        assert!(check_extra_args("yt-dlp", &args(&[""])).is_err());
+        assert!(check_extra_args("yt-dlp", &args(&["a\nb"])).is_err());
+    }
+}
@@ -0,0 +1,276 @@
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager, Url};
+use tauri_plugin_dialog::DialogExt;
+
+use crate::{resolve_output_dir, AppState};
+
+/// Only web URLs are handed to yt-dlp; its other "URLs" (`ytsearch:`, `file://`)
+/// can reach the network or disk in ways the user didn't ask for
+const REMOTE_SCHEMES: &[&str] = &["http", "https"];
+const VIDEO_EXTENSIONS: &[&str] = &["mp4", "mov", "avi", "mkv", "webm"];
+
+/// A checked input. Local paths are canonical and absolute, so they can never
+/// be read as an option or a protocol prefix by ffmpeg/ffprobe.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MediaSource {
+    Remote(String),
+    Local(PathBuf),
+}
+
+impl MediaSource {
+    pub fn is_local(&self) -> bool {
+        matches!(self, MediaSource::Local(_))
+    }
+
+    /// The string passed to the sidecars
+    pub fn as_arg(&self) -> String {
+        match self {
+            MediaSource::Remote(url) => url.clone(),
+            MediaSource::Local(path) => path.to_string_lossy().to_string(),
+        }
+    }
+}
+
+/// Syntax-only check: a web URL or an absolute path (not yet resolved)
+fn classify(input: &str) -> Result<MediaSource, String> {
+    let trimmed = input.trim();
+    if trimmed.is_empty() {
+        return Err("Enter a URL or choose a file".to_string());
+    }
+    if trimmed.starts_with('-') {
+        return Err("Input can't start with '-'".to_string());
+    }
+    if trimmed.chars().any(char::is_control) {
+        return Err("Input contains control characters".to_string());
+    }
+
+    // Single-letter "schemes" are Windows drive letters (C:\...)
+    if let Some(url) = Url::parse(trimmed).ok().filter(|u| u.scheme().len() > 1) {
+        if !REMOTE_SCHEMES.contains(&url.scheme()) {
+            return Err(format!("Unsupported URL scheme: {}", url.scheme()));
+        }
+        if url.host_str().is_none_or(str::is_empty) {
+            return Err("URL has no host".to_string());
+        }
+        return Ok(MediaSource::Remote(trimmed.to_string()));
+    }
+
+    let path = Path::new(trimmed);
+    if !path.is_absolute() {
+        return Err("Not a valid URL or file path".to_string());
+    }
+    Ok(MediaSource::Local(path.to_path_buf()))
+}
+
+/// Canonicalize `path` and require it to sit inside one of `roots`.
+/// Symlinks and `..` are resolved first so they can't escape a root. `dunce`
+/// avoids Windows' `\\?\C:\...` form, which ffmpeg and yt-dlp mishandle.
+fn resolve_local(path: &Path, roots: &[PathBuf]) -> Result<PathBuf, String> {
+    let canonical = dunce::canonicalize(path)
+        .map_err(|_| format!("File not found: {}", path.display()))?;
+    if !canonical.is_file() {
+        return Err(format!("Not a file: {}", path.display()));
+    }
+    let allowed = roots.iter().any(|root| {
+        dunce::canonicalize(root).is_ok_and(|root| canonical.starts_with(&root))
+    });
+    if !allowed {
+        return Err(
+            "Open local files with the file picker or drag them onto the window".to_string(),
+        );
+    }
+    Ok(canonical)
+}
+
+/// A drive root, the home folder or anything above it would open up every
+/// file on the disk. `dir` and `home` must be canonical.
+fn is_broad_root(dir: &Path, home: Option<&Path>) -> bool {
+    dir.parent().is_none() || home.is_some_and(|home| home.starts_with(dir))
+}
+
+/// The download folder, unless it is set to somewhere as broad as a drive or
+/// the home folder. It can be any path, so it is only trusted when narrow.
+fn output_root(app: &AppHandle, state: &AppState) -> Option<PathBuf> {
+    let dir = dunce::canonicalize(resolve_output_dir(app, state).ok()?).ok()?;
+    let home = app
+        .path()
+        .home_dir()
+        .ok()
+        .and_then(|home| dunce::canonicalize(home).ok());
+    if is_broad_root(&dir, home.as_deref()) {
+        log::warn!(
+            "Not reading sources from download folder {}, it is too broad",
+            dir.display()
+        );
+        return None;
+    }
+    Some(dir)
+}
+
+/// Files and folders the user chose, plus our own output and cache folders
+fn allowed_roots(app: &AppHandle) -> Vec<PathBuf> {
+    let state = app.state::<AppState>();
+    let mut roots = state
+        .allowed_local_paths
+        .lock()
+        .map(|paths| paths.clone())
+        .unwrap_or_default();
+    roots.extend(output_root(app, &state));
+    if let Ok(dir) = app.path().app_cache_dir() {
+        roots.push(dir);
+    }
+    roots
+}
+
+/// Validate any user-supplied URL or path before it reaches a sidecar
+pub fn validate_source(app: &AppHandle, input: &str) -> Result<MediaSource, String> {
+    match classify(input)? {
+        MediaSource::Local(path) => {
+            resolve_local(&path, &allowed_roots(app)).map(MediaSource::Local)
+        }
+        remote => Ok(remote),
+    }
+}
+
+/// Like `validate_source` for commands that only make sense for web URLs
+pub fn validate_remote(input: &str) -> Result<String, String> {
+    match classify(input)? {
+        MediaSource::Remote(url) => Ok(url),
+        MediaSource::Local(_) => Err("Expected a web URL".to_string()),
+    }
+}
+
+/// Remember a file or folder the user picked or dropped
+pub fn allow_local_path(app: &AppHandle, path: &Path) {
+    let Ok(canonical) = dunce::canonicalize(path) else {
+        return;
+    };
+    let state = app.state::<AppState>();
+    if let Ok(mut paths) = state.allowed_local_paths.lock() {
+        if !paths.contains(&canonical) {
+            paths.push(canonical);
+        }
+    };
+}
+
+/// yt-dlp arguments with the URL after `--`, so nothing in it is parsed as an option
+pub fn ytdlp_args<I, S>(options: I, url: &str) -> Vec<String>
+where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+{
+    let mut args: Vec<String> = options.into_iter().map(Into::into).collect();
+    args.push("--".to_string());
+    args.push(url.to_string());
+    args
+}
+
+/// Native file picker for local videos; the chosen file becomes an allowed source
+pub fn pick_video(app: &AppHandle, title: Option<&str>) -> Result<Option<PathBuf>, String> {
+    let mut dialog = app.dialog().file().add_filter("Video", VIDEO_EXTENSIONS);
+    if let Some(title) = title {
+        dialog = dialog.set_title(title);
+    }
+    let Some(picked) = dialog.blocking_pick_file() else {
+        return Ok(None);
+    };
+    let path = picked
+        .into_path()
+        .map_err(|e| format!("Failed to read selected file: {}", e))?;
+    allow_local_path(app, &path);
+    Ok(Some(path))
+}
+
+#[tauri::command]
+pub async fn pick_local_video(app: AppHandle) -> Result<Option<String>, String> {
+    Ok(pick_video(&app, None)?.map(|path| path.to_string_lossy().to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn rejects_option_like_input() {
+        for input in ["--exec rm -rf ~", "-o/tmp/x", " -f best", "--", "-"] {
+            assert!(classify(input).is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn only_accepts_web_urls() {
+        assert_eq!(
+            classify("https://youtu.be/dQw4w9WgXcQ"),
+            Ok(MediaSource::Remote(
+                "https://youtu.be/dQw4w9WgXcQ".to_string()
+            ))
+        );
+        for input in [
+            "ytsearch:never gonna",
+            "ytsearch5:x",
+            "file:///etc/passwd",
+            "ftp://example.com/a.mp4",
+            "javascript:alert(1)",
+            "https://",
+            "relative/path.mp4",
+            "https://example.com/\nvideo",
+        ] {
+            assert!(classify(input).is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn local_paths_must_be_inside_allowed_roots() {
+        let base = std::env::temp_dir().join(format!("clipme-validation-{}", std::process::id()));
+        let allowed = base.join("allowed");
+        let other = base.join("other");
+        fs::create_dir_all(&allowed).unwrap();
+        fs::create_dir_all(&other).unwrap();
+        let inside = allowed.join("clip.mp4");
+        let outside = other.join("secret.mp4");
+        fs::write(&inside, b"x").unwrap();
+        fs::write(&outside, b"x").unwrap();
+        let roots = vec![allowed.clone()];
+
+        assert_eq!(
+            resolve_local(&inside, &roots),
+            Ok(dunce::canonicalize(&inside).unwrap())
+        );
+        assert!(resolve_local(&outside, &roots).is_err());
+        // `..` can't climb out of a root
+        assert!(resolve_local(&allowed.join("../other/secret.mp4"), &roots).is_err());
+        assert!(resolve_local(&allowed.join("missing.mp4"), &roots).is_err());
+        assert!(resolve_local(&allowed, &roots).is_err());
+
+        fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn drives_and_home_are_too_broad() {
+        let home = Path::new("/home/ada");
+        assert!(is_broad_root(Path::new("/"), Some(home)));
+        assert!(is_broad_root(Path::new("/home"), Some(home)));
+        assert!(is_broad_root(home, Some(home)));
+        assert!(!is_broad_root(
+            Path::new("/home/ada/Downloads/Clipme"),
+            Some(home)
+        ));
+        assert!(!is_broad_root(Path::new("/media/videos"), Some(home)));
+        assert!(!is_broad_root(Path::new("/media/videos"), None));
+    }
+
+    #[test]
+    fn url_always_follows_separator() {
+        let args = ytdlp_args(["--dump-json", "--no-playlist"], "--exec=touch /tmp/pwned");
+        assert_eq!(
+            args,
+            vec![
+                "--dump-json",
+                "--no-playlist",
+                "--",
+                "--exec=touch /tmp/pwned"
+            ]
+        );
+    }
+}
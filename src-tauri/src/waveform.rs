@@ -0,0 +1,320 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::UNIX_EPOCH;
+use tauri::{AppHandle, Manager};
+
+use crate::{get_ffmpeg, preview, validation};
+
+/// Audio is decoded to mono at this rate; plenty for drawing and silence detection
+const SAMPLE_RATE: u32 = 8000;
+/// Finest zoom level: one min/max pair per 10 ms
+const BASE_SAMPLES_PER_PEAK: usize = 80;
+/// Coarser levels halve the resolution until they drop below this many peaks
+const MIN_PEAKS_PER_LEVEL: usize = 512;
+const MAX_LEVELS: usize = 12;
+
+/// Anything quieter than -40 dBFS counts as silence
+const SILENCE_THRESHOLD: i16 = 328;
+const MIN_SILENCE_SECS: f64 = 0.3;
+
+/// Least recently used waveforms are evicted beyond this
+const CACHE_MAX_BYTES: u64 = 64 * 1024 * 1024;
+
+/// One zoom level. `peaks` interleaves min and max per bucket
+/// (`[min0, max0, min1, max1, ...]`) scaled to the i8 range.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WaveformLevel {
+    seconds_per_peak: f64,
+    peaks: Vec<i8>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SilenceRange {
+    start: f64,
+    end: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WaveformData {
+    source: String,
+    duration: f64,
+    levels: Vec<WaveformLevel>,
+    /// Quiet stretches that cut points can snap to
+    silences: Vec<SilenceRange>,
+}
+
+fn waveform_cache_path(app: &AppHandle, source: &Path) -> Result<PathBuf, String> {
+    // Key on size and mtime too so a replaced file is decoded again
+    let meta = fs::metadata(source).map_err(|e| format!("Failed to read source: {}", e))?;
+    let modified = meta
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    let mut hasher = Sha256::new();
+    hasher.update(source.to_string_lossy().as_bytes());
+    hasher.update([0]);
+    hasher.update(meta.len().to_le_bytes());
+    hasher.update(modified.to_le_bytes());
+
+    let dir = app
+        .path()
+        .app_cache_dir()
+        .map_err(|e| format!("Failed to get cache dir: {}", e))?
+        .join("waveforms");
+    if !dir.exists() {
+        fs::create_dir_all(&dir).map_err(|e| format!("Failed to create waveform dir: {}", e))?;
+    }
+    Ok(dir.join(format!("{}.json", hex::encode(&hasher.finalize()[..8]))))
+}
+
+fn to_i8(sample: i16) -> i8 {
+    (sample >> 8) as i8
+}
+
+/// Min/max buckets at full i16 precision for the finest level
+fn base_buckets(samples: &[i16]) -> Vec<(i16, i16)> {
+    samples
+        .chunks(BASE_SAMPLES_PER_PEAK)
+        .map(|chunk| {
+            let min = chunk.iter().copied().min().unwrap_or(0);
+            let max = chunk.iter().copied().max().unwrap_or(0);
+            (min, max)
+        })
+        .collect()
+}
+
+/// Build every zoom level by repeatedly merging neighbouring buckets
+fn build_levels(base: &[(i16, i16)]) -> Vec<WaveformLevel> {
+    let mut levels = Vec::new();
+    let mut current: Vec<(i16, i16)> = base.to_vec();
+    let mut seconds_per_peak = BASE_SAMPLES_PER_PEAK as f64 / SAMPLE_RATE as f64;
+
+    loop {
+        levels.push(WaveformLevel {
+            seconds_per_peak,
+            peaks: current
+                .iter()
+                .flat_map(|(min, max)| [to_i8(*min), to_i8(*max)])
+                .collect(),
+        });
+        if current.len() / 2 < MIN_PEAKS_PER_LEVEL || levels.len() >= MAX_LEVELS {
+            break;
+        }
+        current = current
+            .chunks(2)
+            .map(|pair| {
+                let min = pair.iter().map(|(min, _)| *min).min().unwrap_or(0);
+                let max = pair.iter().map(|(_, max)| *max).max().unwrap_or(0);
+                (min, max)
+            })
+            .collect();
+        seconds_per_peak *= 2.0;
+    }
+    levels
+}
+
+/// Runs of quiet buckets at least `MIN_SILENCE_SECS` long
+fn find_silences(base: &[(i16, i16)]) -> Vec<SilenceRange> {
+    let bucket_secs = BASE_SAMPLES_PER_PEAK as f64 / SAMPLE_RATE as f64;
+    let is_quiet = |(min, max): &(i16, i16)| {
+        min.saturating_abs() < SILENCE_THRESHOLD && max.saturating_abs() < SILENCE_THRESHOLD
+    };
+
+    let mut silences = Vec::new();
+    let mut run_start: Option<usize> = None;
+    for (index, bucket) in base.iter().enumerate() {
+        match (is_quiet(bucket), run_start) {
+            (true, None) => run_start = Some(index),
+            (false, Some(start)) => {
+                let range = SilenceRange {
+                    start: start as f64 * bucket_secs,
+                    end: index as f64 * bucket_secs,
+                };
+                if range.end - range.start >= MIN_SILENCE_SECS {
+                    silences.push(range);
+                }
+                run_start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(start) = run_start {
+        let end = base.len() as f64 * bucket_secs;
+        if end - start as f64 * bucket_secs >= MIN_SILENCE_SECS {
+            silences.push(SilenceRange {
+                start: start as f64 * bucket_secs,
+                end,
+            });
+        }
+    }
+    silences
+}
+
+/// Decode the first audio stream to mono 16-bit PCM
+fn decode_audio(app: &AppHandle, source: &Path) -> Result<Vec<i16>, String> {
+    let mut child = get_ffmpeg(app)?
+        .command()
+        .args(["-v", "error", "-i"])
+        .arg(source)
+        .args([
+            "-vn",
+            "-ac",
+            "1",
+            "-ar",
+            &SAMPLE_RATE.to_string(),
+            "-f",
+            "s16le",
+            "-acodec",
+            "pcm_s16le",
+            "-",
+        ])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to start ffmpeg: {}", e))?;
+
+    let mut bytes = Vec::new();
+    if let Some(mut stdout) = child.stdout.take() {
+        stdout
+            .read_to_end(&mut bytes)
+            .map_err(|e| format!("Failed to read decoded audio: {}", e))?;
+    }
+    let status = child
+        .wait()
+        .map_err(|e| format!("Failed to wait on ffmpeg: {}", e))?;
+    if !status.success() || bytes.is_empty() {
+        return Err("Failed to decode audio (does the source have an audio track?)".to_string());
+    }
+
+    Ok(bytes
+        .chunks_exact(2)
+        .map(|pair| i16::from_le_bytes([pair[0], pair[1]]))
+        .collect())
+}
+
+/// Compute (or load from cache) waveform peaks for a local file or downloaded preview
+#[tauri::command]
+pub async fn get_waveform(
+    app: AppHandle,
+    source: String,
+    force_refresh: Option<bool>,
+) -> Result<WaveformData, String> {
+    let source = validation::validate_source(&app, &source)?.as_arg();
+    let source_path = Path::new(&source);
+    if !source_path.is_file() {
+        return Err("Waveforms need a local file or downloaded preview".to_string());
+    }
+
+    let cache_path = waveform_cache_path(&app, source_path)?;
+    if !force_refresh.unwrap_or(false) {
+        if let Some(cached) = fs::read_to_string(&cache_path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<WaveformData>(&content).ok())
+        {
+            preview::touch(&cache_path);
+            return Ok(cached);
+        }
+    }
+
+    log::debug!("Computing waveform for {}", source);
+
+    let samples = decode_audio(&app, source_path)?;
+    let base = base_buckets(&samples);
+    let data = WaveformData {
+        source,
+        duration: samples.len() as f64 / SAMPLE_RATE as f64,
+        levels: build_levels(&base),
+        silences: find_silences(&base),
+    };
+
+    let json_str = serde_json::to_string(&data)
+        .map_err(|e| format!("Failed to serialize waveform: {}", e))?;
+    fs::write(&cache_path, json_str).map_err(|e| format!("Failed to write waveform: {}", e))?;
+    if let Some(dir) = cache_path.parent() {
+        preview::enforce_cache_limit(dir, CACHE_MAX_BYTES, &cache_path);
+    }
+
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `secs` of a square wave at `amplitude`, or silence for 0
+    fn tone(secs: f64, amplitude: i16) -> Vec<i16> {
+        let count = (secs * SAMPLE_RATE as f64).round() as usize;
+        (0..count)
+            .map(|i| if i % 2 == 0 { amplitude } else { -amplitude })
+            .collect()
+    }
+
+    #[test]
+    fn buckets_keep_min_and_max() {
+        let mut samples = vec![0; BASE_SAMPLES_PER_PEAK];
+        samples[10] = -12000;
+        samples[70] = 9000;
+        samples.extend([5, 7, 6]);
+        assert_eq!(base_buckets(&samples), vec![(-12000, 9000), (5, 7)]);
+        assert!(base_buckets(&[]).is_empty());
+    }
+
+    #[test]
+    fn levels_halve_until_the_minimum() {
+        let base: Vec<(i16, i16)> = (0..MIN_PEAKS_PER_LEVEL as i16 * 4)
+            .map(|i| (-i, i))
+            .collect();
+        let levels = build_levels(&base);
+
+        assert_eq!(levels.len(), 3);
+        let counts: Vec<usize> = levels.iter().map(|l| l.peaks.len() / 2).collect();
+        assert_eq!(
+            counts,
+            vec![
+                MIN_PEAKS_PER_LEVEL * 4,
+                MIN_PEAKS_PER_LEVEL * 2,
+                MIN_PEAKS_PER_LEVEL
+            ]
+        );
+        assert_eq!(levels[1].seconds_per_peak, levels[0].seconds_per_peak * 2.0);
+        // Merged buckets span both halves of each pair
+        let last = base.len() as i16 - 1;
+        assert_eq!(&levels[1].peaks[..2], &[to_i8(-1), to_i8(1)]);
+        assert_eq!(
+            &levels[2].peaks[levels[2].peaks.len() - 2..],
+            &[to_i8(-last), to_i8(last)]
+        );
+
+        let short = build_levels(&[(-256, 256); 10]);
+        assert_eq!(short.len(), 1);
+        assert_eq!(short[0].peaks, [-1, 1].repeat(10));
+    }
+
+    #[test]
+    fn finds_long_quiet_runs() {
+        let samples = [
+            tone(1.0, 10000),
+            tone(0.5, 0),
+            tone(1.0, 10000),
+            tone(0.1, 100),
+            tone(1.0, 10000),
+            tone(0.4, SILENCE_THRESHOLD - 1),
+        ]
+        .concat();
+        let silences = find_silences(&base_buckets(&samples));
+
+        // The 0.1 s gap is too short to count
+        let ranges: Vec<(f64, f64)> = silences
+            .iter()
+            .map(|s| ((s.start * 100.0).round(), (s.end * 100.0).round()))
+            .collect();
+        assert_eq!(ranges, vec![(100.0, 150.0), (360.0, 400.0)]);
+        assert!(find_silences(&base_buckets(&tone(2.0, SILENCE_THRESHOLD))).is_empty());
+    }
+}
@@ -25,6 +25,20 @@ import { onOpenUrl } from '@tauri-apps/plugin-deep-link';
 import { useAuth } from './contexts/AuthContext';
 import { AuthModal } from './components/AuthModal';
 import { account, client } from './lib/appwrite';
+import {
+  Project,
+  OpenedProject,
+  RecentProject,
+  saveProject,
+  openProject,
+  relinkSource,
+  autosaveProject,
+  getAutosave,
+  discardAutosave,
+  getRecentProjects,
+} from './lib/project';
+import { errorMessage, isRequiresTier } from './lib/entitlements';
+import { FolderOpen } from 'lucide-react';
 
 interface VideoMetadata {
   title: string;
@@ -55,8 +69,33 @@ interface AppSettings {
   preferred_quality: string | null;
 }
 
+/** A clip a clipme:// link asked for; it only runs once the user confirms it */
+interface DeepLinkClip {
+  url: string;
+  title: string;
+  start: number;
+  end: number;
+  quality: string;
+  format: string;
+}
+
+const clockTime = (seconds: number) => {
+  const mins = Math.floor(seconds / 60);
+  const secs = Math.floor(seconds % 60);
+  return `${mins}:${secs.toString().padStart(2, '0')}`;
+};
+
 const FREE_QUALITIES = ['720p', '480p', 'Audio Only'];
 
+// What makes two projects the same session, for telling whether there are unsaved changes
+const projectSnapshot = (project: Project) =>
+  JSON.stringify([
+    project.source.url,
+    project.segments.map((s) => [s.start, s.end, s.label ?? null]),
+    project.quality,
+    project.format,
+  ]);
+
 function App() {
   const [version, setVersion] = useState('');
   const [loadingVersion, setLoadingVersion] = useState(true);
@@ -113,6 +152,11 @@ function App() {
   // Multi-Clip Mode (Pro feature)
   const [multiClipEnabled, setMultiClipEnabled] = useState(false);
   const [segments, setSegments] = useState<ClipSegment[]>([]);
+
+  // Projects
+  const [projectPath, setProjectPath] = useState<string | null>(null);
+  const [recentProjects, setRecentProjects] = useState<RecentProject[]>([]);
+  const savedSnapshotRef = useRef<string | null>(null);
   
   const [toast, setToast] = useState<{ message: string; type: ToastType } | null>(null);
   const [showAuthModal, setShowAuthModal] = useState(false);
@@ -197,6 +241,19 @@ function App() {
         }).catch(console.error);
 
         invoke<string>('get_download_path').then(setDownloadPath).catch(console.error);
+        getRecentProjects().then(setRecentProjects).catch(console.error);
+        getAutosave().then(async (saved) => {
+          if (!saved) return;
+          const restore = await ask(
+            `Restore your unsaved session "${saved.project.source.title}"?`,
+            { title: 'Restore Session', kind: 'info', okLabel: 'Restore', cancelLabel: 'Discard' }
+          );
+          if (restore) {
+            applyProject(saved);
+          } else {
+            discardAutosave().catch(console.error);
+          }
+        }).catch(console.error);
         invoke<LicenseInfo>('get_license_status').then((license) => {
           setIsPro(license.is_pro);
           invoke<AppSettings>('get_app_settings').then((settings) => {
@@ -265,7 +322,87 @@ function App() {
     return () => { unlisten.then(fn => fn()); };
   }, []);
 
-  const handleUrlSubmit = async (inputUrl: string, isLocal: boolean = false) => {
+  // Any web page can open a clipme:// link, so each clip is confirmed first and
+  // then waits here until nothing else is downloading
+  const [linkClips, setLinkClips] = useState<DeepLinkClip[]>([]);
+
+  useEffect(() => {
+    const confirmLinkClips = async () => {
+      const clips = await invoke<DeepLinkClip[]>('take_deep_link_clips');
+      for (const clip of clips) {
+        const yes = await ask(
+          `A link asked to download "${clip.title}" (${clockTime(clip.start)} - ${clockTime(clip.end)}, ${clip.quality}) from ${clip.url}`,
+          { title: 'Download Clip?', kind: 'info', okLabel: 'Download', cancelLabel: 'Ignore' }
+        );
+        if (yes) setLinkClips((queue) => [...queue, clip]);
+      }
+    };
+    confirmLinkClips().catch(console.error);
+    const unlistenClip = listen('deep-link-clip', () => {
+      confirmLinkClips().catch(console.error);
+    });
+    const unlistenError = listen<string>('deep-link-error', (event) => {
+      showToast('Ignored clip link: ' + event.payload, 'error');
+    });
+
+    return () => {
+      unlistenClip.then(fn => fn());
+      unlistenError.then(fn => fn());
+    };
+  }, [showToast]);
+
+  useEffect(() => {
+    if (downloading || linkClips.length === 0) return;
+    const [clip, ...rest] = linkClips;
+    setLinkClips(rest);
+
+    const sessionId = Date.now();
+    downloadSessionRef.current = sessionId;
+    setDownloading(true);
+    setProgress({ percent: 0, speed: 'Starting', eta: '', downloaded: '', total: '', id: sessionId });
+    invoke<string>('download_clip', { ...clip, id: sessionId })
+      .then((path) => showToast('Download complete! Saved to ' + path, 'success'))
+      .catch((error) => {
+        if (isRequiresTier(error)) {
+          showToast(error.message, 'info');
+        } else {
+          showToast('Download failed: ' + errorMessage(error), 'error');
+        }
+      })
+      .finally(() => {
+        setDownloading(false);
+        downloadSessionRef.current = 0;
+        setProgress(null);
+      });
+  }, [downloading, linkClips, showToast]);
+
+  const buildProject = (): Project | null => {
+    if (!videoMeta || !url) return null;
+    return {
+      source: { url, title: videoMeta.title, duration: videoMeta.duration },
+      segments: multiClipEnabled && segments.length > 0
+        ? segments
+        : [{ id: 'clip-1', start: range[0], end: range[1] }],
+      quality: selectedQuality,
+      format: containerFormat,
+    };
+  };
+
+  // Autosave a moment after the session changes; a saved session needs no autosave
+  useEffect(() => {
+    const project = buildProject();
+    if (!project) return;
+    const timer = setTimeout(() => {
+      if (projectSnapshot(project) === savedSnapshotRef.current) {
+        discardAutosave().catch(console.error);
+      } else {
+        autosaveProject(project).catch(console.error);
+      }
+    }, 2000);
+    return () => clearTimeout(timer);
+  }, [videoMeta, url, range, segments, multiClipEnabled, selectedQuality, containerFormat]);
+
+  const handleUrlSubmit = async (inputUrl: string, isLocal: boolean = false): Promise<VideoMetadata | null> => {
     setLoading(true);
     showToast(`${isLocal ? 'Analyzing file' : 'Fetching video metadata'}...`, 'info');
     setVideoMeta(null);
@@ -291,14 +428,80 @@ function App() {
           setSelectedQuality(freeQuality);
         }
       }
+      return meta;
     } catch (error) {
       console.error(error);
       showToast('Error fetching video info: ' + error, 'error');
+      return null;
     } finally {
       setLoading(false);
     }
   };
 
+  const applyProject = async (opened: OpenedProject) => {
+    let { project } = opened;
+    if (opened.source.status === 'missing') {
+      const locate = await ask(
+        `The video for "${project.source.title}" wasn't found at:\n${project.source.url}\n\nLocate it now?`,
+        { title: 'Source Missing', kind: 'warning', okLabel: 'Locate...', cancelLabel: 'Cancel' }
+      );
+      if (!locate) return;
+      const relinked = await relinkSource(project.source);
+      if (!relinked) return;
+      if (!relinked.matches) {
+        showToast("That file doesn't match the original, so clip times may be off", 'info');
+      }
+      project = { ...project, source: relinked.source };
+    } else if (opened.source.status === 'relinked') {
+      showToast('The video moved and was found at its new location', 'info');
+    }
+
+    const isLocal = opened.source.status !== 'remote';
+    const meta = await handleUrlSubmit(project.source.url, isLocal);
+    if (!meta) return;
+    setProjectPath(opened.path);
+    setSelectedQuality(project.quality);
+    setContainerFormat(project.format);
+    if (project.segments.length > 1) {
+      setSegments(project.segments);
+      setMultiClipEnabled(true);
+    } else if (project.segments.length === 1) {
+      setMultiClipEnabled(false);
+      setRange([project.segments[0].start, project.segments[0].end]);
+    }
+    // A restored autosave still has unsaved changes
+    savedSnapshotRef.current = opened.path ? projectSnapshot(project) : null;
+  };
+
+  const handleOpenProject = async (path?: string) => {
+    try {
+      const opened = await openProject(path);
+      if (opened) await applyProject(opened);
+      getRecentProjects().then(setRecentProjects).catch(console.error);
+    } catch (error) {
+      showToast('Failed to open project: ' + error, 'error');
+    }
+  };
+
+  const handleSaveProject = async (saveAs: boolean) => {
+    const project = buildProject();
+    if (!project) {
+      showToast('Open a video before saving a project', 'info');
+      return;
+    }
+    try {
+      const path = await saveProject(project, saveAs ? null : projectPath);
+      if (!path) return;
+      setProjectPath(path);
+      savedSnapshotRef.current = projectSnapshot(project);
+      discardAutosave().catch(console.error);
+      getRecentProjects().then(setRecentProjects).catch(console.error);
+      showToast('Project saved', 'success');
+    } catch (error) {
+      showToast('Failed to save project: ' + error, 'error');
+    }
+  };
+
   const handleFileSelect = (path: string) => {
       handleUrlSubmit(path, true);
   };
@@ -362,23 +565,6 @@ function App() {
           id: sessionId 
         });
         showToast(`${results.length} clips exported to ${targetPath}`, 'success');
-        
-        // Save each clip to history
-        for (let i = 0; i < segments.length; i++) {
-          const seg = segments[i];
-          const historyItem = {
-            id: `${Date.now()}-${i}`,
-            title: `${videoMeta.title} (Clip ${i + 1})`,
-            url: url,
-            thumbnail: videoMeta.preview_url || undefined,
-            duration: seg.end - seg.start,
-            quality: selectedQuality,
-            format: containerFormat,
-            filePath: results[i] || `${targetPath}/${videoMeta.title}_clip${i + 1}.${containerFormat}`,
-            downloadedAt: new Date().toISOString(),
-          };
-          invoke('save_download_history', { item: historyItem }).catch(console.error);
-        }
       } else {
         // Single Clip Mode
         await invoke('download_clip', { 
@@ -391,28 +577,15 @@ function App() {
           id: sessionId 
         });
         showToast('Download complete! Saved to ' + targetPath, 'success');
-        
-        // Save to download history (Pro feature)
-        if (isPro) {
-          const historyItem = {
-            id: `${Date.now()}`,
-            title: videoMeta.title,
-            url: url,
-            thumbnail: videoMeta.preview_url || undefined,
-            duration: range[1] - range[0],
-            quality: selectedQuality,
-            format: containerFormat,
-            filePath: `${targetPath}/${videoMeta.title}_clip_${sessionId}.${containerFormat}`,
-            downloadedAt: new Date().toISOString(),
-          };
-          invoke('save_download_history', { item: historyItem }).catch(console.error);
-        }
       }
     } catch (error) {
       if (typeof error === 'string' && error.includes('cancelled')) {
         showToast('Download cancelled.', 'info');
+      } else if (isRequiresTier(error)) {
+        // The backend checks the verified license; the UI state may be stale
+        showToast(error.message, 'info');
       } else {
-        showToast('Download failed: ' + error, 'error');
+        showToast('Download failed: ' + errorMessage(error), 'error');
       }
     } finally {
       setDownloading(false);
@@ -527,7 +700,10 @@ function App() {
         preferredQuality={preferredQuality}
         onPreferredQualityChange={(quality) => {
           setPreferredQuality(quality);
-          invoke('save_app_settings', { settings: { preferred_quality: quality } }).catch(console.error);
+          // Merge so other saved settings are kept
+          invoke<AppSettings>('get_app_settings')
+            .then((settings) => invoke('save_app_settings', { settings: { ...settings, preferred_quality: quality } }))
+            .catch(console.error);
           if (isPro && quality) setSelectedQuality(quality);
         }}
       />
@@ -541,6 +717,8 @@ function App() {
             handleUrlSubmit(detectedUrl, false);
           }}
           onOpenHistory={() => setShowHistory(true)}
+          onSaveProject={handleSaveProject}
+          onOpenProject={handleOpenProject}
           showToast={showToast}
         />
       </div>
@@ -585,6 +763,24 @@ function App() {
           </div>
         </div>
 
+        {!videoMeta && recentProjects.length > 0 && (
+          <div className="recent-projects">
+            <h3>Recent Projects</h3>
+            {recentProjects.map((recent) => (
+              <button
+                key={recent.path}
+                className="recent-project"
+                onClick={() => handleOpenProject(recent.path)}
+                disabled={!recent.exists}
+                title={recent.exists ? recent.path : `Not found: ${recent.path}`}
+              >
+                <FolderOpen size={14} />
+                <span>{recent.title || recent.path.split(/[\\/]/).pop()}</span>
+              </button>
+            ))}
+          </div>
+        )}
+
         {/* File Drop Zone with Peeking UI */}
         <FileDropZone 
             onFileSelect={handleFileSelect} 
@@ -1,6 +1,7 @@
 import React, { useState, useEffect } from 'react';
 import { invoke } from '@tauri-apps/api/core';
-import { X, Folder, Trash2, Clock, Film } from 'lucide-react';
+import { errorMessage } from '../lib/entitlements';
+import { X, Folder, Trash2, Clock, Film, Search, RefreshCw, RotateCcw, Repeat, Share2 } from 'lucide-react';
 
 export interface DownloadHistoryItem {
   id: string;
@@ -13,8 +14,48 @@ export interface DownloadHistoryItem {
   filePath: string;
   fileSize?: number;
   downloadedAt: string;
+  startTime?: number;
+  endTime?: number;
+  status?: 'ok' | 'missing' | 'failed';
+  jobId?: string;
+  startedAt?: string;
+  elapsedMs?: number;
+  tools?: Record<string, string>;
+  error?: string;
+  errorCode?: string;
 }
 
+interface ReconcileReport {
+  checked: number;
+  missing: string[];
+  relocated: { id: string; from: string; to: string }[];
+}
+
+interface HistoryPage {
+  items: DownloadHistoryItem[];
+  total: number;
+  offset: number;
+  limit: number;
+}
+
+interface HistoryFilters {
+  qualities: string[];
+  formats: string[];
+  sites: string[];
+}
+
+interface FilterValues {
+  from: string;
+  to: string;
+  quality: string;
+  format: string;
+  site: string;
+}
+
+const NO_FILTERS: FilterValues = { from: '', to: '', quality: '', format: '', site: '' };
+
+const PAGE_SIZE = 50;
+
 interface DownloadHistoryProps {
   isOpen: boolean;
   onClose: () => void;
@@ -54,22 +95,54 @@ export const DownloadHistory: React.FC<DownloadHistoryProps> = ({
   showToast 
 }) => {
   const [history, setHistory] = useState<DownloadHistoryItem[]>([]);
+  const [total, setTotal] = useState(0);
+  const [search, setSearch] = useState('');
   const [loading, setLoading] = useState(true);
+  const [checking, setChecking] = useState(false);
+  const [recreating, setRecreating] = useState<string | null>(null);
+  const [selected, setSelected] = useState<Set<string>>(new Set());
+  const [options, setOptions] = useState<HistoryFilters>({ qualities: [], formats: [], sites: [] });
+  const [filters, setFilters] = useState<FilterValues>(NO_FILTERS);
+  const filtering = Object.values(filters).some(Boolean);
 
   useEffect(() => {
     if (isOpen && isPro) {
-      loadHistory();
+      invoke<HistoryFilters>('get_history_filters')
+        .then(setOptions)
+        .catch((error) => console.error('Failed to load history filters:', error));
     }
   }, [isOpen, isPro]);
 
-  const loadHistory = async () => {
-    setLoading(true);
+  useEffect(() => {
+    if (isOpen && isPro) {
+      loadHistory(0);
+    }
+  }, [isOpen, isPro, search, filters]);
+
+  const setFilter = (key: keyof FilterValues, value: string) => {
+    setFilters((prev) => ({ ...prev, [key]: value }));
+  };
+
+  const loadHistory = async (offset: number) => {
+    if (offset === 0) setLoading(true);
     try {
-      const items = await invoke<DownloadHistoryItem[]>('get_download_history');
-      setHistory(items);
+      const page = await invoke<HistoryPage>('get_download_history', {
+        query: {
+          search: search.trim() || undefined,
+          from: filters.from || undefined,
+          to: filters.to || undefined,
+          quality: filters.quality || undefined,
+          format: filters.format || undefined,
+          site: filters.site || undefined,
+          offset,
+          limit: PAGE_SIZE,
+        },
+      });
+      setHistory((prev) => (offset === 0 ? page.items : [...prev, ...page.items]));
+      setTotal(page.total);
     } catch (error) {
       console.error('Failed to load history:', error);
-      setHistory([]);
+      if (offset === 0) setHistory([]);
     } finally {
       setLoading(false);
     }
@@ -79,6 +152,8 @@ export const DownloadHistory: React.FC<DownloadHistoryProps> = ({
     try {
       await invoke('clear_download_history');
       setHistory([]);
+      setTotal(0);
+      setSelected(new Set());
       showToast('History cleared', 'success');
     } catch (error) {
       showToast('Failed to clear history', 'error');
@@ -89,7 +164,74 @@ export const DownloadHistory: React.FC<DownloadHistoryProps> = ({
     try {
       await invoke('open_file_location', { path: filePath });
     } catch (error) {
-      showToast('File not found', 'error');
+      showToast('File not found. Use "Check files" to look for it.', 'error');
+    }
+  };
+
+  const checkFiles = async () => {
+    setChecking(true);
+    try {
+      const report = await invoke<ReconcileReport>('reconcile_history');
+      const parts = [`${report.checked} checked`];
+      if (report.relocated.length) parts.push(`${report.relocated.length} found in new locations`);
+      if (report.missing.length) parts.push(`${report.missing.length} missing`);
+      showToast(parts.join(', '), report.missing.length ? 'info' : 'success');
+      await loadHistory(0);
+    } catch (error) {
+      showToast('Failed to check files: ' + error, 'error');
+    } finally {
+      setChecking(false);
+    }
+  };
+
+  const recreateClip = async (id: string) => {
+    setRecreating(id);
+    try {
+      const item = await invoke<DownloadHistoryItem>('recreate_history_clip', { id });
+      setHistory((prev) => prev.map((entry) => (entry.id === id ? item : entry)));
+      showToast('Clip re-created', 'success');
+    } catch (error) {
+      showToast('Failed to re-create clip: ' + errorMessage(error), 'error');
+    } finally {
+      setRecreating(null);
+    }
+  };
+
+  const rerunJob = async (id: string) => {
+    setRecreating(id);
+    try {
+      const outputs = await invoke<string[]>('rerun_job', { id });
+      showToast(`${outputs.length === 1 ? 'Clip' : `${outputs.length} clips`} exported`, 'success');
+      await loadHistory(0);
+    } catch (error) {
+      showToast('Job failed again: ' + errorMessage(error), 'error');
+      await loadHistory(0);
+    } finally {
+      setRecreating(null);
+    }
+  };
+
+  const toggleSelected = (id: string) => {
+    setSelected((prev) => {
+      const next = new Set(prev);
+      if (next.has(id)) next.delete(id);
+      else next.add(id);
+      return next;
+    });
+  };
+
+  // Exports the selected entries, or everything loaded when nothing is selected
+  const exportClips = async () => {
+    const ids = history.filter((item) => selected.size === 0 || selected.has(item.id)).map((item) => item.id);
+    try {
+      // The backend asks where to save and picks the format from the extension
+      const count = await invoke<number | null>('export_clip_list', {
+        source: { kind: 'history', ids },
+      });
+      if (count === null) return;
+      showToast(`Exported ${count} clip${count === 1 ? '' : 's'}`, 'success');
+    } catch (error) {
+      showToast('Export failed: ' + error, 'error');
     }
   };
 
@@ -104,6 +246,25 @@ export const DownloadHistory: React.FC<DownloadHistoryProps> = ({
             Download History
           </h2>
           <div className="history-header-actions">
+            {history.length > 0 && (
+              <button
+                className="history-clear-btn"
+                onClick={checkFiles}
+                disabled={checking}
+                title="Check files"
+              >
+                <RefreshCw size={16} className={checking ? 'spin' : undefined} />
+              </button>
+            )}
+            {history.length > 0 && (
+              <button
+                className="history-clear-btn"
+                onClick={exportClips}
+                title={selected.size ? `Export ${selected.size} selected` : 'Export clip list'}
+              >
+                <Share2 size={16} />
+              </button>
+            )}
             {history.length > 0 && (
               <button 
                 className="history-clear-btn"
@@ -119,6 +280,60 @@ export const DownloadHistory: React.FC<DownloadHistoryProps> = ({
           </div>
         </div>
 
+        {isPro && (
+          <div className="history-search">
+            <Search size={16} />
+            <input
+              type="text"
+              placeholder="Search by title or URL"
+              value={search}
+              onChange={(e) => setSearch(e.target.value)}
+            />
+          </div>
+        )}
+
+        {isPro && (
+          <div className="history-filters">
+            <input
+              type="date"
+              value={filters.from}
+              max={filters.to || undefined}
+              onChange={(e) => setFilter('from', e.target.value)}
+              title="From"
+            />
+            <input
+              type="date"
+              value={filters.to}
+              min={filters.from || undefined}
+              onChange={(e) => setFilter('to', e.target.value)}
+              title="To"
+            />
+            <select value={filters.quality} onChange={(e) => setFilter('quality', e.target.value)}>
+              <option value="">Any quality</option>
+              {options.qualities.map((quality) => (
+                <option key={quality} value={quality}>{quality}</option>
+              ))}
+            </select>
+            <select value={filters.format} onChange={(e) => setFilter('format', e.target.value)}>
+              <option value="">Any format</option>
+              {options.formats.map((format) => (
+                <option key={format} value={format}>{format.toUpperCase()}</option>
+              ))}
+            </select>
+            <select value={filters.site} onChange={(e) => setFilter('site', e.target.value)}>
+              <option value="">Any site</option>
+              {options.sites.map((site) => (
+                <option key={site} value={site}>{site === 'local' ? 'Local files' : site}</option>
+              ))}
+            </select>
+            {filtering && (
+              <button className="history-clear-btn" onClick={() => setFilters(NO_FILTERS)} title="Clear filters">
+                <X size={14} />
+              </button>
+            )}
+          </div>
+        )}
+
         <div className="history-content">
           {!isPro ? (
             <div className="history-pro-gate">
@@ -134,13 +349,24 @@ export const DownloadHistory: React.FC<DownloadHistoryProps> = ({
           ) : history.length === 0 ? (
             <div className="history-empty">
               <Film size={48} />
-              <h3>No downloads yet</h3>
-              <p>Your download history will appear here.</p>
+              <h3>{search || filtering ? 'No matches' : 'No downloads yet'}</h3>
+              <p>
+                {search || filtering
+                  ? 'Try a different search or filter.'
+                  : 'Your download history will appear here.'}
+              </p>
             </div>
           ) : (
             <div className="history-list">
               {history.map((item) => (
                 <div key={item.id} className="history-item">
+                  <input
+                    type="checkbox"
+                    className="history-select"
+                    checked={selected.has(item.id)}
+                    onChange={() => toggleSelected(item.id)}
+                    title="Select for export"
+                  />
                   <div 
                     className="history-thumbnail"
                     style={{ backgroundImage: item.thumbnail ? `url(${item.thumbnail})` : undefined }}
@@ -165,17 +391,50 @@ export const DownloadHistory: React.FC<DownloadHistoryProps> = ({
                     </div>
                     <div className="history-date">
                       {formatDate(item.downloadedAt)}
+                      {item.status === 'missing' && (
+                        <span className="history-missing">File missing</span>
+                      )}
+                      {item.status === 'failed' && (
+                        <span className="history-missing" title={item.error}>
+                          {item.errorCode === 'cancelled' ? 'Cancelled' : 'Failed'}
+                        </span>
+                      )}
                     </div>
                   </div>
-                  <button 
-                    className="history-open-btn"
-                    onClick={() => openFileLocation(item.filePath)}
-                    title="Open file location"
-                  >
-                    <Folder size={18} />
-                  </button>
+                  {item.status === 'failed' ? (
+                    <button
+                      className="history-open-btn"
+                      onClick={() => rerunJob(item.id)}
+                      disabled={recreating !== null}
+                      title="Run again"
+                    >
+                      <Repeat size={18} className={recreating === item.id ? 'spin' : undefined} />
+                    </button>
+                  ) : item.status === 'missing' ? (
+                    <button
+                      className="history-open-btn"
+                      onClick={() => recreateClip(item.id)}
+                      disabled={recreating !== null || item.startTime == null}
+                      title={item.startTime == null ? 'No clip range stored' : 'Re-create clip'}
+                    >
+                      <RotateCcw size={18} className={recreating === item.id ? 'spin' : undefined} />
+                    </button>
+                  ) : (
+                    <button 
+                      className="history-open-btn"
+                      onClick={() => openFileLocation(item.filePath)}
+                      title="Open file location"
+                    >
+                      <Folder size={18} />
+                    </button>
+                  )}
                 </div>
               ))}
+              {history.length < total && (
+                <button className="history-more-btn" onClick={() => loadHistory(history.length)}>
+                  Load more
+                </button>
+              )}
             </div>
           )}
         </div>
@@ -1,7 +1,7 @@
 import React, { useState, useEffect } from 'react';
 import { Upload, FileVideo } from 'lucide-react';
 import { getCurrentWebviewWindow } from '@tauri-apps/api/webviewWindow';
-import { open } from '@tauri-apps/plugin-dialog';
+import { invoke } from '@tauri-apps/api/core';
 
 interface FileDropZoneProps {
   onFileSelect: (path: string) => void;
@@ -41,14 +41,9 @@ export const FileDropZone: React.FC<FileDropZoneProps> = ({ onFileSelect, onErro
 
   const handleClick = async () => {
     try {
-        const selected = await open({
-            multiple: false,
-            filters: [{
-                name: 'Video',
-                extensions: ['mp4', 'mov', 'avi', 'mkv', 'webm']
-            }]
-        });
-        if (selected && typeof selected === 'string') {
+        // Picked through the backend so it knows the user chose this file
+        const selected = await invoke<string | null>('pick_local_video');
+        if (selected) {
             onFileSelect(selected);
         }
     } catch (err) {
@@ -16,6 +16,7 @@ export interface ClipSegment {
   id: string;
   start: number;
   end: number;
+  label?: string;
 }
 
 interface MultiClipTimelineProps {
@@ -2,7 +2,7 @@ import { useState, useEffect } from 'react';
 import { invoke } from '@tauri-apps/api/core';
 import { getVersion } from '@tauri-apps/api/app';
 import { check } from '@tauri-apps/plugin-updater';
-import { ask } from '@tauri-apps/plugin-dialog';
+import { ask, message } from '@tauri-apps/plugin-dialog';
 import { relaunch } from '@tauri-apps/plugin-process';
 import { useAuth } from '../contexts/AuthContext';
 import { account } from '../lib/appwrite';
@@ -12,8 +12,12 @@ interface LicenseInfo {
   is_pro: boolean;
   license_key: string | null;
   email: string | null;
+  expires_at?: number | null;
+  offline?: boolean;
 }
 
+const DEFAULT_FILENAME_TEMPLATE = '{title}_clip{n}_{timestamp}';
+
 interface SettingsPanelProps {
   isOpen: boolean;
   onClose: () => void;
@@ -38,14 +42,59 @@ export function SettingsPanel({
   const [isClearing, setIsClearing] = useState(false);
   const [version, setVersion] = useState('');
   const [isCheckingUpdate, setIsCheckingUpdate] = useState(false);
+  const [isExporting, setIsExporting] = useState(false);
+  const [filenameTemplate, setFilenameTemplate] = useState('');
+  const [collisionPolicy, setCollisionPolicy] = useState('suffix');
+  const [filenamePreview, setFilenamePreview] = useState('');
+  const [filenameError, setFilenameError] = useState('');
 
   useEffect(() => {
     if (isOpen) {
       invoke<LicenseInfo>('get_license_status').then(setLicenseInfo).catch(console.error);
       getVersion().then(setVersion).catch(console.error);
+      invoke<{ filename_template?: string | null; collision_policy?: string | null }>('get_app_settings')
+        .then((settings) => {
+          setFilenameTemplate(settings.filename_template || DEFAULT_FILENAME_TEMPLATE);
+          setCollisionPolicy(settings.collision_policy || 'suffix');
+        })
+        .catch(console.error);
     }
   }, [isOpen]);
 
+  useEffect(() => {
+    if (!filenameTemplate) return;
+    invoke<string>('preview_filename', { template: filenameTemplate })
+      .then((preview) => {
+        setFilenamePreview(preview);
+        setFilenameError('');
+      })
+      .catch((err) => setFilenameError(String(err)));
+  }, [filenameTemplate]);
+
+  const saveNamingSettings = async (template: string, policy: string) => {
+    try {
+      const settings = await invoke<Record<string, unknown>>('get_app_settings');
+      await invoke('save_app_settings', {
+        settings: { ...settings, filename_template: template, collision_policy: policy },
+      });
+      setFilenameError('');
+    } catch (err) {
+      setFilenameError(String(err));
+    }
+  };
+
+  const handleExportDiagnostics = async () => {
+    setIsExporting(true);
+    try {
+      // Logs, tool versions and settings with personal data removed
+      await invoke<string | null>('export_diagnostics');
+    } catch (err) {
+      await message(String(err), { title: 'Export Failed', kind: 'error' });
+    } finally {
+      setIsExporting(false);
+    }
+  };
+
   const handleCheckUpdate = async () => {
       setIsCheckingUpdate(true);
       try {
@@ -165,6 +214,42 @@ export function SettingsPanel({
                 <p className="settings-hint">Free version is limited to 720p. Upgrade to choose your quality.</p>
               )}
             </div>
+
+            <div className="settings-input-group">
+              <label className="settings-label">File Names</label>
+              <input
+                type="text"
+                className="settings-input"
+                value={filenameTemplate}
+                onChange={(e) => setFilenameTemplate(e.target.value)}
+                onBlur={() => saveNamingSettings(filenameTemplate, collisionPolicy)}
+                spellCheck={false}
+              />
+              <p className="settings-hint">
+                {filenameError || `e.g. ${filenamePreview}`}
+              </p>
+              <p className="settings-hint">
+                Variables: {'{title} {uploader} {id} {start} {end} {quality} {date} {timestamp} {segment_label} {n}'}. Use / for subfolders.
+              </p>
+            </div>
+
+            <div className="settings-input-group">
+              <label className="settings-label">If the File Exists</label>
+              <div className="select-wrapper">
+                <select
+                  className="settings-select"
+                  value={collisionPolicy}
+                  onChange={(e) => {
+                    setCollisionPolicy(e.target.value);
+                    saveNamingSettings(filenameTemplate, e.target.value);
+                  }}
+                >
+                  <option value="suffix">Add a number</option>
+                  <option value="overwrite">Overwrite</option>
+                  <option value="skip">Skip the clip</option>
+                </select>
+              </div>
+            </div>
           </div>
 
           <div className="settings-section">
@@ -206,6 +291,15 @@ export function SettingsPanel({
                 </div>
               )}
 
+              {licenseInfo?.offline && licenseInfo.expires_at && (
+                <div className="license-status-row">
+                  <span className="license-label">Offline</span>
+                  <span className="license-email">
+                    Connect before {new Date((licenseInfo.expires_at + 14 * 24 * 60 * 60) * 1000).toLocaleDateString()} to keep Pro
+                  </span>
+                </div>
+              )}
+
               {isPro && (
                 <div style={{ marginTop: '1rem', display: 'flex', justifyContent: 'flex-end' }}>
                   <button 
@@ -258,7 +352,16 @@ export function SettingsPanel({
                     <span className="license-label">Version</span>
                     <span className="license-key-display">v{version}</span>
                  </div>
-                 <div style={{ marginTop: '1rem', display: 'flex', justifyContent: 'flex-end' }}>
+                 <div style={{ marginTop: '1rem', display: 'flex', justifyContent: 'flex-end', gap: '0.5rem' }}>
+                    <button 
+                      className="settings-button"
+                      onClick={handleExportDiagnostics}
+                      disabled={isExporting}
+                      title="Save recent logs and settings, with personal data removed, for a bug report"
+                      style={{ fontSize: '0.8rem', padding: '0.6rem 1rem' }}
+                    >
+                      {isExporting ? 'Exporting...' : 'Export Diagnostics'}
+                    </button>
                     <button 
                       className="settings-button primary"
                       onClick={handleCheckUpdate}
@@ -1,6 +1,6 @@
 import React, { useState, useEffect, useCallback } from 'react';
 import { getCurrentWindow } from '@tauri-apps/api/window';
-import { Pin, PinOff, Clipboard, ClipboardCheck, Sun, Moon, Monitor, History } from 'lucide-react';
+import { Pin, PinOff, Clipboard, ClipboardCheck, Sun, Moon, Monitor, History, Save, FolderOpen } from 'lucide-react';
 
 type Theme = 'dark' | 'light' | 'system';
 
@@ -8,6 +8,8 @@ interface TitlebarActionsProps {
   isPro: boolean;
   onClipboardUrl?: (url: string) => void;
   onOpenHistory?: () => void;
+  onSaveProject?: (saveAs: boolean) => void;
+  onOpenProject?: () => void;
   showToast: (message: string, type: 'info' | 'success' | 'error') => void;
 }
 
@@ -35,6 +37,8 @@ export const TitlebarActions: React.FC<TitlebarActionsProps> = ({
   isPro, 
   onClipboardUrl,
   onOpenHistory,
+  onSaveProject,
+  onOpenProject,
   showToast 
 }) => {
   const [isAlwaysOnTop, setIsAlwaysOnTop] = useState(false);
@@ -176,6 +180,22 @@ export const TitlebarActions: React.FC<TitlebarActionsProps> = ({
         {getThemeIcon()}
       </button>
 
+      {/* Projects - Free for all */}
+      <button
+        className="titlebar-action-btn"
+        onClick={() => onOpenProject?.()}
+        title="Open Project"
+      >
+        <FolderOpen size={16} />
+      </button>
+      <button
+        className="titlebar-action-btn"
+        onClick={(e) => onSaveProject?.(e.shiftKey)}
+        title="Save Project (Shift+click to Save As)"
+      >
+        <Save size={16} />
+      </button>
+
       {/* Download History - Pro */}
       <button 
         className={`titlebar-action-btn ${!isPro ? 'pro-locked' : ''}`}
@@ -0,0 +1,19 @@
+export type Tier = 'free' | 'pro';
+export type Feature = 'multi_clip' | 'ultra_hd_quality' | 'batch';
+
+/** Error of a gated command when the license doesn't cover the feature */
+export interface RequiresTier {
+  code: 'requires_tier';
+  feature: Feature;
+  tier: Tier;
+  message: string;
+}
+
+export const isRequiresTier = (error: unknown): error is RequiresTier =>
+  typeof error === 'object' &&
+  error !== null &&
+  (error as { code?: unknown }).code === 'requires_tier';
+
+/** Text for a command error, whether it's a message or a structured error */
+export const errorMessage = (error: unknown): string =>
+  isRequiresTier(error) ? error.message : String(error);
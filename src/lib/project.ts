@@ -0,0 +1,59 @@
+import { invoke } from '@tauri-apps/api/core';
+import type { ClipSegment } from '../components/MultiClipTimeline';
+
+export interface ProjectSource {
+  url: string;
+  title: string;
+  duration?: number;
+  file_size?: number;
+  sha256?: string;
+  relative_path?: string;
+}
+
+export interface Project {
+  version?: number;
+  source: ProjectSource;
+  segments: ClipSegment[];
+  quality: string;
+  format: string;
+  segment_options?: Record<string, unknown>;
+  saved_at?: string;
+}
+
+export type SourceStatus =
+  | { status: 'remote' }
+  | { status: 'found' }
+  | { status: 'relinked'; from: string }
+  | { status: 'missing' };
+
+export interface OpenedProject {
+  path: string | null;
+  project: Project;
+  source: SourceStatus;
+}
+
+export interface RecentProject {
+  path: string;
+  title: string;
+  opened_at: string;
+  exists: boolean;
+}
+
+/** Returns the saved path, or null if the save dialog was cancelled */
+export const saveProject = (project: Project, path: string | null) =>
+  invoke<string | null>('save_project', { project, path });
+
+/** Opens through a file picker unless `path` comes from the recent list */
+export const openProject = (path?: string) =>
+  invoke<OpenedProject | null>('open_project', { path: path ?? null });
+
+export const relinkSource = (source: ProjectSource) =>
+  invoke<{ source: ProjectSource; matches: boolean } | null>('relink_project_source', { source });
+
+export const autosaveProject = (project: Project) => invoke('autosave_project', { project });
+
+export const getAutosave = () => invoke<OpenedProject | null>('get_autosave');
+
+export const discardAutosave = () => invoke('discard_autosave');
+
+export const getRecentProjects = () => invoke<RecentProject[]>('get_recent_projects');